@@ -0,0 +1,43 @@
+//! Compares alternative algorithms for the same puzzle. Run with
+//! `cargo bench`.
+extern crate advent_of_code_2018;
+extern crate euclid;
+
+use advent_of_code_2018::bench;
+use advent_of_code_2018::december10;
+use advent_of_code_2018::december11;
+use advent_of_code_2018::input::Input;
+use euclid::{Point2D, Rect, Size2D};
+use std::time::Duration;
+
+const RUNS: usize = 100;
+
+fn budget() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn main() {
+    let input = Input::for_day(10, None).unwrap();
+    let sky = december10::parse(&input.lines()).unwrap();
+    let naive = bench::measure(RUNS, budget(), || {
+        december10::conserve_momentum(&mut sky.clone())
+    });
+    let binary = bench::measure(RUNS, budget(), || {
+        december10::conserve_momentum_bsearch(&mut sky.clone())
+    });
+    println!("december10 search_naive   {}", naive);
+    println!("december10 search_binary  {}", binary);
+    println!(
+        "december10 the binary search is {:.0}x faster",
+        naive.median.as_secs_f64() / binary.median.as_secs_f64()
+    );
+
+    let table = bench::measure(RUNS, budget(), || {
+        december11::max_power_summed_area_table(
+            Rect::new(Point2D::new(1, 1), Size2D::new(300, 300)),
+            &(1..=300).collect::<Vec<_>>(),
+            5153,
+        )
+    });
+    println!("december11 summed_area_table {}", table);
+}
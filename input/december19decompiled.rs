@@ -0,0 +1,100 @@
+/// The ElfCode program as a function from the registers it starts with to
+/// those it halts with, decompiled by `aoc decompile`.
+pub fn run(mut r: [i64; 6]) -> [i64; 6] {
+    r[4] += 2; // 17
+    r[4] *= r[4]; // 18
+    r[4] *= 19; // 19
+    r[4] *= 11; // 20
+    r[1] += 6; // 21
+    r[1] *= 22; // 22
+    r[1] += 10; // 23
+    r[4] += r[1]; // 24
+    match r[0] {
+        // 25
+        0 | 9 => {}
+        1 => {
+            r[1] = 27; // 27
+            r[1] *= 28; // 28
+            r[1] += 29; // 29
+            r[1] *= 30; // 30
+            r[1] *= 14; // 31
+            r[1] *= 32; // 32
+            r[4] += r[1]; // 33
+            r[0] = 0; // 34
+        }
+        2 => {
+            r[1] *= 28; // 28
+            r[1] += 29; // 29
+            r[1] *= 30; // 30
+            r[1] *= 14; // 31
+            r[1] *= 32; // 32
+            r[4] += r[1]; // 33
+            r[0] = 0; // 34
+        }
+        3 => {
+            r[1] += 29; // 29
+            r[1] *= 30; // 30
+            r[1] *= 14; // 31
+            r[1] *= 32; // 32
+            r[4] += r[1]; // 33
+            r[0] = 0; // 34
+        }
+        4 => {
+            r[1] *= 30; // 30
+            r[1] *= 14; // 31
+            r[1] *= 32; // 32
+            r[4] += r[1]; // 33
+            r[0] = 0; // 34
+        }
+        5 => {
+            r[1] *= 14; // 31
+            r[1] *= 32; // 32
+            r[4] += r[1]; // 33
+            r[0] = 0; // 34
+        }
+        6 => {
+            r[1] *= 32; // 32
+            r[4] += r[1]; // 33
+            r[0] = 0; // 34
+        }
+        7 => {
+            r[4] += r[1]; // 33
+            r[0] = 0; // 34
+        }
+        8 => {
+            r[0] = 0; // 34
+        }
+        _ => {
+            r[3] = 25 + r[0];
+            return r;
+        }
+    }
+    r[5] = 1; // 01
+    loop {
+        // 02 - 14
+        r[2] = 1; // 02
+        loop {
+            // 03 - 10
+            r[1] = r[5] * r[2]; // 03
+            r[1] = (r[1] == r[4]) as i64; // 04
+            if r[1] == 1 {
+                // 05
+                r[0] += r[5]; // 07
+            }
+            r[2] += 1; // 08
+            r[1] = (r[2] > r[4]) as i64; // 09
+            if r[1] == 1 {
+                // 10
+                break;
+            }
+        }
+        r[5] += 1; // 12
+        r[1] = (r[5] > r[4]) as i64; // 13
+        if r[1] == 1 {
+            // 14
+            break;
+        }
+    }
+    r[3] = 256;
+    r
+}
@@ -0,0 +1,52 @@
+/// The ElfCode program as a function from the registers it starts with to
+/// those it halts with, decompiled by `aoc decompile`.
+pub fn run(mut r: [i64; 6]) -> [i64; 6] {
+    r[5] = 123; // 00
+    loop {
+        // 01 - 03
+        r[5] &= 0b111001000; // 01
+        r[5] = (r[5] == 72) as i64; // 02
+        if r[5] == 1 {
+            // 03
+            break;
+        }
+    }
+    r[5] = 0; // 05
+    loop {
+        // 06 - 29
+        r[4] = r[5] | 0b10000000000000000; // 06
+        r[5] = 15466939; // 07
+        loop {
+            // 08 - 26
+            r[3] = r[4] & 0b11111111; // 08
+            r[5] += r[3]; // 09
+            r[5] &= 0b111111111111111111111111; // 10
+            r[5] *= 65899; // 11
+            r[5] &= 0b111111111111111111111111; // 12
+            r[3] = (256 > r[4]) as i64; // 13
+            if r[3] == 1 {
+                // 14
+                break;
+            }
+            r[3] = 0; // 17
+            loop {
+                // 18 - 24
+                r[1] = r[3] + 1; // 18
+                r[1] *= 256; // 19
+                r[1] = (r[1] > r[4]) as i64; // 20
+                if r[1] == 1 {
+                    // 21
+                    break;
+                }
+                r[3] += 1; // 24
+            }
+            r[4] = r[3]; // 26
+        }
+        r[3] = (r[5] == r[0]) as i64; // 28
+        if r[3] == 1 {
+            // 29
+            r[2] = 30;
+            return r;
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! Benchmarks that run on stable Rust: solve a day repeatedly, and summarise
+//! how long parsing and each part took.
+use days;
+use error::Error;
+use input::Input;
+use parse;
+use solution::{Part, Result};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::hint;
+use std::time::{Duration, Instant};
+
+/// Summary statistics of repeated measurements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub runs: usize,
+    pub mean: Duration,
+    pub median: Duration,
+    /// The standard deviation.
+    pub spread: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl Stats {
+    /// Summarise the given samples, or `None` if there are none.
+    pub fn from_samples(samples: &[Duration]) -> Option<Stats> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+        let median = if n.is_multiple_of(2) {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2
+        } else {
+            sorted[n / 2]
+        };
+
+        let secs = sorted.iter().map(Duration::as_secs_f64).collect::<Vec<_>>();
+        let mean = secs.iter().sum::<f64>() / n as f64;
+        let variance =
+            secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n as f64;
+
+        Some(Stats {
+            runs: n,
+            mean: Duration::from_secs_f64(mean),
+            median,
+            spread: Duration::from_secs_f64(variance.sqrt()),
+            min: sorted[0],
+            max: sorted[n - 1],
+        })
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "median {:.3?}, mean {:.3?} ± {:.3?} ({:.3?}..{:.3?}, {} runs)",
+            self.median, self.mean, self.spread, self.min, self.max, self.runs
+        )
+    }
+}
+
+/// Run `f` at least once and at most `runs` times, stopping early once `budget`
+/// is spent, and summarise how long each run took.
+pub fn measure<F, T>(runs: usize, budget: Duration, mut f: F) -> Stats
+where
+    F: FnMut() -> T,
+{
+    let start = Instant::now();
+    let mut samples = Vec::new();
+
+    while samples.is_empty()
+        || (samples.len() < runs && start.elapsed() < budget)
+    {
+        let run = Instant::now();
+        hint::black_box(f());
+        samples.push(run.elapsed());
+    }
+
+    Stats::from_samples(&samples).expect("at least one run")
+}
+
+/// The phases of a solution that are timed separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    Parse,
+    Part1,
+    Part2,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Parse => "parse",
+            Phase::Part1 => "part1",
+            Phase::Part2 => "part2",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Phase> {
+        match name {
+            "parse" => Some(Phase::Parse),
+            "part1" => Some(Phase::Part1),
+            "part2" => Some(Phase::Part2),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The timings of the phases of one day over repeated runs.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub day: u32,
+    pub phases: Vec<(Phase, Stats)>,
+}
+
+/// Solve a day repeatedly with the given puzzle parameters, as `measure`
+/// does, timing every phase. The input is parsed once, as what it is parsed
+/// into is cached with it, so parsing is timed once. The parts are timed on
+/// the runs after a first one, which warms up the caches.
+pub fn bench_day(
+    day: u32,
+    input: &Input,
+    part: Part,
+    params: &[(String, String)],
+    runs: usize,
+    budget: Duration,
+) -> Result<Report> {
+    let solver = days::solver(day).ok_or_else(|| {
+        Error::Param(format!("There is no puzzle on day {}", day))
+    })?;
+    let (_, first) = solver(input, part, params)?;
+
+    let start = Instant::now();
+    let mut samples: BTreeMap<Phase, Vec<Duration>> = BTreeMap::new();
+    samples.insert(Phase::Parse, vec![first.parse]);
+    let mut done = 0;
+    while done == 0 || (done < runs && start.elapsed() < budget) {
+        let (_, timings) = solver(input, part, params)?;
+        done += 1;
+        if let Some(part1) = timings.part1 {
+            samples.entry(Phase::Part1).or_default().push(part1);
+        }
+        if let Some(part2) = timings.part2 {
+            samples.entry(Phase::Part2).or_default().push(part2);
+        }
+    }
+
+    Ok(Report {
+        day,
+        phases: samples
+            .iter()
+            .filter_map(|(&phase, samples)| {
+                Some((phase, Stats::from_samples(samples)?))
+            })
+            .collect(),
+    })
+}
+
+/// Saved median timings per day and phase, to compare later runs against.
+///
+/// Baselines are stored as text, one `<day> <phase> <median in ns>` per line.
+#[derive(Debug, Default, Clone)]
+pub struct Baseline {
+    medians: BTreeMap<(u32, Phase), Duration>,
+}
+
+impl Baseline {
+    pub fn parse(lines: &[&str]) -> Result<Baseline> {
+        let mut medians = BTreeMap::new();
+
+        for line in parse::lines(lines).iter().filter(|l| !l.is_empty()) {
+            let parts = line.split(" ");
+            if parts.len() != 3 {
+                return Err(line.error("expected `<day> <phase> <median>`"));
+            }
+
+            let day = parts[0].parse()?;
+            let phase = Phase::from_name(parts[1].text).ok_or_else(|| {
+                parts[1].error(format!("unknown phase `{}`", parts[1].text))
+            })?;
+            let nanos = parts[2].parse()?;
+            medians.insert((day, phase), Duration::from_nanos(nanos));
+        }
+
+        Ok(Baseline { medians })
+    }
+
+    /// Record the medians of a report, replacing earlier ones of that day.
+    pub fn record(&mut self, report: &Report) {
+        for &(phase, stats) in &report.phases {
+            self.medians.insert((report.day, phase), stats.median);
+        }
+    }
+
+    pub fn median(&self, day: u32, phase: Phase) -> Option<Duration> {
+        self.medians.get(&(day, phase)).cloned()
+    }
+
+    /// The relative change of a median compared to the baseline, e.g. `-0.25`
+    /// if it became 25% faster.
+    pub fn change(
+        &self,
+        day: u32,
+        phase: Phase,
+        median: Duration,
+    ) -> Option<f64> {
+        let baseline = self.median(day, phase)?.as_secs_f64();
+        if baseline == 0.0 {
+            return None;
+        }
+
+        Some(median.as_secs_f64() / baseline - 1.0)
+    }
+}
+
+impl Display for Baseline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for ((day, phase), median) in &self.medians {
+            writeln!(f, "{} {} {}", day, phase, median.as_nanos())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use input::Input;
+
+    #[test]
+    fn stats() {
+        let samples = [4, 1, 3, 2]
+            .iter()
+            .map(|&ms| Duration::from_millis(ms))
+            .collect::<Vec<_>>();
+        let stats = Stats::from_samples(&samples).unwrap();
+        assert_eq!(stats.runs, 4);
+        assert_eq!(stats.median, Duration::from_micros(2500));
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.max, Duration::from_millis(4));
+        assert_eq!(stats.mean.as_micros(), 2500);
+        assert_eq!(stats.spread.as_micros(), 1118);
+        assert!(Stats::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn baseline() {
+        let baseline =
+            Baseline::parse(&["3 parse 1000", "3 part1 2000"]).unwrap();
+        assert_eq!(baseline.to_string(), "3 parse 1000\n3 part1 2000\n");
+        assert_eq!(
+            baseline.change(3, Phase::Part1, Duration::from_nanos(1500)),
+            Some(-0.25)
+        );
+        assert_eq!(
+            baseline.change(3, Phase::Part2, Duration::from_nanos(1500)),
+            None
+        );
+        assert!(Baseline::parse(&["3 part3 1000"]).is_err());
+    }
+
+    /// The puzzle parameters reach the solver, which rejects those it does
+    /// not know.
+    #[test]
+    fn params() {
+        let input =
+            Input::for_day(7, Some("input/december07test.txt")).unwrap();
+        let bench = |params: &[(&str, &str)]| {
+            let params = params
+                .iter()
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect::<Vec<_>>();
+            let budget = Duration::from_secs(1);
+            bench_day(7, &input, Part::Both, &params, 1, budget)
+        };
+        assert!(bench(&[("workers", "2"), ("base_duration", "0")]).is_ok());
+        assert!(bench(&[("workers", "many")]).is_err());
+        assert!(bench(&[("elves", "2")]).is_err());
+    }
+}
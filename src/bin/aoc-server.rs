@@ -0,0 +1,65 @@
+extern crate advent_of_code_2018;
+use advent_of_code_2018::server;
+use std::io::{self, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+static USAGE: &str = "\
+Usage: aoc-server [--address <host:port>] [--timeout <seconds>]
+
+Serves the solutions over HTTP on --address (default 127.0.0.1:2018); port 0
+picks a free port. The address is printed once the server is listening.
+Solutions taking longer than --timeout (default 60) are cancelled, and
+answered with status 503.
+
+  POST /day/<n>/part/<1|2|both>[?<name>=<value>&...]
+      Solves the puzzle input in the body, with the given parameters, and
+      answers with the JSON of `aoc run --json`.
+  POST /day/<n>/render[?scale=<n>]
+      Draws the puzzle state of days 10, 13, 15, 17 and 18 as a PNG image.";
+
+fn parse_args(args: &[String]) -> Result<(String, Duration), String> {
+    let mut address = "127.0.0.1:2018".to_owned();
+    let mut timeout = Duration::from_secs(60);
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--address" => {
+                address =
+                    rest.next().ok_or("Missing value for --address")?.clone()
+            }
+            "--timeout" => {
+                let secs = rest
+                    .next()
+                    .ok_or("Missing value for --timeout")?
+                    .parse::<f64>()
+                    .map_err(|_| "Expected seconds")?;
+                timeout = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| "Expected a positive number of seconds")?;
+            }
+            _ => return Err(format!("Unknown argument `{}`", arg)),
+        }
+    }
+    Ok((address, timeout))
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (address, timeout) = match parse_args(&args) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let result = TcpListener::bind(&address).and_then(|listener| {
+        println!("Listening on http://{}", listener.local_addr()?);
+        io::stdout().flush()?;
+        server::serve(listener, timeout)
+    });
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
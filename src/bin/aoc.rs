@@ -0,0 +1,1037 @@
+extern crate advent_of_code_2018;
+use advent_of_code_2018::bench::{self, Baseline, Report};
+use advent_of_code_2018::days;
+use advent_of_code_2018::debugger::{self, Debugger};
+use advent_of_code_2018::decompile;
+use advent_of_code_2018::disasm;
+use advent_of_code_2018::error::Error;
+use advent_of_code_2018::generate;
+use advent_of_code_2018::input::Input;
+use advent_of_code_2018::json;
+use advent_of_code_2018::pool::{self, Outcome};
+use advent_of_code_2018::profile;
+use advent_of_code_2018::record;
+use advent_of_code_2018::solution::{Answers, Part, Result, Timings};
+use advent_of_code_2018::store::{self, Store};
+use advent_of_code_2018::trace;
+use advent_of_code_2018::verify;
+use advent_of_code_2018::view;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::time::{Duration, Instant};
+
+static USAGE: &str = "\
+Usage: aoc run <day> [--input <path|->] [--part <1|2|both>]
+                     [--param <name>=<value>]... [--json]
+                     [--render <path>] [--record <path>] [--scale <n>]
+                     [--view] [--skip <n>] [--delay <ms>]
+       aoc run all [--part <1|2|both>] [--json] [--jobs <n>]
+                   [--timeout <seconds>]
+       aoc run <day|all> --verify [--input <path|->] [--cases <n>]
+       aoc bench <day|all> [--input <path|->] [--part <1|2|both>]
+                           [--param <name>=<value>]...
+                           [--runs <n>] [--time <seconds>]
+                           [--baseline <path>] [--save <path>]
+       aoc profile <day|all> [--input <path|->] [--part <1|2|both>]
+                             [--param <name>=<value>]... [--trace <path>]
+       aoc generate <day> [--seed <n>] [--size <n>]
+       aoc disasm <day> [--input <path|->]
+       aoc decompile <day> [--input <path|->]
+       aoc debug <day> [--input <path>]
+       aoc trace <day> [--input <path|->] [--set r<n>=<value>]...
+                       [--at <ip>]... [--steps <n>] [--json]
+       aoc check <day|all> [--part <1|2|both>] [--store <path>]
+       aoc confirm <day> <1|2> [--answer <answer>] [--too-high <n>]
+                               [--too-low <n>] [--store <path>]
+
+Runs the solution of a day (1-25) against its puzzle input. The input is read
+from input/decemberNN.txt unless another file is given; `-` reads stdin. If
+there is no such file, the input embedded at compile time is used.
+Parameters from the puzzle text that are not part of the input, such as the
+number of workers on day 7, can be overridden with --param.
+`aoc run all` runs every day and prints a summary table in day order, with
+the time every day took, their total and the time it all took. --jobs runs
+that many days at once (default 1), and --timeout cancels days that take
+longer than that.
+With --json, a JSON document is printed per day instead, on a line of its own,
+with the answers, timings in milliseconds and diagnostics of the solution.
+With --render, the puzzle state of days 10, 13, 15, 17 and 18 is also saved as
+a .png or .ppm image, with every tile drawn as a square of --scale pixels
+(default 4).
+With --record, the simulations of days 12, 13, 15, 17, 18 and 24 are recorded
+while solving, and saved as an animated .gif or as numbered .ppm images
+(out.ppm becomes out-00000.ppm, out-00001.ppm, ...). --skip leaves out that
+many ticks after every recorded one, and --delay sets the time every frame is
+shown (default 100ms).
+With --view, the recorded simulation is played in the terminal after the
+answers are printed: space plays or pauses, n and p step, + and - change the
+speed, the arrow keys or hjkl scroll, and q quits. When stdout is not a
+terminal, the frames are printed one after another instead.
+With --verify, the days that have two algorithms for the same answer (3, 10,
+19 and 21) run both on the puzzle input and on --cases generated inputs
+(default 20) instead of solving, and report the fewest lines of the first
+input found that they disagree on.
+
+`aoc bench` solves days repeatedly, up to --runs times (default 10) or until
+--time seconds (default 5) are spent per day, and reports the median, mean,
+standard deviation and range of the parse, part 1 and part 2 timings. The
+input is parsed once, and the parts are solved from it every run. With
+--baseline, medians are compared against those saved earlier with --save.
+Examples are benchmarked with their own --param, as with `aoc run`.
+
+`aoc profile` solves days once, and reports the time and the peak memory
+allocated of parsing, each part and the spans solutions mark within them,
+such as every combat on day 15, with the slowest first. --trace also saves
+them as a Chrome trace-event JSON file, for trace viewers such as Perfetto.
+
+`aoc generate` prints a random input for a day, which can be solved with
+`aoc run <day> --input -`. The same --seed (default 0) always gives the same
+input; --size (default 50) sets how large it is, such as the number of lines
+or the width of a map.
+
+`aoc disasm` lists the ElfCode program of day 19 or 21 with every instruction
+in pseudocode, and arrows from the jumps to the instructions they go to.
+`aoc decompile` prints it as a Rust function instead, with its jumps turned
+into `if`s, `loop`s and `match`es.
+`aoc debug` runs it in a debugger that reads commands from stdin, such as
+`break 28`, `watch r5`, `set r0 = 1`, `step 10`, `continue` and `print`;
+`help` lists them all.
+`aoc trace` runs it with registers set by --set (default 0) for up to --steps
+instructions (default 10000000), and reports its hottest instructions and the
+jumps back that make its loops. --at takes snapshots of the registers before
+an instruction runs, and stops the program once it comes back there with
+registers it had before. With --json, the profile is printed as JSON.
+
+`aoc check` solves days on their puzzle inputs and compares the answers with
+those confirmed in input/answers.toml, or the --store file. It fails if any
+answer is wrong: not the confirmed one, or at least as high as an answer the
+website said was too high, or no higher than one it said was too low.
+`aoc confirm` records the answer to a part once the website has accepted it:
+the answer the day gives, or the one given with --answer. --too-high and
+--too-low record the hint the website gave for a wrong answer instead.";
+
+enum Days {
+    One(u32),
+    All,
+}
+
+struct BenchArgs {
+    runs: usize,
+    time: Duration,
+    baseline: Option<String>,
+    save: Option<String>,
+}
+
+struct RunArgs {
+    json: bool,
+    render: Option<String>,
+    record: Option<String>,
+    view: bool,
+    verify: bool,
+    cases: usize,
+    pool: pool::Options,
+    recording: record::Options,
+    scale: usize,
+}
+
+struct TraceArgs {
+    options: trace::Options,
+    registers: Vec<(usize, i64)>,
+    json: bool,
+}
+
+struct ConfirmArgs {
+    part: u32,
+    store: String,
+    answer: Option<String>,
+    too_high: Option<i64>,
+    too_low: Option<i64>,
+}
+
+enum Command {
+    Run(RunArgs),
+    Bench(BenchArgs),
+    Profile { trace: Option<String> },
+    Generate(generate::Options),
+    Disasm,
+    Decompile,
+    Debug,
+    Trace(TraceArgs),
+    Check { store: String },
+    Confirm(ConfirmArgs),
+}
+
+struct Args {
+    command: Command,
+    days: Days,
+    input: Option<String>,
+    part: Part,
+    params: Vec<(String, String)>,
+}
+
+fn parse_args(args: &[String]) -> std::result::Result<Args, String> {
+    let mut command = match args.first().map(String::as_str) {
+        Some("run") => Command::Run(RunArgs {
+            json: false,
+            render: None,
+            record: None,
+            view: false,
+            verify: false,
+            cases: 20,
+            pool: pool::Options::default(),
+            recording: record::Options::default(),
+            scale: 4,
+        }),
+        Some("bench") => Command::Bench(BenchArgs {
+            runs: 10,
+            time: Duration::from_secs(5),
+            baseline: None,
+            save: None,
+        }),
+        Some("profile") => Command::Profile { trace: None },
+        Some("generate") => Command::Generate(generate::Options::default()),
+        Some("disasm") => Command::Disasm,
+        Some("decompile") => Command::Decompile,
+        Some("debug") => Command::Debug,
+        Some("trace") => Command::Trace(TraceArgs {
+            options: trace::Options::default(),
+            registers: Vec::new(),
+            json: false,
+        }),
+        Some("check") => Command::Check {
+            store: store::DEFAULT_PATH.to_owned(),
+        },
+        Some("confirm") => Command::Confirm(ConfirmArgs {
+            part: 0,
+            store: store::DEFAULT_PATH.to_owned(),
+            answer: None,
+            too_high: None,
+            too_low: None,
+        }),
+        _ => return Err("Expected a command".to_owned()),
+    };
+
+    let days = match args.get(1).map(String::as_str) {
+        Some("all") => Days::All,
+        Some(day) => match day.parse::<u32>() {
+            Ok(day) if days::solver(day).is_some() => Days::One(day),
+            _ => return Err(format!("There is no puzzle on day `{}`", day)),
+        },
+        None => return Err("Expected a day".to_owned()),
+    };
+
+    let mut input = None;
+    let mut part = Part::Both;
+    let mut params = Vec::new();
+    let mut rest = args[2..].iter();
+    if let Command::Confirm(ref mut confirm) = command {
+        confirm.part = match rest.next().map(String::as_str) {
+            Some("1") => 1,
+            Some("2") => 2,
+            _ => return Err("Expected a part, 1 or 2".to_owned()),
+        };
+    }
+    while let Some(arg) = rest.next() {
+        if arg == "--json" || arg == "--view" || arg == "--verify" {
+            match command {
+                Command::Run(ref mut run) if arg == "--json" => run.json = true,
+                Command::Run(ref mut run) if arg == "--view" => run.view = true,
+                Command::Run(ref mut run) => run.verify = true,
+                Command::Trace(ref mut trace) if arg == "--json" => {
+                    trace.json = true
+                }
+                _ if arg == "--json" => {
+                    return Err(
+                        "--json can only be used with `run` and `trace`".into(),
+                    )
+                }
+                _ => {
+                    return Err(format!("{} can only be used with `run`", arg))
+                }
+            }
+            continue;
+        }
+
+        let value = rest.next().ok_or(format!("Missing value for {}", arg));
+        match (&mut command, arg.as_str()) {
+            (Command::Generate(options), "--seed") => {
+                options.seed =
+                    value?.parse().map_err(|_| "Expected a number as seed")?
+            }
+            (Command::Generate(options), "--size") => {
+                options.size = value?.parse().map_err(|_| "Expected a size")?
+            }
+            (Command::Generate(_), _) => {
+                return Err(format!("Unknown argument `{}`", arg))
+            }
+            (Command::Disasm, arg)
+            | (Command::Decompile, arg)
+            | (Command::Debug, arg)
+                if arg != "--input" =>
+            {
+                return Err(format!("Unknown argument `{}`", arg))
+            }
+            (Command::Trace(trace), "--at") => trace
+                .options
+                .at
+                .push(value?.parse().map_err(|_| "Expected an instruction")?),
+            (Command::Trace(trace), "--set") => {
+                let value = value?;
+                let mut parts = value.splitn(2, '=');
+                let register = parts
+                    .next()
+                    .and_then(|register| register.trim().strip_prefix('r'))
+                    .and_then(|register| register.parse().ok())
+                    .filter(|&register| register < 6)
+                    .ok_or("Expected a register from r0 to r5")?;
+                let value = parts
+                    .next()
+                    .and_then(|value| value.trim().parse().ok())
+                    .ok_or("Expected r<n>=<value>")?;
+                trace.registers.push((register, value));
+            }
+            (Command::Trace(trace), "--steps") => {
+                trace.options.steps =
+                    value?.parse().map_err(|_| "Expected a number of steps")?
+            }
+            (Command::Trace(_), arg) if arg != "--input" => {
+                return Err(format!("Unknown argument `{}`", arg))
+            }
+            (Command::Check { store }, "--store")
+            | (Command::Confirm(ConfirmArgs { store, .. }), "--store") => {
+                *store = value?.clone()
+            }
+            (Command::Confirm(confirm), "--answer") => {
+                confirm.answer = Some(value?.clone())
+            }
+            (Command::Confirm(confirm), "--too-high") => {
+                confirm.too_high =
+                    Some(value?.parse().map_err(|_| "Expected a number")?)
+            }
+            (Command::Confirm(confirm), "--too-low") => {
+                confirm.too_low =
+                    Some(value?.parse().map_err(|_| "Expected a number")?)
+            }
+            (Command::Confirm(_), _) => {
+                return Err(format!("Unknown argument `{}`", arg))
+            }
+            (_, "--input") => input = Some(value?.clone()),
+            (_, "--part") => part = value?.parse()?,
+            (Command::Bench(bench), "--runs") => {
+                bench.runs =
+                    value?.parse().map_err(|_| "Expected a number of runs")?
+            }
+            (Command::Bench(bench), "--time") => {
+                let secs =
+                    value?.parse::<f64>().map_err(|_| "Expected seconds")?;
+                bench.time = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| "Expected a positive number of seconds")?;
+            }
+            (Command::Bench(bench), "--baseline") => {
+                bench.baseline = Some(value?.clone())
+            }
+            (Command::Bench(bench), "--save") => {
+                bench.save = Some(value?.clone())
+            }
+            (Command::Run(run), "--render") => {
+                run.render = Some(value?.clone())
+            }
+            (Command::Run(run), "--record") => {
+                run.record = Some(value?.clone())
+            }
+            (Command::Run(run), "--skip") => {
+                run.recording.skip =
+                    value?.parse().map_err(|_| "Expected a number of ticks")?
+            }
+            (Command::Run(run), "--delay") => {
+                let millis =
+                    value?.parse().map_err(|_| "Expected milliseconds")?;
+                run.recording.delay = Duration::from_millis(millis);
+            }
+            (Command::Run(run), "--cases") => {
+                run.cases =
+                    value?.parse().map_err(|_| "Expected a number of cases")?
+            }
+            (Command::Run(run), "--jobs") => {
+                run.pool.jobs = match value?.parse() {
+                    Ok(jobs) if jobs > 0 => jobs,
+                    _ => {
+                        return Err("Expected a positive number of jobs".into())
+                    }
+                }
+            }
+            (Command::Run(run), "--timeout") => {
+                let secs =
+                    value?.parse::<f64>().map_err(|_| "Expected seconds")?;
+                run.pool.timeout =
+                    Some(Duration::try_from_secs_f64(secs).map_err(|_| {
+                        "Expected a positive number of seconds"
+                    })?);
+            }
+            (Command::Run(run), "--scale") => {
+                run.scale = match value?.parse() {
+                    Ok(scale) if scale > 0 => scale,
+                    _ => return Err("Expected a positive scale".into()),
+                }
+            }
+            (Command::Profile { trace }, "--trace") => {
+                *trace = Some(value?.clone())
+            }
+            (Command::Run(_), "--param")
+            | (Command::Bench(_), "--param")
+            | (Command::Profile { .. }, "--param") => {
+                let value = value?;
+                let mut split = value.splitn(2, '=');
+                match (split.next(), split.next()) {
+                    (Some(name), Some(value)) => {
+                        params.push((name.to_owned(), value.to_owned()))
+                    }
+                    _ => return Err("Expected --param <name>=<value>".into()),
+                }
+            }
+            (_, _) => return Err(format!("Unknown argument `{}`", arg)),
+        }
+    }
+
+    match (&command, &days) {
+        (Command::Run(run), _)
+            if run.verify
+                && (run.json
+                    || run.view
+                    || run.render.is_some()
+                    || run.record.is_some()) =>
+        {
+            return Err(
+                "--verify cannot be used with --json, --render, --record or \
+                 --view"
+                    .into(),
+            )
+        }
+        (Command::Run(run), _)
+            if (run.pool != pool::Options::default())
+                && (run.verify || !matches!(days, Days::All)) =>
+        {
+            return Err(
+                "--jobs and --timeout can only be used with `run all`".into()
+            )
+        }
+        (Command::Run(run), &Days::One(day))
+            if run.verify && verify::pairs(day).is_empty() =>
+        {
+            return Err(format!("There is nothing to verify on day {}", day))
+        }
+        (Command::Generate(_), Days::All) => {
+            return Err("`generate` needs a day".into())
+        }
+        (Command::Confirm(_), Days::All) => {
+            return Err("`confirm` needs a day".into())
+        }
+        (Command::Disasm, Days::All) => {
+            return Err("`disasm` needs a day".into())
+        }
+        (Command::Decompile, Days::All) => {
+            return Err("`decompile` needs a day".into())
+        }
+        (Command::Debug, Days::All) => return Err("`debug` needs a day".into()),
+        (Command::Trace(_), Days::All) => {
+            return Err("`trace` needs a day".into())
+        }
+        (Command::Debug, _) if input.as_deref() == Some("-") => {
+            return Err("`debug` reads commands from stdin; give the program \
+                        with --input <path>"
+                .into())
+        }
+        (Command::Disasm, &Days::One(day))
+        | (Command::Decompile, &Days::One(day))
+        | (Command::Debug, &Days::One(day))
+        | (Command::Trace(_), &Days::One(day))
+            if days::program(day).is_none() =>
+        {
+            return Err(format!("There is no ElfCode program on day {}", day))
+        }
+        (Command::Confirm(confirm), _)
+            if [
+                confirm.answer.is_some(),
+                confirm.too_high.is_some(),
+                confirm.too_low.is_some(),
+            ]
+            .iter()
+            .filter(|&&given| given)
+            .count()
+                > 1 =>
+        {
+            return Err(
+                "Only one of --answer, --too-high and --too-low can be given"
+                    .into(),
+            )
+        }
+        (Command::Check { .. }, _) if input.is_some() || !params.is_empty() => {
+            return Err("--input and --param cannot be used with `check`".into())
+        }
+        (Command::Run(run), Days::All)
+            if run.render.is_some() || run.record.is_some() || run.view =>
+        {
+            return Err(
+                "--render, --record and --view cannot be used with `all`"
+                    .into(),
+            )
+        }
+        (
+            Command::Run(RunArgs {
+                render: Some(_), ..
+            }),
+            &Days::One(day),
+        ) if days::renderer(day).is_none() => {
+            return Err(format!("There is nothing to render on day {}", day))
+        }
+        (_, Days::All) if input.is_some() || !params.is_empty() => {
+            return Err("--input and --param cannot be used with `all`".into())
+        }
+        _ => {}
+    }
+
+    Ok(Args {
+        command,
+        days,
+        input,
+        part,
+        params,
+    })
+}
+
+fn run(
+    day: u32,
+    input: &Input,
+    part: Part,
+    params: &[(String, String)],
+) -> Result<(Answers, Timings)> {
+    let solver = days::solver(day).expect("days are checked by parse_args");
+    solver(input, part, params).map_err(|err| err.in_file(input.name()))
+}
+
+/// Draw the puzzle state of a day and save it as an image.
+fn render(
+    day: u32,
+    input: &Input,
+    params: &[(String, String)],
+    path: &str,
+    scale: usize,
+) -> Result<()> {
+    let renderer = days::renderer(day).expect("days are checked by parse_args");
+    let image =
+        renderer(input, params).map_err(|err| err.in_file(input.name()))?;
+    image.scaled(scale).save(path)
+}
+
+/// Fits an answer in a table cell.
+fn cell(answer: &Option<String>) -> String {
+    match answer {
+        Some(answer) if answer.contains('\n') => {
+            format!("[{} lines]", answer.lines().count())
+        }
+        Some(answer) => answer.clone(),
+        None => "-".to_owned(),
+    }
+}
+
+/// Cross-check the algorithms of days against each other, failing if any
+/// disagree.
+fn run_verify(days: &Days, input: Option<&str>, cases: usize) -> Result<()> {
+    let days = match days {
+        Days::One(day) => vec![*day],
+        Days::All => (1..=days::DAYS).collect(),
+    };
+
+    let mut disagreements = 0;
+    for day in days {
+        for pair in verify::pairs(day) {
+            let mut inputs = vec![Input::for_day(day, input)?];
+            inputs.extend(verify::generated(day, cases));
+            let report = pair.verify(&inputs)?;
+            println!("{}", report);
+            if report.counterexample.is_some() {
+                disagreements += 1;
+            }
+        }
+    }
+
+    match disagreements {
+        0 => Ok(()),
+        n => Err(Error::unsolvable(format!(
+            "{} pair(s) of algorithms disagree",
+            n
+        ))),
+    }
+}
+
+/// Print the listing of a day's ElfCode program, or the Rust function it
+/// decompiles into.
+fn run_disasm(day: u32, input: Option<&str>, rust: bool) -> Result<()> {
+    let input = Input::for_day(day, input)?;
+    let parse = days::program(day).expect("days are checked by parse_args");
+    let program =
+        parse(&input.lines()).map_err(|err| err.in_file(input.name()))?;
+    if rust {
+        print!("{}", decompile::rust(&program));
+    } else {
+        print!("{}", disasm::listing(&program));
+    }
+    Ok(())
+}
+
+/// Debug a day's ElfCode program with commands from stdin.
+fn run_debug(day: u32, input: Option<&str>) -> Result<()> {
+    let input = Input::for_day(day, input)?;
+    let parse = days::program(day).expect("days are checked by parse_args");
+    let program =
+        parse(&input.lines()).map_err(|err| err.in_file(input.name()))?;
+    // Days 19 and 21 run on a machine with six registers.
+    let mut debugger = Debugger::new(&program, 6)?;
+    let stdin = io::stdin();
+    let prompt = stdin.is_terminal();
+    debugger::session(&mut debugger, stdin.lock(), &mut io::stdout(), prompt)
+}
+
+/// Run a day's ElfCode program while tracing it, and print its profile.
+fn run_trace(day: u32, input: Option<&str>, args: &TraceArgs) -> Result<()> {
+    let input = Input::for_day(day, input)?;
+    let parse = days::program(day).expect("days are checked by parse_args");
+    let program =
+        parse(&input.lines()).map_err(|err| err.in_file(input.name()))?;
+    let mut registers = vec![0; 6];
+    for &(register, value) in &args.registers {
+        registers[register] = value;
+    }
+    let trace = trace::run(&program, registers, &args.options)?;
+    if args.json {
+        println!("{}", trace.json());
+    } else {
+        print!("{}", trace.table(&program));
+    }
+    Ok(())
+}
+
+/// Solve days once while profiling them, and report where the time and
+/// memory went.
+fn run_profile(args: &Args, trace: Option<&str>) -> Result<()> {
+    let days = match args.days {
+        Days::One(day) => vec![day],
+        Days::All => (1..=days::DAYS).collect(),
+    };
+
+    let mut profiles = Vec::new();
+    for day in days {
+        let input = Input::for_day(day, args.input.as_deref())?;
+        let solver = days::solver(day).expect("days are checked by parse_args");
+        profile::start();
+        let result = solver(&input, args.part, &args.params);
+        let profile = profile::stop();
+        match result {
+            Ok(_) => profiles.push((day, profile)),
+            Err(err) => {
+                println!("{:>3}  error: {}", day, err.in_file(input.name()))
+            }
+        }
+    }
+
+    print!("{}", profile::report(&profiles));
+    if let Some(path) = trace {
+        fs::write(path, profile::trace(&profiles).to_string())?;
+    }
+    Ok(())
+}
+
+/// Solve every day, failing if any of them cannot be solved.
+fn run_all(part: Part, json: bool, options: pool::Options) -> Result<()> {
+    let started = Instant::now();
+    let days = (1..=days::DAYS).collect::<Vec<_>>();
+    let runs = pool::solve_days(&days, part, options);
+    let elapsed = started.elapsed();
+    let failures = runs
+        .iter()
+        .filter(|run| !matches!(run.outcome, Outcome::Solved(..)))
+        .count();
+    let result = match failures {
+        0 => Ok(()),
+        n => Err(Error::unsolvable(format!("{} day(s) not solved", n))),
+    };
+
+    if json {
+        for run in &runs {
+            let day = run.day;
+            match &run.outcome {
+                Outcome::Solved(answers, timings) => {
+                    println!("{}", json::answers(day, answers, timings))
+                }
+                Outcome::Failed(err) => println!("{}", json::error(day, err)),
+                _ => println!("{}", json::error(day, &failure(run))),
+            }
+        }
+        return result;
+    }
+
+    println!(
+        "{:>3}  {:<26}  {:<26}  {:>10}",
+        "Day", "Part 1", "Part 2", "Time"
+    );
+
+    let mut total = Duration::default();
+    for run in &runs {
+        match &run.outcome {
+            Outcome::Solved(answers, timings) => {
+                let elapsed = timings.total();
+                total += elapsed;
+                println!(
+                    "{:>3}  {:<26}  {:<26}  {:>8.3}ms",
+                    run.day,
+                    cell(&answers.part1),
+                    cell(&answers.part2),
+                    elapsed.as_secs_f64() * 1000.0
+                );
+            }
+            _ => println!("{:>3}  {}", run.day, failure(run)),
+        }
+    }
+
+    for (name, time) in &[("Total", total), ("Wall time", elapsed)] {
+        println!(
+            "{:>3}  {:<26}  {:<26}  {:>8.3}ms",
+            "",
+            "",
+            name,
+            time.as_secs_f64() * 1000.0
+        );
+    }
+    result
+}
+
+/// Why a day was not solved.
+fn failure(run: &pool::Run) -> String {
+    match &run.outcome {
+        Outcome::Solved(..) => "solved".to_owned(),
+        Outcome::Failed(err) => format!("error: {}", err),
+        Outcome::Panicked(message) => format!("panicked: {}", message),
+        Outcome::TimedOut => {
+            format!("timed out after {:.3}s", run.elapsed.as_secs_f64())
+        }
+    }
+}
+
+/// Read the store of confirmed answers, which is empty if there is no such
+/// file yet.
+fn load_store(path: &str) -> Result<Store> {
+    if fs::metadata(path).is_err() {
+        return Ok(Store::default());
+    }
+    let input = Input::from_path(path)?;
+    Store::parse(&input.lines()).map_err(|err| err.in_file(path))
+}
+
+/// Solve days and compare their answers with the confirmed ones, failing if
+/// any answer is wrong or a day cannot be solved.
+fn run_check(days: &Days, part: Part, path: &str) -> Result<()> {
+    let store = load_store(path)?;
+    let days = match days {
+        Days::One(day) => vec![*day],
+        Days::All => (1..=days::DAYS).collect(),
+    };
+    let runs = pool::solve_days(&days, part, pool::Options::default());
+
+    println!("{:>3}  {:>4}  {:<26}  Verdict", "Day", "Part", "Answer");
+    let mut failures = 0;
+    for run in &runs {
+        let answers = match &run.outcome {
+            Outcome::Solved(answers, _) => answers,
+            _ => {
+                println!("{:>3}  {:>4}  {}", run.day, "", failure(run));
+                failures += 1;
+                continue;
+            }
+        };
+        for &(part, answer) in &[(1, &answers.part1), (2, &answers.part2)] {
+            let verdict = match answer {
+                Some(answer) => store.check(run.day, part, answer),
+                None => continue,
+            };
+            if verdict.is_wrong() {
+                failures += 1;
+            }
+            println!(
+                "{:>3}  {:>4}  {:<26}  {}",
+                run.day,
+                part,
+                cell(answer),
+                verdict
+            );
+        }
+    }
+
+    match failures {
+        0 => Ok(()),
+        n => Err(Error::unsolvable(format!("{} check(s) failed", n))),
+    }
+}
+
+/// Record the answer to a part that the website accepted, or the hint it gave
+/// for a wrong answer.
+fn run_confirm(day: u32, args: &ConfirmArgs) -> Result<()> {
+    let mut store = load_store(&args.store)?;
+    let part = args.part;
+
+    if let Some(answer) = args.too_high {
+        store.too_high(day, part, answer);
+        println!("Day {} part {}: {} is too high", day, part, answer);
+    } else if let Some(answer) = args.too_low {
+        store.too_low(day, part, answer);
+        println!("Day {} part {}: {} is too low", day, part, answer);
+    } else {
+        let answer = match &args.answer {
+            Some(answer) => answer.clone(),
+            None => {
+                let input = Input::for_day(day, None)?;
+                let only = if part == 1 { Part::One } else { Part::Two };
+                let (answers, _) = run(day, &input, only, &[])?;
+                answers.part1.or(answers.part2).ok_or_else(|| {
+                    Error::unsolvable(format!(
+                        "Day {} has no part {}",
+                        day, part
+                    ))
+                })?
+            }
+        };
+        let shown = cell(&Some(answer.clone()));
+        match store.confirm(day, part, answer) {
+            Some(earlier) => println!(
+                "Day {} part {}: confirmed {}, instead of {}",
+                day,
+                part,
+                shown,
+                cell(&Some(earlier))
+            ),
+            None => println!("Day {} part {}: confirmed {}", day, part, shown),
+        }
+    }
+
+    fs::write(&args.store, store.to_string())?;
+    Ok(())
+}
+
+/// Formats a duration in milliseconds.
+fn millis(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn print_report(report: &Report, baseline: Option<&Baseline>) {
+    for (phase, stats) in &report.phases {
+        let change = baseline
+            .and_then(|baseline| {
+                baseline.change(report.day, *phase, stats.median)
+            })
+            .map(|change| format!("{:+.1}%", change * 100.0))
+            .unwrap_or_default();
+        println!(
+            "{:>3}  {:<5}  {:>12}  {:>12}  {:>12}  {:>12}  {:>12}  {:>4}  {:>8}",
+            report.day,
+            phase,
+            millis(stats.median),
+            millis(stats.mean),
+            millis(stats.spread),
+            millis(stats.min),
+            millis(stats.max),
+            stats.runs,
+            change
+        );
+    }
+}
+
+fn run_bench(
+    days: &Days,
+    input: Option<&str>,
+    part: Part,
+    params: &[(String, String)],
+    args: &BenchArgs,
+) -> Result<()> {
+    let baseline = match &args.baseline {
+        Some(path) => {
+            let input = Input::from_path(path)?;
+            Some(
+                Baseline::parse(&input.lines())
+                    .map_err(|err| err.in_file(path))?,
+            )
+        }
+        None => None,
+    };
+    // Saving to an existing baseline only replaces the days benchmarked now.
+    let mut saved = match &args.save {
+        Some(path) if fs::metadata(path).is_ok() => {
+            let input = Input::from_path(path)?;
+            Baseline::parse(&input.lines()).map_err(|err| err.in_file(path))?
+        }
+        _ => Baseline::default(),
+    };
+
+    println!(
+        "{:>3}  {:<5}  {:>12}  {:>12}  {:>12}  {:>12}  {:>12}  {:>4}  {:>8}",
+        "Day",
+        "Phase",
+        "Median",
+        "Mean",
+        "Std dev",
+        "Min",
+        "Max",
+        "Runs",
+        "Change"
+    );
+
+    let days = match days {
+        Days::One(day) => vec![*day],
+        Days::All => (1..=days::DAYS).collect(),
+    };
+    for day in days {
+        let input = Input::for_day(day, input)?;
+        match bench::bench_day(day, &input, part, params, args.runs, args.time)
+        {
+            Ok(report) => {
+                print_report(&report, baseline.as_ref());
+                saved.record(&report);
+            }
+            Err(err) => {
+                println!("{:>3}  error: {}", day, err.in_file(input.name()))
+            }
+        }
+    }
+
+    if let Some(path) = &args.save {
+        fs::write(path, saved.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Solves a day and prints its answers, or a JSON document with --json, and
+/// saves the rendered puzzle state with --render and the recorded simulation
+/// with --record, and plays the simulation with --view.
+fn run_day(day: u32, args: &Args, run_args: &RunArgs) -> Result<()> {
+    let recording = run_args.record.is_some() || run_args.view;
+    if recording {
+        record::start(run_args.recording);
+    }
+    let result = Input::for_day(day, args.input.as_deref()).and_then(|input| {
+        let (answers, timings) = run(day, &input, args.part, &args.params)?;
+        Ok((input, answers, timings))
+    });
+    let animation = record::stop();
+
+    let input = if run_args.json {
+        match result {
+            Ok((input, answers, timings)) => {
+                println!("{}", json::answers(day, &answers, &timings));
+                input
+            }
+            Err(err) => {
+                println!("{}", json::error(day, &err));
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let (input, answers, _) = result?;
+        for diagnostic in &answers.diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        print_answer(1, answers.part1);
+        print_answer(2, answers.part2);
+        input
+    };
+
+    if recording && animation.frames.is_empty() {
+        return Err(Error::Param(format!(
+            "There is no simulation to record on day {}",
+            day
+        )));
+    }
+    if let Some(path) = &run_args.record {
+        animation.scaled(run_args.scale).save(path)?;
+    }
+    if run_args.view {
+        view::view(&animation)?;
+    }
+
+    match &run_args.render {
+        Some(path) => render(day, &input, &args.params, path, run_args.scale),
+        None => Ok(()),
+    }
+}
+
+/// Prints an answer, starting multi-line answers on a line of their own.
+fn print_answer(part: u32, answer: Option<String>) {
+    match answer {
+        Some(ref answer) if answer.contains('\n') => {
+            println!("Part {}:\n{}", part, answer)
+        }
+        Some(answer) => println!("Part {}: {}", part, answer),
+        None => {}
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: profile::Counting = profile::Counting;
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = match parse_args(&args) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let result = match (&args.command, &args.days) {
+        (Command::Bench(bench), days) => {
+            let input = args.input.as_deref();
+            run_bench(days, input, args.part, &args.params, bench)
+        }
+        (Command::Run(run_args), days) if run_args.verify => {
+            run_verify(days, args.input.as_deref(), run_args.cases)
+        }
+        (Command::Run(run_args), Days::All) => {
+            run_all(args.part, run_args.json, run_args.pool)
+        }
+        (Command::Run(run_args), &Days::One(day)) => {
+            run_day(day, &args, run_args)
+        }
+        (Command::Profile { trace }, _) => run_profile(&args, trace.as_deref()),
+        (&Command::Generate(options), &Days::One(day)) => {
+            let input = generate::input(day, options).expect("a puzzle day");
+            print!("{}", input.as_str());
+            Ok(())
+        }
+        (Command::Generate(_), Days::All) => unreachable!(),
+        (Command::Disasm, &Days::One(day)) => {
+            run_disasm(day, args.input.as_deref(), false)
+        }
+        (Command::Decompile, &Days::One(day)) => {
+            run_disasm(day, args.input.as_deref(), true)
+        }
+        (Command::Debug, &Days::One(day)) => {
+            run_debug(day, args.input.as_deref())
+        }
+        (Command::Trace(trace), &Days::One(day)) => {
+            run_trace(day, args.input.as_deref(), trace)
+        }
+        (Command::Disasm, Days::All)
+        | (Command::Decompile, Days::All)
+        | (Command::Debug, Days::All)
+        | (Command::Trace(_), Days::All) => unreachable!(),
+        (Command::Check { store }, days) => run_check(days, args.part, store),
+        (Command::Confirm(confirm), &Days::One(day)) => {
+            run_confirm(day, confirm)
+        }
+        (Command::Confirm(_), Days::All) => unreachable!(),
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
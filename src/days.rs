@@ -0,0 +1,149 @@
+//! Look up the solution of a day by its number.
+use elfcode::Program;
+use generate::Rng;
+use input::Input;
+use render::Image;
+use solution::{self, Answers, Part, Result, Solution, Timings};
+
+use december01::December01;
+use december02::December02;
+use december03::December03;
+use december04::December04;
+use december05::December05;
+use december06::December06;
+use december07::December07;
+use december08::December08;
+use december09::December09;
+use december10::December10;
+use december11::December11;
+use december12::December12;
+use december13::December13;
+use december14::December14;
+use december15::December15;
+use december16::December16;
+use december17::December17;
+use december18::December18;
+use december19::{self, December19};
+use december20::December20;
+use december21::{self, December21};
+use december22::December22;
+use december23::December23;
+use december24::December24;
+use december25::December25;
+
+/// The number of days in the calendar.
+pub const DAYS: u32 = 25;
+
+/// Parses an input, unless it was parsed before, and solves the requested
+/// parts of one day, with the given parameter overrides.
+pub type Solver =
+    fn(&Input, Part, &[(String, String)]) -> Result<(Answers, Timings)>;
+
+/// The solver of the given day, if there is a puzzle on that day.
+pub fn solver(day: u32) -> Option<Solver> {
+    let solver: Solver = match day {
+        1 => solution::solve::<December01>,
+        2 => solution::solve::<December02>,
+        3 => solution::solve::<December03>,
+        4 => solution::solve::<December04>,
+        5 => solution::solve::<December05>,
+        6 => solution::solve::<December06>,
+        7 => solution::solve::<December07>,
+        8 => solution::solve::<December08>,
+        9 => solution::solve::<December09>,
+        10 => solution::solve::<December10>,
+        11 => solution::solve::<December11>,
+        12 => solution::solve::<December12>,
+        13 => solution::solve::<December13>,
+        14 => solution::solve::<December14>,
+        15 => solution::solve::<December15>,
+        16 => solution::solve::<December16>,
+        17 => solution::solve::<December17>,
+        18 => solution::solve::<December18>,
+        19 => solution::solve::<December19>,
+        20 => solution::solve::<December20>,
+        21 => solution::solve::<December21>,
+        22 => solution::solve::<December22>,
+        23 => solution::solve::<December23>,
+        24 => solution::solve::<December24>,
+        25 => solution::solve::<December25>,
+        _ => return None,
+    };
+
+    Some(solver)
+}
+
+/// Parses an input, unless it was parsed before, and draws the puzzle state,
+/// with the given parameter overrides.
+pub type Renderer = fn(&Input, &[(String, String)]) -> Result<Image>;
+
+/// The renderer of the given day, if its puzzle state is a picture.
+pub fn renderer(day: u32) -> Option<Renderer> {
+    let renderer: Renderer = match day {
+        10 => solution::render::<December10>,
+        13 => solution::render::<December13>,
+        15 => solution::render::<December15>,
+        17 => solution::render::<December17>,
+        18 => solution::render::<December18>,
+        _ => return None,
+    };
+
+    Some(renderer)
+}
+
+/// Parses an input that is an ElfCode program.
+pub type ProgramParser = fn(&[&str]) -> Result<Program<i64>>;
+
+/// The parser of the given day, if its input is an ElfCode program with its
+/// instruction pointer bound to a register.
+pub fn program(day: u32) -> Option<ProgramParser> {
+    let parser: ProgramParser = match day {
+        19 => december19::parse,
+        21 => december21::parse,
+        _ => return None,
+    };
+
+    Some(parser)
+}
+
+/// Generates a random input of the given size.
+pub type Generator = fn(&mut Rng, usize) -> String;
+
+/// The input generator of the given day, if there is a puzzle on that day.
+pub fn generator(day: u32) -> Option<Generator> {
+    let generator: Generator = match day {
+        1 => December01::generate,
+        2 => December02::generate,
+        3 => December03::generate,
+        4 => December04::generate,
+        5 => December05::generate,
+        6 => December06::generate,
+        7 => December07::generate,
+        8 => December08::generate,
+        9 => December09::generate,
+        10 => December10::generate,
+        11 => December11::generate,
+        12 => December12::generate,
+        13 => December13::generate,
+        14 => December14::generate,
+        15 => December15::generate,
+        16 => December16::generate,
+        17 => December17::generate,
+        18 => December18::generate,
+        19 => December19::generate,
+        20 => December20::generate,
+        21 => December21::generate,
+        22 => December22::generate,
+        23 => December23::generate,
+        24 => December24::generate,
+        25 => December25::generate,
+        _ => return None,
+    };
+
+    Some(generator)
+}
+
+/// The path of the puzzle input of the given day.
+pub fn input_path(day: u32) -> String {
+    format!("input/december{:02}.txt", day)
+}
@@ -0,0 +1,545 @@
+//! A debugger for ElfCode programs, with breakpoints, watchpoints and
+//! stepping. Commands are read a line at a time, so that sessions can be
+//! typed at a prompt or scripted by piping a file of commands in.
+use disasm::{self, Jump};
+use elfcode::{Machine, Program, Word};
+use error::Error;
+use solution::Result;
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+pub static HELP: &str = "\
+break <ip>         stop before the instruction at <ip> runs (b)
+delete <ip>        remove the breakpoint at <ip> (d)
+watch r<n>         stop after an instruction writes to register <n> (w)
+watch r<n> == <v>  stop after an instruction writes <v> to register <n>
+unwatch r<n>       remove the watchpoints on register <n>
+step [<n>]         run <n> instructions, 1 by default (s)
+continue           run until a breakpoint, a watchpoint or the end (c)
+print              show the registers and the next instruction (p)
+set r<n> = <v>     change a register, or the instruction pointer with `ip`
+hits               show how often every instruction has run
+help               show this (h)
+quit               end the session (q)
+
+Lines that are empty or start with `#` are skipped.";
+
+/// A command of the debugger.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command<W> {
+    Break(usize),
+    Delete(usize),
+    /// Watch a register for writes, or for writes of a value.
+    Watch {
+        register: usize,
+        value: Option<W>,
+    },
+    Unwatch(usize),
+    Step(u64),
+    Continue,
+    Print,
+    /// Set a register, or the instruction pointer if `register` is `None`.
+    Set {
+        register: Option<usize>,
+        value: W,
+    },
+    Hits,
+    Help,
+    Quit,
+}
+
+fn register(word: &str) -> std::result::Result<usize, String> {
+    word.strip_prefix('r')
+        .and_then(|register| register.parse().ok())
+        .ok_or_else(|| {
+            format!("Expected a register such as r0, not `{}`", word)
+        })
+}
+
+fn number<T: FromStr>(word: &str) -> std::result::Result<T, String> {
+    word.parse()
+        .map_err(|_| format!("Expected a number, not `{}`", word))
+}
+
+impl<W: Word + FromStr> Command<W> {
+    pub fn parse(line: &str) -> std::result::Result<Command<W>, String> {
+        // `set r0=1` and `watch r5==3` split like `set r0 = 1` and
+        // `watch r5 = = 3`.
+        let spaced = line.replace('=', " = ");
+        let words = spaced.split_whitespace().collect::<Vec<_>>();
+        Ok(match words[..] {
+            ["break", ip] | ["b", ip] => Command::Break(number(ip)?),
+            ["delete", ip] | ["d", ip] => Command::Delete(number(ip)?),
+            ["watch", r] | ["w", r] => Command::Watch {
+                register: register(r)?,
+                value: None,
+            },
+            ["watch", r, "=", "=", value] | ["w", r, "=", "=", value] => {
+                Command::Watch {
+                    register: register(r)?,
+                    value: Some(number(value)?),
+                }
+            }
+            ["unwatch", r] => Command::Unwatch(register(r)?),
+            ["step"] | ["s"] => Command::Step(1),
+            ["step", steps] | ["s", steps] => Command::Step(number(steps)?),
+            ["continue"] | ["c"] => Command::Continue,
+            ["print"] | ["p"] => Command::Print,
+            ["set", "ip", "=", value] => Command::Set {
+                register: None,
+                value: number(value)?,
+            },
+            ["set", r, "=", value] => Command::Set {
+                register: Some(register(r)?),
+                value: number(value)?,
+            },
+            ["hits"] => Command::Hits,
+            ["help"] | ["h"] => Command::Help,
+            ["quit"] | ["q"] => Command::Quit,
+            _ => {
+                return Err(format!(
+                    "Unknown command `{}`; `help` lists the commands",
+                    line.trim()
+                ))
+            }
+        })
+    }
+}
+
+/// Why running the program stopped.
+enum Stop<W> {
+    /// It ran as many instructions as it was asked to.
+    Steps,
+    Breakpoint,
+    /// The instruction at `idx` wrote to a watched register.
+    Watch {
+        idx: usize,
+        register: usize,
+        before: W,
+        after: W,
+    },
+    Halted,
+}
+
+/// A machine running a program under the debugger.
+pub struct Debugger<'a, W: 'a> {
+    program: &'a Program<W>,
+    machine: Machine<W>,
+    breakpoints: BTreeSet<usize>,
+    /// The watched registers, with the values they are watched for.
+    watches: Vec<(usize, Option<W>)>,
+    /// How often every instruction has run.
+    hits: Vec<u64>,
+    /// Where every instruction goes, if it is a jump.
+    jumps: Vec<Option<Jump>>,
+    /// The width of the longest numbered instruction, to line up their
+    /// pseudocode.
+    width: usize,
+}
+
+impl<'a, W: Word> Debugger<'a, W> {
+    /// A debugger at the start of the program, on a machine with the given
+    /// number of registers, all zero.
+    pub fn new(
+        program: &'a Program<W>,
+        registers: usize,
+    ) -> Result<Debugger<'a, W>> {
+        Ok(Debugger {
+            program,
+            machine: Machine::for_program(program, registers)?,
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+            hits: vec![0; program.instrs.len()],
+            jumps: disasm::jumps(program),
+            width: program
+                .instrs
+                .iter()
+                .enumerate()
+                .map(|(idx, instr)| format!("{:02} {}", idx, instr).len())
+                .max()
+                .unwrap_or(0),
+        })
+    }
+
+    pub fn machine(&self) -> &Machine<W> {
+        &self.machine
+    }
+
+    /// Run up to `steps` instructions, or until the program halts. It also
+    /// stops before an instruction with a breakpoint, unless that is the
+    /// first one, and after a write to a watched register.
+    fn run(&mut self, steps: Option<u64>) -> Result<Stop<W>> {
+        let instrs = &self.program.instrs;
+        let mut ran = 0;
+        loop {
+            if self.machine.halted(instrs) {
+                return Ok(Stop::Halted);
+            }
+            if steps == Some(ran) {
+                return Ok(Stop::Steps);
+            }
+            let idx = self.machine.ip.to_index().expect("not halted");
+            if ran > 0 && self.breakpoints.contains(&idx) {
+                return Ok(Stop::Breakpoint);
+            }
+
+            let register = instrs[idx].c.to_index();
+            let before = register
+                .and_then(|register| self.machine.registers.get(register))
+                .cloned();
+            self.machine.step(instrs)?;
+            self.hits[idx] += 1;
+            ran += 1;
+
+            if let (Some(register), Some(before)) = (register, before) {
+                let after = self.machine.registers[register];
+                let watched = self.watches.iter().any(|&(watched, value)| {
+                    watched == register && value.is_none_or(|v| v == after)
+                });
+                if watched {
+                    return Ok(Stop::Watch {
+                        idx,
+                        register,
+                        before,
+                        after,
+                    });
+                }
+            }
+        }
+    }
+
+    /// The instruction at `idx`, numbered and in pseudocode.
+    fn instr(&self, idx: usize) -> String {
+        let instr = format!("{:02} {}", idx, self.program.instrs[idx]);
+        format!(
+            "{:w$}  # {}",
+            instr,
+            disasm::pseudocode(self.program, &self.jumps, idx),
+            w = self.width
+        )
+    }
+
+    /// Show the registers, and the instruction that runs next.
+    fn print<O: Write>(&self, out: &mut O) -> io::Result<()> {
+        let registers = self
+            .machine
+            .registers
+            .iter()
+            .enumerate()
+            .map(|(register, value)| format!("r{} {}", register, value))
+            .collect::<Vec<_>>();
+        writeln!(out, "ip {}  {}", self.machine.ip, registers.join("  "))?;
+        match self.machine.ip.to_index() {
+            Some(idx) if idx < self.program.instrs.len() => {
+                writeln!(out, "{}", self.instr(idx))
+            }
+            _ => writeln!(out, "halted"),
+        }
+    }
+
+    fn hits<O: Write>(&self, out: &mut O) -> io::Result<()> {
+        let instrs = (0..self.program.instrs.len())
+            .map(|idx| self.instr(idx))
+            .collect::<Vec<_>>();
+        let width = self
+            .hits
+            .iter()
+            .max()
+            .map_or(0, |hits| hits.to_string().len());
+        for (hits, instr) in self.hits.iter().zip(instrs) {
+            writeln!(out, "{:>w$}  {}", hits, instr, w = width)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the machine has the register.
+    fn check<O: Write>(
+        &self,
+        register: usize,
+        out: &mut O,
+    ) -> io::Result<bool> {
+        let registers = self.machine.registers.len();
+        if register < registers {
+            return Ok(true);
+        }
+        writeln!(
+            out,
+            "There is no register r{}; the machine has r0 to r{}",
+            register,
+            registers - 1
+        )?;
+        Ok(false)
+    }
+
+    /// Run a command, writing what it shows to `out`. Returns false on
+    /// `quit`.
+    pub fn execute<O: Write>(
+        &mut self,
+        command: Command<W>,
+        out: &mut O,
+    ) -> io::Result<bool> {
+        let stop = match command {
+            Command::Break(idx) if idx >= self.program.instrs.len() => {
+                writeln!(out, "There is no instruction {}", idx)?;
+                return Ok(true);
+            }
+            Command::Break(idx) => {
+                self.breakpoints.insert(idx);
+                writeln!(out, "Breakpoint at {}", self.instr(idx).trim_end())?;
+                return Ok(true);
+            }
+            Command::Delete(idx) => {
+                if self.breakpoints.remove(&idx) {
+                    writeln!(out, "Deleted the breakpoint at {:02}", idx)?;
+                } else {
+                    writeln!(out, "There is no breakpoint at {:02}", idx)?;
+                }
+                return Ok(true);
+            }
+            Command::Watch { register, value } => {
+                if self.check(register, out)? {
+                    self.watches.push((register, value));
+                    match value {
+                        Some(value) => writeln!(
+                            out,
+                            "Watching r{} for writes of {}",
+                            register, value
+                        )?,
+                        None => {
+                            writeln!(out, "Watching r{} for writes", register)?
+                        }
+                    }
+                }
+                return Ok(true);
+            }
+            Command::Unwatch(register) => {
+                self.watches.retain(|&(watched, _)| watched != register);
+                writeln!(out, "Stopped watching r{}", register)?;
+                return Ok(true);
+            }
+            Command::Step(steps) => self.run(Some(steps)),
+            Command::Continue => self.run(None),
+            Command::Print => {
+                self.print(out)?;
+                return Ok(true);
+            }
+            Command::Set { register, value } => {
+                match register {
+                    Some(register) if !self.check(register, out)? => {
+                        return Ok(true)
+                    }
+                    Some(register) => self.machine.registers[register] = value,
+                    None => self.machine.ip = value,
+                }
+                self.print(out)?;
+                return Ok(true);
+            }
+            Command::Hits => {
+                self.hits(out)?;
+                return Ok(true);
+            }
+            Command::Help => {
+                writeln!(out, "{}", HELP)?;
+                return Ok(true);
+            }
+            Command::Quit => return Ok(false),
+        };
+
+        match stop {
+            Ok(Stop::Steps) => {}
+            Ok(Stop::Breakpoint) => {
+                let idx = self.machine.ip.to_index().expect("a breakpoint");
+                writeln!(out, "Breakpoint at {:02}", idx)?
+            }
+            Ok(Stop::Watch {
+                idx,
+                register,
+                before,
+                after,
+            }) => writeln!(
+                out,
+                "r{} changed from {} to {} at {:02}",
+                register, before, after, idx
+            )?,
+            Ok(Stop::Halted) => {
+                let ran = self.hits.iter().sum::<u64>();
+                writeln!(out, "Halted after {} instructions", ran)?
+            }
+            Err(err) => writeln!(out, "{}", err)?,
+        }
+        self.print(out)?;
+        Ok(true)
+    }
+}
+
+/// Read commands from `input` and run them until it ends or says `quit`,
+/// writing what they show to `out`. With `prompt`, a prompt is written
+/// before every command is read.
+pub fn session<W, R, O>(
+    debugger: &mut Debugger<W>,
+    mut input: R,
+    out: &mut O,
+    prompt: bool,
+) -> Result<()>
+where
+    W: Word + FromStr,
+    R: BufRead,
+    O: Write,
+{
+    let mut line = String::new();
+    loop {
+        if prompt {
+            write!(out, "(elfcode) ")?;
+            out.flush()?;
+        }
+        line.clear();
+        if input.read_line(&mut line).map_err(Error::from)? == 0 {
+            return Ok(());
+        }
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match Command::parse(line) {
+            Ok(command) => {
+                if !debugger.execute(command, out)? {
+                    return Ok(());
+                }
+            }
+            Err(message) => writeln!(out, "{}", message)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use days;
+    use december21;
+    use input::Input;
+
+    fn transcript(program: &Program<i64>, script: &str) -> String {
+        let mut debugger = Debugger::new(program, 6).unwrap();
+        let mut out = Vec::new();
+        session(&mut debugger, script.as_bytes(), &mut out, false).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn commands() {
+        let parse = |line| Command::<i64>::parse(line);
+        assert_eq!(parse("b 28"), Ok(Command::Break(28)));
+        assert_eq!(
+            parse("watch r5==3"),
+            Ok(Command::Watch {
+                register: 5,
+                value: Some(3)
+            })
+        );
+        assert_eq!(
+            parse("w r1"),
+            Ok(Command::Watch {
+                register: 1,
+                value: None
+            })
+        );
+        assert_eq!(parse("step"), Ok(Command::Step(1)));
+        assert_eq!(parse("  s 10 "), Ok(Command::Step(10)));
+        assert_eq!(
+            parse("set r0=1"),
+            Ok(Command::Set {
+                register: Some(0),
+                value: 1
+            })
+        );
+        assert_eq!(
+            parse("set ip = -1"),
+            Ok(Command::Set {
+                register: None,
+                value: -1
+            })
+        );
+        assert!(parse("set x = 1").is_err());
+        assert!(parse("step many").is_err());
+        assert!(parse("jump 3").is_err());
+    }
+
+    #[test]
+    fn scripted() {
+        let program = Program::parse(
+            &[
+                "#ip 3",
+                "seti 3 0 1",
+                "addi 1 -1 1",
+                "eqri 1 0 2",
+                "addr 2 3 3",
+                "seti 0 0 3",
+                "addi 0 1 0",
+            ],
+            6,
+        )
+        .unwrap();
+        let script = "\
+# Count r1 down from 3, stopping where the loop goes round.
+break 3
+watch r1 == 1
+continue
+set r1 = 5
+continue
+delete 3
+step 2
+hits
+continue
+continue
+quit
+print
+";
+        assert_eq!(
+            transcript(&program, script),
+            "\
+Breakpoint at 03 addr 2 3 3   # goto 05 if R2
+Watching r1 for writes of 1
+Breakpoint at 03
+ip 3  r0 0  r1 2  r2 0  r3 2  r4 0  r5 0
+03 addr 2 3 3   # goto 05 if R2
+ip 3  r0 0  r1 5  r2 0  r3 2  r4 0  r5 0
+03 addr 2 3 3   # goto 05 if R2
+Breakpoint at 03
+ip 3  r0 0  r1 4  r2 0  r3 2  r4 0  r5 0
+03 addr 2 3 3   # goto 05 if R2
+Deleted the breakpoint at 03
+ip 1  r0 0  r1 4  r2 0  r3 0  r4 0  r5 0
+01 addi 1 -1 1  # R1 += -1
+1  00 seti 3 0 1   # R1 = 3
+2  01 addi 1 -1 1  # R1 += -1
+2  02 eqri 1 0 2   # R2 = R1 == 0
+2  03 addr 2 3 3   # goto 05 if R2
+2  04 seti 0 0 3   # goto 01
+0  05 addi 0 1 0   # R0 += 1
+r1 changed from 2 to 1 at 01
+ip 2  r0 0  r1 1  r2 0  r3 1  r4 0  r5 0
+02 eqri 1 0 2   # R2 = R1 == 0
+Halted after 25 instructions
+ip 6  r0 1  r1 0  r2 1  r3 5  r4 0  r5 0
+halted
+"
+        );
+    }
+
+    /// Breaking where day 21 compares register 5 with register 0 finds the
+    /// answer to part 1.
+    #[test]
+    fn december21() {
+        let input = Input::for_day(21, None).unwrap();
+        let program = days::program(21).unwrap()(&input.lines()).unwrap();
+        let answer = december21::run(&program, 0, true).unwrap();
+        let transcript = transcript(&program, "break 28\ncontinue\n");
+        let registers = transcript.lines().nth(2).unwrap();
+        assert!(
+            registers.ends_with(&format!("r5 {}", answer)),
+            "{}",
+            registers
+        );
+    }
+}
@@ -1,12 +1,17 @@
+use generate::Rng;
+use parse;
+use solution::{self, Result, Solution};
 use std::collections::BTreeMap;
-mod utils;
 
-fn find_duplicate_frequency(numbers: &[i32]) -> i32 {
+pub struct December01;
+
+pub fn find_duplicate_frequency(numbers: &[i32]) -> i32 {
     let mut frequencies = BTreeMap::new();
     let mut found = false;
     let mut frequency = 0;
 
     while !found {
+        solution::checkpoint();
         for number in numbers {
             frequency += number;
 
@@ -22,15 +27,45 @@ fn find_duplicate_frequency(numbers: &[i32]) -> i32 {
     frequency
 }
 
-fn main() {
-    let lines: Vec<String> = utils::lines_from_file("input/december01.txt").unwrap();
-    let numbers: Vec<i32> = lines
-        .into_iter()
-        .map(|line| line.parse::<i32>().unwrap())
-        .collect();
-    let sum: i32 = (&numbers).iter().sum();
-    let dup = find_duplicate_frequency(&numbers);
-
-    println!("Part 1: {:#?}", sum);
-    println!("Part 2: {:#?}", dup)
+impl Solution for December01 {
+    type Input = Vec<i32>;
+    type Params = ();
+    type Part1 = i32;
+    type Part2 = i32;
+
+    fn parse(lines: &[&str]) -> Result<Vec<i32>> {
+        parse::lines(lines).iter().map(|line| line.parse()).collect()
+    }
+
+    fn part1(numbers: &Vec<i32>, _: &()) -> Result<i32> {
+        Ok(numbers.iter().sum())
+    }
+
+    fn part2(numbers: &Vec<i32>, _: &()) -> Result<i32> {
+        Ok(find_duplicate_frequency(numbers))
+    }
+
+    /// `20 * size` frequency changes of at most 20. They add up to less than
+    /// their number, so the running sums of two changes are a multiple of
+    /// the total apart and some frequency is reached twice.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let n = (size * 20).max(2) as i64;
+        let mut changes = (0..n)
+            .map(|_| rng.range(1, 20) * if rng.chance(0.5) { 1 } else { -1 })
+            .collect::<Vec<i64>>();
+
+        // Nudge the changes towards the total, keeping them non-zero.
+        let total = rng.range(-(n - 1) / 2, (n - 1) / 2);
+        let mut sum = changes.iter().sum::<i64>();
+        while sum != total {
+            let step = (total - sum).signum();
+            let change = &mut changes[rng.index(n as usize)];
+            if *change + step != 0 && (*change + step).abs() <= 20 {
+                *change += step;
+                sum += step;
+            }
+        }
+
+        changes.iter().map(|c| format!("{:+}\n", c)).collect()
+    }
 }
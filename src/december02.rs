@@ -1,7 +1,12 @@
+use error::Error;
+use generate::Rng;
+use parse;
+use solution::{Result, Solution};
 use std::collections::BTreeMap;
-mod utils;
 
-fn checksum(ids: &Vec<String>) -> i32 {
+pub struct December02;
+
+pub fn checksum(ids: &[String]) -> i32 {
     let mut exactly2 = 0;
     let mut exactly3 = 0;
 
@@ -25,7 +30,7 @@ fn checksum(ids: &Vec<String>) -> i32 {
     exactly2 * exactly3
 }
 
-fn find_similar_ids(ids: &[String]) -> Option<(&str, &str)> {
+pub fn find_similar_ids(ids: &[String]) -> Option<(&str, &str)> {
     for id1 in ids {
         for id2 in ids {
             assert!(id1.len() == id2.len());
@@ -50,9 +55,66 @@ fn find_similar_ids(ids: &[String]) -> Option<(&str, &str)> {
     None
 }
 
-fn main() {
-    let lines: Vec<String> = utils::lines_from_file("input/december02.txt").unwrap();
+impl Solution for December02 {
+    type Input = Vec<String>;
+    type Params = ();
+    type Part1 = i32;
+    type Part2 = String;
+
+    fn parse(lines: &[&str]) -> Result<Vec<String>> {
+        for line in parse::lines(lines) {
+            if line.text.len() != lines[0].len() {
+                return Err(line.error(format!(
+                    "expected an id of length {}",
+                    lines[0].len()
+                )));
+            }
+        }
+
+        Ok(lines.iter().map(|&line| line.to_owned()).collect())
+    }
+
+    fn part1(ids: &Vec<String>, _: &()) -> Result<i32> {
+        Ok(checksum(ids))
+    }
 
-    println!("Part 1: {:#?}", checksum(&lines));
-    println!("Part 2: {:#?}", find_similar_ids(&lines))
+    /// The letters the two similar ids have in common.
+    fn part2(ids: &Vec<String>, _: &()) -> Result<String> {
+        let (id1, id2) = find_similar_ids(ids)
+            .ok_or_else(|| Error::unsolvable("No similar ids"))?;
+
+        Ok(id1
+            .chars()
+            .zip(id2.chars())
+            .filter(|(char1, char2)| char1 == char2)
+            .map(|(c, _)| c)
+            .collect())
+    }
+
+    /// `5 * size` ids of 26 letters, two of which differ in one letter.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let letters = b"abcdefghijklmnopqrstuvwxyz";
+        // A small alphabet per id repeats letters, as the checksum counts.
+        let mut ids = (0..(size * 5).max(2))
+            .map(|_| {
+                let alphabet = rng.range(8, 26) as usize;
+                (0..26)
+                    .map(|_| letters[rng.index(alphabet)] as char)
+                    .collect::<Vec<char>>()
+            })
+            .collect::<Vec<_>>();
+
+        let (first, second) = (rng.index(ids.len()), rng.index(ids.len() - 1));
+        let second = if second >= first { second + 1 } else { second };
+        let mut similar = ids[first].clone();
+        let at = rng.index(similar.len());
+        while similar[at] == ids[first][at] {
+            similar[at] = *rng.choose(letters) as char;
+        }
+        ids[second] = similar;
+
+        ids.iter()
+            .map(|id| id.iter().chain(Some(&'\n')).collect::<String>())
+            .collect()
+    }
 }
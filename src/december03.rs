@@ -1,17 +1,17 @@
-extern crate regex;
-extern crate euclid;
-use euclid::{Rect, Point2D, Size2D};
+use euclid::{Point2D, Rect, Size2D};
+use generate::Rng;
+use parse;
+use solution::{Result, Solution};
 use std::collections::BTreeMap;
-mod utils;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December03;
 
 ///Finds all overlaps between claims (including duplicate overlaps).
-fn claim_overlaps(claims: &[Rect<u32>]) -> Vec<Rect<u32>> {
+pub fn claim_overlaps(claims: &[Rect<u32>]) -> Vec<Rect<u32>> {
     let mut intersections = Vec::new();
     
-    for (idx, rect1) in claims[1..].iter().enumerate() {
-        for rect2 in claims[..=idx].iter() {
+    for (idx, rect1) in claims.iter().enumerate().skip(1) {
+        for rect2 in claims[..idx].iter() {
             if let Some(intersection) = rect1.intersection(rect2) {
                 intersections.push(intersection)
             }
@@ -23,7 +23,7 @@ fn claim_overlaps(claims: &[Rect<u32>]) -> Vec<Rect<u32>> {
 
 /// Naively finds the overlap between claims by exhaustively tracking each
 /// square inch in claim overlaps.
-fn overlap_area_naive(claims: &[Rect<u32>]) -> u32 {
+pub fn overlap_area_naive(claims: &[Rect<u32>]) -> u32 {
     let intersections = claim_overlaps(claims);
     
     // Naive solution:
@@ -48,14 +48,16 @@ fn overlap_area_naive(claims: &[Rect<u32>]) -> u32 {
 /// - the part of r that is to the bottom of the current overlap; and
 /// - the part of r that is to the left of the current overlap.
 ///
-/// Assumes there is at least one claim.
-fn overlap_area(claims: &[Rect<u32>]) -> u32 {
+pub fn overlap_area(claims: &[Rect<u32>]) -> u32 {
     let overlaps = claim_overlaps(claims);
 
-    let bound = overlaps
+    let bound = match overlaps
         .iter()
         .fold(None, |bound, &claim| Some(bound.unwrap_or(claim).union(&claim)))
-        .unwrap();
+    {
+        Some(bound) => bound,
+        None => return 0,
+    };
     
     fn area(bound: &Rect<u32>, overlaps: &[Rect<u32>]) -> u32 {
         if overlaps.is_empty() {
@@ -88,19 +90,19 @@ fn overlap_area(claims: &[Rect<u32>]) -> u32 {
                 BBBBBBBB
             */
             
-            let bound_top = Rect::from_points(&[
+            let bound_top = Rect::from_points([
                 Point2D::new(bound.min_x(), bound.min_y()),
                 Point2D::new(bound.max_x(), overlap.min_y())
             ]);
-            let bound_right = Rect::from_points(&[
+            let bound_right = Rect::from_points([
                 Point2D::new(overlap.max_x(), overlap.min_y()),
                 Point2D::new(bound.max_x(), overlap.max_y())
             ]);
-            let bound_bottom = Rect::from_points(&[
+            let bound_bottom = Rect::from_points([
                 Point2D::new(bound.min_x(), overlap.max_y()),
                 Point2D::new(bound.max_x(), bound.max_y())
             ]);
-            let bound_left = Rect::from_points(&[
+            let bound_left = Rect::from_points([
                 Point2D::new(bound.min_x(), overlap.min_y()),
                 Point2D::new(overlap.min_x(), overlap.max_y())
             ]);
@@ -128,7 +130,7 @@ fn overlap_area(claims: &[Rect<u32>]) -> u32 {
 
 /// Finds the id of the (first) claim that overlaps no other claim.
 /// Note that claim ids in the input monotonically increase by 1, starting at 1.
-fn no_overlap(claims: &[Rect<u32>]) -> Option<usize> {
+pub fn no_overlap(claims: &[Rect<u32>]) -> Option<usize> {
     for (idx, claim) in claims.iter().enumerate() {
         if !claims
             .iter()
@@ -144,39 +146,80 @@ fn no_overlap(claims: &[Rect<u32>]) -> Option<usize> {
 }
 
 /// Parses a list of claim Strings into claim Rects.
-fn parse_claims(lines: &[String]) -> Result<Vec<Rect<u32>>> {
-    let re = regex::Regex::new(r"\D")?;  // Matches all non-digits.
-    
-    lines
+pub fn parse_claims(lines: &[&str]) -> Result<Vec<Rect<u32>>> {
+    parse::lines(lines)
         .iter()
-        .map(|s| {
-            // Replace all non-digits by spaces.
-            let nstr = re.replace_all(s, " ");
-            
-            // Parse all numbers.
-            let numbers = nstr
-                .split(' ')
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<u32>().map_err(|e| e.into()))
-                .collect::<Result<Vec<_>>>()?;
-            
+        .map(|line| {
+            let [_, x, y, width, height] =
+                line.unsigned_n("a claim like `#1 @ 1,3: 4x4`")?;
+
             Ok(Rect::new(
-                Point2D::new(numbers[1], numbers[2]),
-                Size2D::new(numbers[3], numbers[4])
+                Point2D::new(x, y),
+                Size2D::new(width, height)
             ))
         })
         .collect::<Result<Vec<_>>>()
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december03.txt")?;
-    let claims = parse_claims(&lines)?;
-    
-    println!("Part 1 (naive):            {:#?}", overlap_area_naive(&claims));
-    println!("Part 1 (divide & conquer): {:#?}", overlap_area(&claims));
-    println!("Part 2: {:#?}", no_overlap(&claims).unwrap_or(0));
-    
-    Ok(())
+impl Solution for December03 {
+    type Input = Vec<Rect<u32>>;
+    type Params = ();
+    type Part1 = u32;
+    type Part2 = usize;
+
+    fn parse(lines: &[&str]) -> Result<Vec<Rect<u32>>> {
+        parse_claims(lines)
+    }
+
+    fn part1(claims: &Vec<Rect<u32>>, _: &()) -> Result<u32> {
+        Ok(overlap_area(claims))
+    }
+
+    fn part2(claims: &Vec<Rect<u32>>, _: &()) -> Result<usize> {
+        Ok(no_overlap(claims).unwrap_or(0))
+    }
+
+    /// `25 * size` claims on a square of 1000 inches. One of them, at a
+    /// random place in the list, overlaps no other claim.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut claim = || {
+            let (width, height) = (rng.range(5, 30), rng.range(5, 30));
+            Rect::new(
+                Point2D::new(
+                    rng.range(0, 1000 - width),
+                    rng.range(0, 1000 - height),
+                ),
+                Size2D::new(width, height),
+            )
+        };
+
+        let intact = claim();
+        let n = (size * 25).max(1);
+        let mut claims = Vec::with_capacity(n);
+        claims.push(intact);
+        while claims.len() < n {
+            let other = claim();
+            if !other.intersects(&intact) {
+                claims.push(other);
+            }
+        }
+        rng.shuffle(&mut claims);
+
+        claims
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| {
+                format!(
+                    "#{} @ {},{}: {}x{}\n",
+                    idx + 1,
+                    c.origin.x,
+                    c.origin.y,
+                    c.size.width,
+                    c.size.height
+                )
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -186,17 +229,15 @@ mod tests {
     #[test]
     fn parse() {
         let claims_str = vec![
-            "#1 @ 1,3: 4x4".to_owned(),
-            "#2 @ 3,1: 4x4".to_owned(),
-            "#3 @ 5,5: 2x2".to_owned(),
-            "#4 @ 6,6: 1x1".to_owned()
+            "#1 @ 1,3: 4x4",
+            "#2 @ 3,1: 4x4",
+            "#3 @ 5,5: 2x2",
+            "#4 @ 6,6: 1x1"
         ];
-        let sizes = vec![
-            (1,3,4,4),
+        let sizes = [(1,3,4,4),
             (3,1,4,4),
             (5,5,2,2),
-            (6,6,1,1)
-        ];
+            (6,6,1,1)];
         
         let claims = parse_claims(&claims_str).unwrap();
         
@@ -214,8 +255,8 @@ mod tests {
     #[test]
     fn overlap() {
         let claims_str = vec![
-            "#1 @ 1,3: 4x4".to_owned(),
-            "#2 @ 3,1: 4x4".to_owned()
+            "#1 @ 1,3: 4x4",
+            "#2 @ 3,1: 4x4"
         ];
         
         let claims = parse_claims(&claims_str).unwrap();
@@ -228,5 +269,8 @@ mod tests {
             && overlap[0].size.width == 2
             && overlap[0].size.height == 2
         );
+
+        assert!(claim_overlaps(&[]).is_empty());
+        assert!(overlap_area(&[]) == 0);
     }
 }
@@ -1,11 +1,13 @@
-extern crate regex;
-use std::collections::HashMap;
-mod utils;
+use error::Error;
+use generate::Rng;
+use parse::{self, Line};
+use solution::{Result, Solution};
+use std::collections::{HashMap, HashSet};
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December04;
 
 #[derive(Debug)]
-enum Event {
+pub enum Event {
     Start(i32),
     Sleep,
     Wake
@@ -14,39 +16,43 @@ enum Event {
 /// Parse events into a vector of time+Event tuples. Times are a direct integer
 /// representation of [year][month][day][hour][minute]. For example,
 /// "2018-12-25 23:57" is 201812252357
-fn parse_events(lines: &[String]) -> Result<Vec<(i64, Event)>> {
-    let re = regex::Regex::new(r"^\[\d{4}-\d{2}-\d{2} \d{2}:(?P<minute>\d{2})\] ((?P<sleep>falls asleep)|(?P<wake>wakes up)|(Guard #(?P<guard>\d+) begins shift))$")?;
-    
-    let mut lines: Vec<String> = lines.iter().cloned().collect();
-    lines.sort();
+pub fn parse_events(lines: &[&str]) -> Result<Vec<(i64, Event)>> {
+    // Sort by time; the lines remember their line numbers.
+    let mut lines = parse::lines(lines);
+    lines.sort_by_key(|line| line.text);
 
-    let v = lines
+    lines
         .iter()
-        .map(|s| {
-            let captures = re.captures(s).ok_or_else(|| format!("String {} does not match", s))?;
-
-            let minute = captures.name("minute").ok_or("Parse err")?.as_str();
-            let sleep = captures.name("sleep");
-            let wake = captures.name("wake");
-            let guard = captures.name("guard");
-            
-            let event = match (guard, sleep, wake) {
-                (Some(n), None, None) => Ok(Event::Start(n.as_str().parse::<i32>()?)),
-                (None, Some(_), None) => Ok(Event::Sleep),
-                (None, None, Some(_)) => Ok(Event::Wake),
-                _ => Err("Parse failure")
-            };
-            
-            Ok((minute.parse::<i64>()?, event?))
+        .map(|line| {
+            let (stamp, event) = line.after("[")?.split_once("] ")?;
+            let [_, _, _, _, minute] =
+                stamp.unsigned_n("a time like `1518-11-01 00:00`")?;
+            Ok((minute, parse_event(event)?))
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect()
+}
 
-    Ok(v)
+/// Parse an event like `falls asleep` or `Guard #10 begins shift`.
+fn parse_event(event: Line) -> Result<Event> {
+    match event.text {
+        "falls asleep" => Ok(Event::Sleep),
+        "wakes up" => Ok(Event::Wake),
+        _ => {
+            let guard = event.after("Guard #").map_err(|_| {
+                event.error("expected a guard event")
+            })?;
+            let (guard, rest) = guard.split_once(" ")?;
+            if rest.text != "begins shift" {
+                return Err(rest.error("expected `begins shift`"));
+            }
+            Ok(Event::Start(guard.parse()?))
+        }
+    }
 }
 
 /// Split a vector of times and Events into a map of such vectors, with guard
 /// shift start events removed.
-fn events_by_guard(events: Vec<(i64, Event)>) -> HashMap<i32, Vec<(i64, Event)>> {
+pub fn events_by_guard(events: Vec<(i64, Event)>) -> HashMap<i32, Vec<(i64, Event)>> {
     let mut map = HashMap::new();
     let mut current_guard = -1;
     
@@ -64,7 +70,7 @@ fn events_by_guard(events: Vec<(i64, Event)>) -> HashMap<i32, Vec<(i64, Event)>>
     map
 }
 
-fn part1(guard_events: &HashMap<i32, Vec<(i64, Event)>>) -> Result<i64> {
+pub fn part1(guard_events: &HashMap<i32, Vec<(i64, Event)>>) -> Result<i64> {
     let mut sleep_time: Vec<(i32, i64)> = Vec::new();
     
     for (guard, events) in guard_events {
@@ -82,7 +88,7 @@ fn part1(guard_events: &HashMap<i32, Vec<(i64, Event)>>) -> Result<i64> {
         ))
     }
     
-    let (guard, _) = *sleep_time.iter().max_by_key(|(_, t)| t).ok_or("No events")?;
+    let (guard, _) = *sleep_time.iter().max_by_key(|(_, t)| t).ok_or_else(|| Error::unsolvable("No events"))?;
     let mut sleep_minute_count: HashMap<i64, i32> = HashMap::new();
     let mut sleep_start_minute = 0;
     
@@ -99,12 +105,12 @@ fn part1(guard_events: &HashMap<i32, Vec<(i64, Event)>>) -> Result<i64> {
         }
     }
     
-    let (minute, _) = sleep_minute_count.iter().max_by_key(|(_, t)| *t).ok_or("No events")?;
+    let (minute, _) = sleep_minute_count.iter().max_by_key(|(_, t)| *t).ok_or_else(|| Error::unsolvable("No events"))?;
     
     Ok(i64::from(guard) * minute)
 }
 
-fn part2(guard_events: &HashMap<i32, Vec<(i64, Event)>>) -> Result<i64> {
+pub fn part2(guard_events: &HashMap<i32, Vec<(i64, Event)>>) -> Result<i64> {
     let mut max_guard = 0;
     let mut max_minute = 0;
     let mut max_count = 0;
@@ -125,7 +131,7 @@ fn part2(guard_events: &HashMap<i32, Vec<(i64, Event)>>) -> Result<i64> {
             }
         }
         
-        let (minute, count) = sleep_minute_count.iter().max_by_key(|(_, t)| *t).ok_or("No events")?;
+        let (minute, count) = sleep_minute_count.iter().max_by_key(|(_, t)| *t).ok_or_else(|| Error::unsolvable("No events"))?;
         
         if *count > max_count {
             max_guard = *guard;
@@ -137,12 +143,92 @@ fn part2(guard_events: &HashMap<i32, Vec<(i64, Event)>>) -> Result<i64> {
     Ok(i64::from(max_guard) * max_minute)
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december04.txt")?;
-    let events = events_by_guard(parse_events(&lines)?);
+impl Solution for December04 {
+    type Input = HashMap<i32, Vec<(i64, Event)>>;
+    type Params = ();
+    type Part1 = i64;
+    type Part2 = i64;
 
-    println!("Part 1: {:#?}", part1(&events)?);
-    println!("Part 2: {:#?}", part2(&events)?);
-    
-    Ok(())
+    fn parse(lines: &[&str]) -> Result<Self::Input> {
+        Ok(events_by_guard(parse_events(lines)?))
+    }
+
+    fn part1(guard_events: &Self::Input, _: &()) -> Result<i64> {
+        part1(guard_events)
+    }
+
+    fn part2(guard_events: &Self::Input, _: &()) -> Result<i64> {
+        part2(guard_events)
+    }
+
+    /// Guard logs of `size` nights, at most 364, in random order. There are
+    /// `size / 5` guards, at least two, and all guards that fall asleep do
+    /// so during the first night they are on duty.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        /// The date of a day of the year 1518, counting from 0.
+        fn date(mut day: usize) -> String {
+            let months = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+            let mut month = 0;
+            while day >= months[month] {
+                day -= months[month];
+                month += 1;
+            }
+            format!("1518-{:02}-{:02}", month + 1, day + 1)
+        }
+
+        let nights = size.clamp(1, 364);
+        let guards = (0..(size / 5).max(2))
+            .map(|_| rng.range(10, 3500))
+            .collect::<Vec<_>>();
+        let first = rng.index(365 - nights) + 1;
+
+        let mut log = Vec::new();
+        let mut asleep = HashSet::new();
+        for night in first..first + nights {
+            let guard = *rng.choose(&guards);
+            if rng.chance(0.5) {
+                let minute = rng.range(0, 5);
+                log.push(format!(
+                    "[{} 00:{:02}] Guard #{} begins shift",
+                    date(night),
+                    minute,
+                    guard
+                ));
+            } else {
+                let minute = rng.range(45, 59);
+                log.push(format!(
+                    "[{} 23:{:02}] Guard #{} begins shift",
+                    date(night - 1),
+                    minute,
+                    guard
+                ));
+            }
+
+            let naps = if asleep.insert(guard) {
+                rng.range(1, 3)
+            } else {
+                rng.range(0, 3)
+            };
+            let mut minutes = (6..60).collect::<Vec<i64>>();
+            rng.shuffle(&mut minutes);
+            minutes.truncate(naps as usize * 2);
+            minutes.sort();
+            for (n, minute) in minutes.iter().enumerate() {
+                let event = if n % 2 == 0 {
+                    "falls asleep"
+                } else {
+                    "wakes up"
+                };
+                log.push(format!(
+                    "[{} 00:{:02}] {}",
+                    date(night),
+                    minute,
+                    event
+                ));
+            }
+        }
+        rng.shuffle(&mut log);
+
+        log.iter().map(|line| format!("{}\n", line)).collect()
+    }
 }
@@ -1,15 +1,16 @@
-extern crate regex;
-mod utils;
+use generate::Rng;
+use parse;
+use solution::{Result, Solution};
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December05;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-enum Unit {
+pub enum Unit {
     Positive(char),
     Negative(char)
 }
 
-trait UnitType {
+pub trait UnitType {
     fn get_unit_type(&self) -> char;
     fn is_opposite(&self, other: &Self) -> bool;
 }
@@ -27,19 +28,24 @@ impl UnitType for Unit {
     }
 }
 
-fn get_polymer(lines: &[String]) -> Result<Vec<Unit>> {
-    if lines.len() != 1 {
-        return Err("Expected only one line of input".into())
-    }
+pub fn get_polymer(lines: &[&str]) -> Result<Vec<Unit>> {
+    let line = parse::single(lines, "a polymer")?;
 
-    Ok(
-        lines[0].chars()
-            .map(|c| if c.is_ascii_uppercase() { Unit::Positive(c.to_ascii_lowercase()) } else { Unit::Negative(c) })
-            .collect()
-    )
+    line.text
+        .char_indices()
+        .map(|(offset, c)| {
+            if !c.is_ascii_alphabetic() {
+                Err(line.error_at(offset, format!("invalid unit `{}`", c)))
+            } else if c.is_ascii_uppercase() {
+                Ok(Unit::Positive(c.to_ascii_lowercase()))
+            } else {
+                Ok(Unit::Negative(c))
+            }
+        })
+        .collect()
 }
 
-fn react(polymer: &[Unit]) -> Vec<&Unit> {
+pub fn react(polymer: &[Unit]) -> Vec<&Unit> {
     polymer.iter().fold(Vec::new(), |mut reacted, unit| {
         if !reacted.is_empty() && unit.is_opposite(reacted.last().unwrap()) {
             reacted.pop();
@@ -50,11 +56,11 @@ fn react(polymer: &[Unit]) -> Vec<&Unit> {
     })
 }
 
-fn remove_unit(polymer: &[Unit], unit_type: char) -> Vec<Unit> {
+pub fn remove_unit(polymer: &[Unit], unit_type: char) -> Vec<Unit> {
     polymer.iter().filter(|u| u.get_unit_type() != unit_type).cloned().collect()
 }
 
-fn part2(polymer: &[&Unit]) -> usize {
+pub fn part2(polymer: &[&Unit]) -> usize {
     let ascii_iter = (0..26).map(|x| (x + b'a') as char);
     ascii_iter.fold(polymer.len(), |shortest, char| {
         std::cmp::min(
@@ -68,13 +74,49 @@ fn part2(polymer: &[&Unit]) -> usize {
     })
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december05.txt")?;
-    let polymer = get_polymer(&lines)?;
-    let reacted = react(&polymer);
+impl Solution for December05 {
+    type Input = Vec<Unit>;
+    type Params = ();
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(lines: &[&str]) -> Result<Vec<Unit>> {
+        get_polymer(lines)
+    }
+
+    fn part1(polymer: &Vec<Unit>, _: &()) -> Result<usize> {
+        Ok(react(polymer).len())
+    }
 
-    println!("Part 1: {:#?}", reacted.len());
-    println!("Part 2: {:#?}", part2(&reacted));
-    
-    Ok(())
+    fn part2(polymer: &Vec<Unit>, _: &()) -> Result<usize> {
+        Ok(part2(&react(polymer)))
+    }
+
+    /// A polymer of `200 * size` units, many of which react: units are
+    /// often followed by the opposite of the last unit that has not reacted.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut polymer = String::with_capacity(size * 200);
+        let mut unreacted = Vec::new();
+        for _ in 0..(size * 200).max(1) {
+            let unit = match unreacted.last() {
+                Some(&unit) if rng.chance(0.45) => {
+                    unreacted.pop();
+                    // Swap the case to make the opposite unit.
+                    unit ^ 0x20
+                }
+                _ => {
+                    let unit = b'a' + rng.below(26) as u8;
+                    let unit = if rng.chance(0.5) {
+                        unit.to_ascii_uppercase()
+                    } else {
+                        unit
+                    };
+                    unreacted.push(unit);
+                    unit
+                }
+            };
+            polymer.push(unit as char);
+        }
+        polymer
+    }
 }
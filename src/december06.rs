@@ -1,24 +1,53 @@
-extern crate euclid;
-use euclid::{Rect, Point2D, Size2D};
-use std::collections::BTreeMap;
-mod utils;
-
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-
-fn parse_coordinates(lines: &[String]) -> Result<Vec<Point2D<i32>>> {
-	lines.iter()
-		.map(|s| {
-			let parts: Vec<&str> = s.split(", ").collect();
-			Ok(Point2D::new(parts[0].parse()?, parts[1].parse()?))
+use euclid::{Point2D, Rect, Size2D};
+use error::{self, Error};
+use generate::Rng;
+use parse;
+use solution::{self, Result, Solution};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub struct December06;
+
+pub struct Params {
+    /// Part 2 looks for the region with a total distance to all coordinates
+    /// below this.
+    pub max_total_distance: i32,
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            max_total_distance: 10_000,
+        }
+    }
+}
+
+impl solution::Params for Params {
+    fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "max_total_distance" => {
+                self.max_total_distance = error::parse_param(name, value)?
+            }
+            _ => Err(Error::Param(format!("Unknown parameter `{}`", name)))?,
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_coordinates(lines: &[&str]) -> Result<Vec<Point2D<i32>>> {
+	parse::lines(lines)
+		.iter()
+		.map(|line| {
+			let [x, y] = line.signed_n("a coordinate like `1, 6`")?;
+			Ok(Point2D::new(x, y))
 		})
 		.collect()
 }
 
-fn distance(coord: Point2D<i32>, other_coord: Point2D<i32>) -> i32 {
+pub fn distance(coord: Point2D<i32>, other_coord: Point2D<i32>) -> i32 {
 	(coord.x - other_coord.x).abs() + (coord.y - other_coord.y).abs()
 }
 
-fn part1(coords: &[Point2D<i32>]) -> i32 {
+pub fn part1(coords: &[Point2D<i32>]) -> i32 {
 	let bbox_ = Rect::from_points(coords);
 	let bbox = Rect::new(bbox_.origin, Size2D::new(bbox_.size.width+1, bbox_.size.height+1));
 	let inside_box = bbox.inflate(-1, -1);
@@ -30,7 +59,7 @@ fn part1(coords: &[Point2D<i32>]) -> i32 {
 		for y in bbox.origin.y..=bbox.origin.y + bbox.size.height {
 			let point = Point2D::new(x, y);
 			let mut closest = None;
-			let mut min_distance = std::i32::MAX;
+			let mut min_distance = i32::MAX;
 			for coord in coords.iter() {
 				let dist = distance(*coord, point);
 				if dist < min_distance {
@@ -61,7 +90,7 @@ fn part1(coords: &[Point2D<i32>]) -> i32 {
 	})
 }
 
-fn part2(coords: &[Point2D<i32>]) -> i32 {
+pub fn part2(coords: &[Point2D<i32>], max_total_distance: i32) -> i32 {
 	let bbox_ = Rect::from_points(coords);
 	let bbox = Rect::new(bbox_.origin, Size2D::new(bbox_.size.width+1, bbox_.size.height+1));
 	let mut size = 0;
@@ -69,7 +98,7 @@ fn part2(coords: &[Point2D<i32>]) -> i32 {
 	for x in bbox.origin.x..=bbox.origin.x + bbox.size.width {
 		for y in bbox.origin.y..=bbox.origin.y + bbox.size.height {
 			let point = Point2D::new(x, y);
-			if coords.iter().fold(0, |acc, coord| acc + distance(*coord, point)) < 10_000 {
+			if coords.iter().fold(0, |acc, coord| acc + distance(*coord, point)) < max_total_distance {
 				size += 1;
 			}
 		}
@@ -77,13 +106,38 @@ fn part2(coords: &[Point2D<i32>]) -> i32 {
 	size
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december06.txt")?;
-    let coordinates = parse_coordinates(&lines)?;
+impl Solution for December06 {
+    type Input = Vec<Point2D<i32>>;
+    type Params = Params;
+    type Part1 = i32;
+    type Part2 = i32;
 
-    println!("Part 1: {:#?}", part1(&coordinates));
-    println!("Part 2: {:#?}", part2(&coordinates));
-    
-    Ok(())
-}
+    fn parse(lines: &[&str]) -> Result<Vec<Point2D<i32>>> {
+        parse_coordinates(lines)
+    }
+
+    fn part1(coordinates: &Vec<Point2D<i32>>, _: &Params) -> Result<i32> {
+        Ok(part1(coordinates))
+    }
 
+    fn part2(coordinates: &Vec<Point2D<i32>>, params: &Params) -> Result<i32> {
+        Ok(part2(coordinates, params.max_total_distance))
+    }
+
+    /// `size` distinct coordinates in a square with sides of `6 * size`.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let side = (size as i64 * 6).max(10);
+        let mut coordinates = BTreeSet::new();
+        while coordinates.len() < size.max(1) {
+            coordinates
+                .insert((rng.range(40, 40 + side), rng.range(40, 40 + side)));
+        }
+        let mut coordinates = coordinates.into_iter().collect::<Vec<_>>();
+        rng.shuffle(&mut coordinates);
+
+        coordinates
+            .iter()
+            .map(|(x, y)| format!("{}, {}\n", x, y))
+            .collect()
+    }
+}
@@ -1,20 +1,59 @@
+use error::{self, Error};
+use generate::Rng;
+use parse::{self, Line};
+use solution::{self, Result, Solution};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::BinaryHeap;
-mod utils;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December07;
+
+pub struct Params {
+    /// The number of workers in part 2.
+    pub workers: usize,
+    /// The duration of every step in part 2, on top of its position in the
+    /// alphabet.
+    pub base_duration: i32,
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            workers: 5,
+            base_duration: 60,
+        }
+    }
+}
+
+impl solution::Params for Params {
+    fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "workers" => {
+                self.workers = error::parse_param(name, value)?;
+                if self.workers == 0 {
+                    Err(Error::Param("At least one worker is needed".into()))?
+                }
+            }
+            "base_duration" => {
+                self.base_duration = error::parse_param(name, value)?
+            }
+            _ => Err(Error::Param(format!("Unknown parameter `{}`", name)))?,
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-struct Step {
-	name: char,
-	dependencies: Vec<char>
+pub struct Step {
+	pub name: char,
+	pub dependencies: Vec<char>
 }
 
 impl PartialOrd for Step {
 	fn partial_cmp(&self, other: &Step) -> Option<Ordering> {
-        self.name.partial_cmp(&other.name).map(|ord| ord.reverse())
+        Some(self.cmp(other))
     }
 }
 
@@ -24,12 +63,23 @@ impl Ord for Step {
     }
 }
 
-fn parse_steps(lines: &[String]) -> Result<HashMap<char, Step>> {
+/// Parse a step name, a single capital letter.
+fn parse_name(name: Line) -> Result<char> {
+	let mut chars = name.text.chars();
+	match (chars.next(), chars.next()) {
+		(Some(c), None) if c.is_ascii_uppercase() => Ok(c),
+		_ => Err(name.error("expected a step name")),
+	}
+}
+
+pub fn parse_steps(lines: &[&str]) -> Result<HashMap<char, Step>> {
 	let mut map = HashMap::new();
 
-	for line in lines {
-		let char1 = line.chars().nth(5).ok_or_else(|| format!("Malformed string: {:#?}", line))?;
-		let char2 = line.chars().nth(36).ok_or_else(|| format!("Malformed string: {:#?}", line))?;
+	for line in parse::lines(lines) {
+		let (char1, rest) = line.after("Step ")?
+			.split_once(" must be finished before step ")?;
+		let (char2, _) = rest.split_once(" can begin.")?;
+		let (char1, char2) = (parse_name(char1)?, parse_name(char2)?);
 
 		map.entry(char1).or_insert(Step { name: char1, dependencies: Vec::new() });
 		map.entry(char2).or_insert(Step { name: char2, dependencies: Vec::new() }).dependencies.push(char1);
@@ -42,11 +92,11 @@ fn parse_steps(lines: &[String]) -> Result<HashMap<char, Step>> {
 	Ok(map)
 }
 
-fn work(deps: &HashMap<char, Step>, num_workers: usize, step_duration: &Fn(char) -> i32) -> (String, i32) {
+pub fn work(deps: &HashMap<char, Step>, num_workers: usize, step_duration: &dyn Fn(char) -> i32) -> Result<(String, i32)> {
 	let mut time_elapsed = 0;
 	let mut next_task_avail = BinaryHeap::new();	
 	// Note we use negative worker time as a hack, as the collection is a max-heap.
-	let mut next_worker_avail: BinaryHeap<i32> = std::iter::repeat(0).take(num_workers).collect();
+	let mut next_worker_avail: BinaryHeap<i32> = std::iter::repeat_n(0, num_workers).collect();
 	let mut finish_at: BTreeMap<i32, _> = BTreeMap::new();
 	let mut step_dep_count = HashMap::new();
 
@@ -65,7 +115,8 @@ fn work(deps: &HashMap<char, Step>, num_workers: usize, step_duration: &Fn(char)
 	while done.len() < deps.len() {
 		if next_task_avail.is_empty() {
 			// No tasks can be started. Wait until the next task is finished.
-			let next_time = finish_at.keys().nth(0).unwrap();
+			let next_time = finish_at.keys().next()
+				.ok_or_else(|| Error::unsolvable("The steps depend on each other in a cycle"))?;
 			while next_worker_avail.peek().unwrap() > &-next_time {
 				next_worker_avail.pop();
 				next_worker_avail.push(- *next_time);
@@ -100,15 +151,51 @@ fn work(deps: &HashMap<char, Step>, num_workers: usize, step_duration: &Fn(char)
 		finish_at.remove(&time_elapsed);
 	}
 
-	(done.iter().collect(), time_elapsed)
+	Ok((done.iter().collect(), time_elapsed))
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december07.txt")?;
-    let deps = parse_steps(&lines)?;
+impl Solution for December07 {
+    type Input = HashMap<char, Step>;
+    type Params = Params;
+    type Part1 = String;
+    type Part2 = i32;
 
-    println!("Part 1: {:?}", work(&deps, 1, &|_| 0));
-    println!("Part 2: {:?}", work(&deps, 5, &|c| 60 + 1 + c as i32 - 'A' as i32));
-    
-    Ok(())
+    fn parse(lines: &[&str]) -> Result<HashMap<char, Step>> {
+        parse_steps(lines)
+    }
+
+    fn part1(deps: &HashMap<char, Step>, _: &Params) -> Result<String> {
+        let (done, _) = work(deps, 1, &|_| 0)?;
+        Ok(done)
+    }
+
+    fn part2(deps: &HashMap<char, Step>, params: &Params) -> Result<i32> {
+        let (_, time) = work(deps, params.workers, &|c| {
+            params.base_duration + 1 + c as i32 - 'A' as i32
+        })?;
+        Ok(time)
+    }
+
+    /// Dependencies between `size` steps, at most 26, in random order. The
+    /// steps are put in a random order, and each depends on one to three
+    /// earlier ones, so there are no cycles.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut steps = (b'A'..=b'Z').map(char::from).collect::<Vec<_>>();
+        rng.shuffle(&mut steps);
+        steps.truncate(size.clamp(2, 26));
+
+        let mut lines = BTreeSet::new();
+        for (n, step) in steps.iter().enumerate().skip(1) {
+            for _ in 0..rng.range(1, 3) {
+                let dependency = steps[rng.index(n)];
+                lines.insert(format!(
+                    "Step {} must be finished before step {} can begin.\n",
+                    dependency, step
+                ));
+            }
+        }
+        let mut lines = lines.into_iter().collect::<Vec<_>>();
+        rng.shuffle(&mut lines);
+        lines.concat()
+    }
 }
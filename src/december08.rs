@@ -1,62 +1,127 @@
-mod utils;
+use error::Error;
+use generate::Rng;
+use parse;
+use solution::{Result, Solution};
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December08;
 
-fn sum1(tree: &[u32]) -> (&[u32], u32) {
+/// Sums the metadata of the tree, returning the remainder of the input after
+/// the tree. Returns None if the input ends before the tree does.
+pub fn sum1(tree: &[u32]) -> Option<(&[u32], u32)> {
 	let mut tree = tree;
 	let mut sum_metadata = 0;
-    let num_nodes = tree[0];
-    let num_metadata = tree[1];
+    let num_nodes = *tree.first()?;
+    let num_metadata = *tree.get(1)?;
 
     tree = &tree[2..];
     for _ in 0..num_nodes {
-    	let (new_tree, sum) = sum1(tree);
+    	let (new_tree, sum) = sum1(tree)?;
     	tree = new_tree;
     	sum_metadata += sum;
     }
 
-    for i in 0..num_metadata {
-    	sum_metadata += tree[i as usize];
+    for metadata in tree.get(..num_metadata as usize)? {
+    	sum_metadata += metadata;
     }
 
-    (&tree[num_metadata as usize..], sum_metadata)
+    Some((&tree[num_metadata as usize..], sum_metadata))
 }
 
-fn sum2(tree: &[u32]) -> (&[u32], u32) {
+/// Finds the value of the root node of the tree, returning the remainder of
+/// the input after the tree. Returns None if the input ends before the tree
+/// does.
+pub fn sum2(tree: &[u32]) -> Option<(&[u32], u32)> {
 	let mut tree = tree;
 	let mut sum_metadata = 0;
 	let mut child_val = Vec::new();
-    let num_nodes = tree[0];
-    let num_metadata = tree[1];
+    let num_nodes = *tree.first()?;
+    let num_metadata = *tree.get(1)?;
 
     tree = &tree[2..];
     for _ in 0..num_nodes {
-    	let (new_tree, sum) = sum2(tree);
+    	let (new_tree, sum) = sum2(tree)?;
     	tree = new_tree;
     	child_val.push(sum);
     }
 
-    for i in 0..num_metadata {
+    for &metadata in tree.get(..num_metadata as usize)? {
     	if num_nodes == 0 {
-    		sum_metadata += tree[i as usize];
-    	} else {
-			let idx = tree[i as usize] - 1;
-			if (idx as usize) < child_val.len() {
-				sum_metadata += child_val[idx as usize];
-			}
-
+    		sum_metadata += metadata;
+    	} else if let Some(&val) = child_val.get((metadata as usize).wrapping_sub(1)) {
+			sum_metadata += val;
     	}
     }
 
-    (&tree[num_metadata as usize..], sum_metadata)
+    Some((&tree[num_metadata as usize..], sum_metadata))
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december08.txt")?;
-    let tree: Vec<u32> = lines[0].split(" ").map(|c| c.parse::<u32>().unwrap()).collect();
+impl Solution for December08 {
+    type Input = Vec<u32>;
+    type Params = ();
+    type Part1 = u32;
+    type Part2 = u32;
+
+    fn parse(lines: &[&str]) -> Result<Vec<u32>> {
+        let line = parse::single(lines, "a license tree")?;
+        let numbers = line.split(" ");
+        let tree = numbers
+            .iter()
+            .map(|number| number.parse())
+            .collect::<Result<Vec<u32>>>()?;
+
+        match sum1(&tree) {
+            None => Err(line.error_at(line.text.len(), "unexpected end of tree")),
+            Some((rest, _)) if !rest.is_empty() => {
+                let first_unused = numbers[tree.len() - rest.len()];
+                Err(first_unused.error("expected the end of the tree"))
+            }
+            Some(_) => Ok(tree),
+        }
+    }
+
+    fn part1(tree: &Vec<u32>, _: &()) -> Result<u32> {
+        let (_, sum) =
+            sum1(tree).ok_or_else(|| Error::unsolvable("Incomplete tree"))?;
+        Ok(sum)
+    }
 
-    println!("Part 1: {:?}", sum1(&tree));
-    println!("Part 2: {:?}", sum2(&tree));
-    
-    Ok(())
+    fn part2(tree: &Vec<u32>, _: &()) -> Result<u32> {
+        let (_, sum) =
+            sum2(tree).ok_or_else(|| Error::unsolvable("Incomplete tree"))?;
+        Ok(sum)
+    }
+
+    /// A license tree of `20 * size` nodes, each with one to three metadata
+    /// entries. Every node hangs below a random earlier one, so the tree is
+    /// only logarithmically deep.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        fn write(
+            node: usize,
+            children: &[Vec<usize>],
+            rng: &mut Rng,
+            out: &mut Vec<String>,
+        ) {
+            let metadata = rng.range(1, 3);
+            out.push(children[node].len().to_string());
+            out.push(metadata.to_string());
+            for &child in &children[node] {
+                write(child, children, rng, out);
+            }
+            for _ in 0..metadata {
+                // Entries can refer to children that do not exist.
+                let max = children[node].len() as i64 + 2;
+                out.push(rng.range(1, max.min(9)).to_string());
+            }
+        }
+
+        let n = (size * 20).max(1);
+        let mut children = vec![Vec::new(); n];
+        for node in 1..n {
+            children[rng.index(node)].push(node);
+        }
+
+        let mut out = Vec::new();
+        write(0, &children, rng, &mut out);
+        out.join(" ")
+    }
 }
@@ -1,7 +1,9 @@
-mod utils;
+use generate::Rng;
+use parse;
+use solution::{Result, Solution};
 use std::collections::{VecDeque, HashMap};
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December09;
 
 fn rotate(table: &mut VecDeque<u64>, rot: i32) {
     let mut rot = rot;
@@ -20,11 +22,11 @@ fn rotate(table: &mut VecDeque<u64>, rot: i32) {
     }
 }
 
-fn play(num_players: u64, last_value: u64) -> u64 {
+pub fn play(num_players: u64, last_value: u64) -> u64 {
     let mut scores = HashMap::new();
 
     // Use a deque for quick insertion and retrieval at the front *and* back.
-    let mut table: VecDeque<u64> = vec![0].iter().cloned().collect();
+    let mut table: VecDeque<u64> = [0].iter().cloned().collect();
     table.reserve_exact((last_value * 22 / 23) as usize);
     let mut n = 1;
     loop {
@@ -39,21 +41,47 @@ fn play(num_players: u64, last_value: u64) -> u64 {
         }
 
         if n == last_value {
-            break *scores.values().max().unwrap()
+            break scores.values().max().cloned().unwrap_or(0)
         }
 
         n += 1;
     }
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december09.txt")?;
-    let config: Vec<&str> = lines[0].split(" ").collect();
-    let num_players = config[0].parse::<u64>()?;
-    let last_marble = config[6].parse::<u64>()?;
+impl Solution for December09 {
+    /// The number of players and the value of the last marble.
+    type Input = (u64, u64);
+    type Params = ();
+    type Part1 = u64;
+    type Part2 = u64;
 
-    println!("Part 1: {:?}", play(num_players, last_marble));
-    println!("Part 2: {:?}", play(num_players, last_marble * 100));
-    
-    Ok(())
+    fn parse(lines: &[&str]) -> Result<(u64, u64)> {
+        let expected = "`<n> players; last marble is worth <n> points`";
+        let line = parse::single(lines, expected)?;
+        let [num_players, last_marble] = line.unsigned_n(expected)?;
+        if num_players == 0 {
+            return Err(line.error("expected at least one player"));
+        }
+
+        Ok((num_players, last_marble))
+    }
+
+    fn part1(&(num_players, last_marble): &(u64, u64), _: &()) -> Result<u64> {
+        Ok(play(num_players, last_marble))
+    }
+
+    fn part2(&(num_players, last_marble): &(u64, u64), _: &()) -> Result<u64> {
+        Ok(play(num_players, last_marble * 100))
+    }
+
+    /// Up to `size * 10` players, at most 500, and a last marble worth
+    /// around `1000 * size` points; part 2 plays a hundred times as long.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let size = size.max(1) as i64;
+        format!(
+            "{} players; last marble is worth {} points",
+            rng.range(2, (size * 10).clamp(2, 500)),
+            rng.range(size * 500, size * 1500)
+        )
+    }
 }
@@ -1,34 +1,42 @@
-#![feature(test)]
-extern crate test;
-extern crate regex;
-extern crate euclid;
-use euclid::{Rect, Point2D, Vector2D};
-mod utils;
-
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-type Sky = Vec<(Point2D<i64>, Vector2D<i64>)>;
-
-fn parse(lines: &[String]) -> Result<Sky> {
-    let re = regex::Regex::new(r"^position=<\s*(-?[0-9]+),\s*(-?[0-9]+)> velocity=<\s*(-?[0-9]+),\s*(-?[0-9]+)>$")?;
-    lines.iter()
-        .map(|s| {
-            let captures = re.captures(s).ok_or_else(|| format!("String {} does not match", s))?;
-
-            let x1 = captures[1].parse::<i64>()?;
-            let x2 = captures[2].parse::<i64>()?;
-            let v1 = captures[3].parse::<i64>()?;
-            let v2 = captures[4].parse::<i64>()?;
+use euclid::{Point2D, Rect, Vector2D};
+use error::Error;
+use generate::Rng;
+use parse;
+use render::{Image, Palette, Rgb};
+use solution::{Result, Solution};
+
+pub struct December10;
+
+pub type Sky = Vec<(Point2D<i64>, Vector2D<i64>)>;
+
+pub fn parse(lines: &[&str]) -> Result<Sky> {
+    if lines.is_empty() {
+        return Err(Error::input(0, 0, "expected at least one point of light"));
+    }
+
+    parse::lines(lines)
+        .iter()
+        .map(|line| {
+            let [x1, x2] = line.vector("position")?;
+            let [v1, v2] = line.vector("velocity")?;
 
             Ok((Point2D::new(x1, x2), Vector2D::new(v1, v2)))
         })
         .collect()
 }
 
-fn conserve_momentum(sky: &mut Sky) -> (Sky, i32) {
-    let mut area = std::i64::MAX;
+/// The message appears within 12 hours; the searches give up after that.
+const LIMIT: i64 = 12 * 60 * 60;
+
+pub fn conserve_momentum(sky: &mut Sky) -> (Sky, i32) {
+    let mut area = i64::MAX;
     let mut seconds = 0;
 
     loop {
+        if seconds == LIMIT {
+            break (sky.clone(), seconds as i32)
+        }
+
         for (x, v) in sky.iter_mut() {
             *x += *v;
         }
@@ -41,7 +49,7 @@ fn conserve_momentum(sky: &mut Sky) -> (Sky, i32) {
                 *x -= *v;
             }
 
-            break (sky.clone(), seconds)
+            break (sky.clone(), seconds as i32)
         }
 
         seconds += 1;
@@ -49,99 +57,167 @@ fn conserve_momentum(sky: &mut Sky) -> (Sky, i32) {
     }
 }
 
-/// Quicker than the naive search:
-/// > running 2 tests
-/// > test tests::search_binary ... bench:      16,369 ns/iter (+/- 2,829)
-/// > test tests::search_naive  ... bench:   4,628,550 ns/iter (+/- 1,951,750)
-fn conserve_momentum_bsearch(sky: &mut Sky) -> (Sky, i32) {
-    let mut left = 0;
-    let mut right = 12 * 60 * 60;
-    let mut prev_middle = 0;
+/// The area of the bounding box of the sky after the given number of seconds.
+fn area_after(sky: &Sky, seconds: i64) -> i64 {
+    Rect::from_points(sky.iter().map(|&(x, v)| x + v * seconds)).size.area()
+}
 
-    loop {
-        if left == right {
-            for (x, v) in sky.iter_mut() {
-                *x += *v * (left - prev_middle - 1);
-            }
-            break (sky.clone(), right as i32)
-        }
+/// Quicker than the naive search (`cargo bench`):
+/// > december10 search_naive   median 10.169ms, mean 10.465ms ± 1.574ms (8.098ms..13.944ms, 100 runs)
+/// > december10 search_binary  median 51.103µs, mean 44.579µs ± 9.655µs (30.146µs..65.260µs, 100 runs)
+pub fn conserve_momentum_bsearch(sky: &mut Sky) -> (Sky, i32) {
+    // Like the naive search, find the first second after which the bounding
+    // box grows.
+    let mut left = 0;
+    let mut right = LIMIT;
 
+    while left < right {
         let middle = left + (right - left) / 2;
-
-        for (x, v) in sky.iter_mut() {
-            *x += *v * (middle - prev_middle - 1);
-        }
-
-        let area1 = Rect::from_points(sky.iter().map(|(x, _)| x)).size.area();
-
-        for (x, v) in sky.iter_mut() {
-            *x += *v;
-        }
-        
-        let area2 = Rect::from_points(sky.iter().map(|(x, _)| x)).size.area();
-
-        prev_middle = middle;
-        if area1 >= area2 {
-            if left == right - 1 {
-                left = middle + 1;
-            } else {
-                left = middle;
-            }
+        if area_after(sky, middle) < area_after(sky, middle + 1) {
+            right = middle;
         } else {
-            if left == right - 1 {
-                right = middle - 1;
-            } else {
-                right = middle;
-            }
+            left = middle + 1;
         }
     }
 
+    for (x, v) in sky.iter_mut() {
+        *x += *v * left;
+    }
+    (sky.clone(), left as i32)
 }
 
-fn ascii_art(sky: Sky) -> String {
+static PALETTE: Palette = Palette(&[
+    (' ', Rgb(0x0f, 0x0f, 0x23)),
+    ('#', Rgb(0xff, 0xff, 0x66)),
+]);
+
+pub fn ascii_art(sky: Sky) -> String {
     let mut s = "".to_owned();
     let bbox = Rect::from_points(sky.iter().map(|(x, _)| x));
     for x2 in bbox.origin.y..=bbox.origin.y+bbox.size.height {
         for x1 in bbox.origin.x..=bbox.origin.x+bbox.size.   width {
             if sky.iter().any(|(x, _)| x.x == x1 && x.y == x2) {
-                s.push_str("#");
+                s.push('#');
             } else {
-                s.push_str(" ");
+                s.push(' ');
             }
         }
 
-        s.push_str("\n");
+        s.push('\n');
     }
 
     s
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december10.txt")?;
-    let mut sky = parse(&lines)?;
-    let (sky, seconds) = conserve_momentum_bsearch(&mut sky);
-    println!("Part 1:\n{}", ascii_art(sky));
-    println!("Part 2: {}", seconds);
-    
-    Ok(())
-}
+/// Letters of three by five pixels, row by row, to write messages in the
+/// sky with.
+static FONT: [&str; 26] = [
+    ".#./#.#/###/#.#/#.#",
+    "##./#.#/##./#.#/##.",
+    ".##/#../#../#../.##",
+    "##./#.#/#.#/#.#/##.",
+    "###/#../##./#../###",
+    "###/#../##./#../#..",
+    ".##/#../#.#/#.#/.##",
+    "#.#/#.#/###/#.#/#.#",
+    "###/.#./.#./.#./###",
+    "..#/..#/..#/#.#/.#.",
+    "#.#/#.#/##./#.#/#.#",
+    "#../#../#../#../###",
+    "#.#/###/###/#.#/#.#",
+    "##./#.#/#.#/#.#/#.#",
+    ".#./#.#/#.#/#.#/.#.",
+    "##./#.#/##./#../#..",
+    ".#./#.#/#.#/##./.##",
+    "##./#.#/##./#.#/#.#",
+    ".##/#../.#./..#/##.",
+    "###/.#./.#./.#./.#.",
+    "#.#/#.#/#.#/#.#/###",
+    "#.#/#.#/#.#/#.#/.#.",
+    "#.#/#.#/###/###/#.#",
+    "#.#/#.#/.#./#.#/#.#",
+    "#.#/#.#/.#./.#./.#.",
+    "###/..#/.#./#../###",
+];
+
+impl Solution for December10 {
+    type Input = Sky;
+    type Params = ();
+    type Part1 = String;
+    type Part2 = i32;
+
+    fn parse(lines: &[&str]) -> Result<Sky> {
+        parse(lines)
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use test::Bencher;
+    /// The message in the sky, as ASCII art.
+    fn part1(sky: &Sky, _: &()) -> Result<String> {
+        let (sky, _) = conserve_momentum_bsearch(&mut sky.clone());
+        Ok(ascii_art(sky))
+    }
+
+    fn part2(sky: &Sky, _: &()) -> Result<i32> {
+        let (_, seconds) = conserve_momentum_bsearch(&mut sky.clone());
+        Ok(seconds)
+    }
 
-    #[bench]
-    fn search_naive(b: &mut Bencher) {
-        let lines = utils::lines_from_file("input/december10.txt").unwrap();
-        let sky = parse(&lines).unwrap();
-        b.iter(|| conserve_momentum(&mut sky.clone()));
+    /// The message in the sky.
+    fn render(sky: &Sky, _: &()) -> Result<Image> {
+        let (sky, _) = conserve_momentum_bsearch(&mut sky.clone());
+        Ok(Image::from_text(&ascii_art(sky), &PALETTE))
     }
 
-    #[bench]
-    fn search_binary(b: &mut Bencher) {
-        let lines = utils::lines_from_file("input/december10.txt").unwrap();
-        let sky = parse(&lines).unwrap();
-        b.iter(|| conserve_momentum_bsearch(&mut sky.clone()));
+    /// A message of `size / 10` random letters, one to ten, that the points
+    /// of light spell after 5000 to 15000 seconds. Every lit pixel of the
+    /// message is one or two points.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let letters = (0..(size / 10).clamp(1, 10))
+            .map(|_| rng.choose(&FONT))
+            .collect::<Vec<_>>();
+        let mut pixels = Vec::new();
+        for (n, letter) in letters.iter().enumerate() {
+            for (y, row) in letter.split('/').enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    if c == '#' {
+                        for _ in 0..rng.range(1, 2) {
+                            pixels.push(Point2D::new(
+                                4 * n as i64 + x as i64,
+                                y as i64,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        let seconds = rng.range(5000, 15000);
+        // Draw velocities until the message is the smallest the sky gets.
+        let sky = loop {
+            let sky = pixels
+                .iter()
+                .map(|&pixel| {
+                    let mut speed = || {
+                        rng.range(1, 5) * if rng.chance(0.5) { 1 } else { -1 }
+                    };
+                    let v = Vector2D::new(speed(), speed());
+                    (pixel - v * seconds, v)
+                })
+                .collect::<Sky>();
+            let message = area_after(&sky, seconds);
+            if message < area_after(&sky, seconds - 1)
+                && message < area_after(&sky, seconds + 1)
+            {
+                break sky;
+            }
+        };
+
+        sky.iter()
+            .map(|(x, v)| {
+                format!(
+                    "position=<{:6}, {:6}> velocity=<{:2}, {:2}>\n",
+                    x.x, x.y, v.x, v.y
+                )
+            })
+            .collect()
     }
 }
@@ -1,27 +1,27 @@
-#![feature(test)]
-extern crate test;
-extern crate euclid;
-use euclid::{Rect, Point2D, Size2D};
+use euclid::{Point2D, Rect, Size2D};
+use generate::Rng;
+use parse;
+use solution::{Result, Solution};
 
-static SERIAL_INPUT: i32 = 5153;
+pub struct December11;
 
-fn power(x: usize, y: usize) -> i32 {
+pub fn power(x: usize, y: usize, serial: i32) -> i32 {
     let x = x as i32;
     let y = y as i32;
     let rack_id = x + 10;
-    (((rack_id * y + SERIAL_INPUT) * rack_id) % 1000) / 100 - 5
+    (((rack_id * y + serial) * rack_id) % 1000) / 100 - 5
 }
 
 /// Uses a summed-area table.
-fn max_power_summed_area_table(bbox: Rect<usize>, sizes: &[usize]) -> (Point2D<usize>, usize) {
+pub fn max_power_summed_area_table(bbox: Rect<usize>, sizes: &[usize], serial: i32) -> (Point2D<usize>, usize) {
     let mut max_area = (Point2D::new(0,0), 0);
-    let mut max_power = std::i32::MIN;
+    let mut max_power = i32::MIN;
 
     let mut grid = vec![vec![0; bbox.size.width+1]; bbox.size.height+1];
 
     for x in 1..=bbox.size.width {
         for y in 1..=bbox.size.height {
-            grid[x][y] = power(x,y) + grid[x-1][y] + grid[x][y-1] - grid[x-1][y-1]
+            grid[x][y] = power(x, y, serial) + grid[x-1][y] + grid[x][y-1] - grid[x-1][y-1]
         }
     }
 
@@ -40,19 +40,40 @@ fn max_power_summed_area_table(bbox: Rect<usize>, sizes: &[usize]) -> (Point2D<u
     max_area
 }
 
-fn main() {
-    println!("Part 1: {:?}", max_power_summed_area_table(Rect::new(Point2D::new(1,1),Size2D::new(300,300)), &[3]));
-    println!("Part 2: {:?}", max_power_summed_area_table(Rect::new(Point2D::new(1,1),Size2D::new(300,300)), &(1..=300).collect::<Vec<_>>()));
-}
+impl Solution for December11 {
+    /// The grid serial number.
+    type Input = i32;
+    type Params = ();
+    type Part1 = String;
+    type Part2 = String;
 
+    fn parse(lines: &[&str]) -> Result<i32> {
+        parse::single(lines, "a serial number")?.trim().parse()
+    }
 
-#[cfg(test)]
-mod tests {
-    use euclid::{Rect, Point2D, Size2D};
-    use test::Bencher;
+    /// The top-left coordinate of the 3x3 square with the largest power.
+    fn part1(&serial: &i32, _: &()) -> Result<String> {
+        let (point, _) = max_power_summed_area_table(
+            Rect::new(Point2D::new(1, 1), Size2D::new(300, 300)),
+            &[3],
+            serial,
+        );
+        Ok(format!("{},{}", point.x, point.y))
+    }
+
+    /// The top-left coordinate and size of the square with the largest power.
+    fn part2(&serial: &i32, _: &()) -> Result<String> {
+        let (point, size) = max_power_summed_area_table(
+            Rect::new(Point2D::new(1, 1), Size2D::new(300, 300)),
+            &(1..=300).collect::<Vec<_>>(),
+            serial,
+        );
+        Ok(format!("{},{},{}", point.x, point.y, size))
+    }
 
-    #[bench]
-    fn max_summed_area_table(b: &mut Bencher) {
-        b.iter(|| super::max_power_summed_area_table(Rect::new(Point2D::new(1,1),Size2D::new(300,300)), &(1..=300).collect::<Vec<_>>()));
+    /// A grid serial number. The grid is always 300 by 300, so `size` is
+    /// ignored.
+    fn generate(rng: &mut Rng, _size: usize) -> String {
+        rng.range(1000, 9999).to_string()
     }
 }
@@ -1,30 +1,71 @@
+use error::Error;
+use generate::Rng;
+use parse::{self, Line};
+use record;
+use render::{Image, Palette, Rgb};
+use solution::{Result, Solution};
 use std::collections::BTreeSet;
-mod utils;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-type Plant = bool;
-type Rule = ([Plant; 5], Plant);
+pub struct December12;
 
-fn parse(lines: &[String]) -> Result<(Vec<Plant>, Vec<Rule>)> {
-    let plants = lines[0][15..].chars().map(|c| c == '#').collect();
-    let rules: Vec<Rule> = lines[2..]
-        .iter()
-        .map(|s| {
-            let mut rule = [false; 5];
+pub type Plant = bool;
+pub type Rule = ([Plant; 5], Plant);
 
-            for (idx, c) in s[0..5].chars().enumerate() {
-                rule[idx] = c == '#'
-            }
+/// Parse pots like `#..#.`.
+fn parse_plants(pots: Line) -> Result<Vec<Plant>> {
+    pots.text
+        .char_indices()
+        .map(|(offset, c)| match c {
+            '#' => Ok(true),
+            '.' => Ok(false),
+            _ => Err(pots.error_at(offset, "expected `#` or `.`")),
+        })
+        .collect()
+}
 
-            let outcome = s.chars().nth(9).ok_or("Invalid rule")? == '#';
-            Ok((rule, outcome))
+pub fn parse(lines: &[&str]) -> Result<(Vec<Plant>, Vec<Rule>)> {
+    let sections = parse::sections(lines, 1);
+    let section = sections
+        .first()
+        .ok_or_else(|| Error::input(0, 0, "expected `initial state: `"))?;
+    let initial = section.line(0).after("initial state: ")?;
+    if section.lines.len() > 1 {
+        return Err(section.line(1).error("expected a blank line"));
+    }
+    let plants = parse_plants(initial)?;
+    if plants.is_empty() {
+        return Err(initial.error("expected pots"));
+    }
+
+    let rules: Vec<Rule> = sections
+        .iter()
+        .skip(1)
+        .flat_map(|section| section.lines())
+        .map(|line| {
+            let (pattern, outcome) = line.split_once(" => ")?;
+            match (&parse_plants(pattern)?[..], &parse_plants(outcome)?[..]) {
+                (&[a, b, c, d, e], &[outcome]) => Ok(([a, b, c, d, e], outcome)),
+                _ => Err(line.error("expected a rule like `..#.. => #`")),
+            }
         })
         .collect::<Result<Vec<Rule>>>()?;
 
     Ok((plants, rules))
 }
 
-fn game_of_plants(plants: &[Plant], rules: &[Rule], epochs: u64) -> i64 {
+static PALETTE: Palette = Palette(&[
+    ('.', Rgb(0x0f, 0x0f, 0x23)),
+    ('#', Rgb(0x00, 0xcc, 0x00)),
+]);
+
+/// The pots from `from` to `to`, as in the input.
+pub fn ascii_art(plants: &BTreeSet<i64>, from: i64, to: i64) -> String {
+    (from..=to)
+        .map(|k| if plants.contains(&k) { '#' } else { '.' })
+        .collect()
+}
+
+pub fn game_of_plants(plants: &[Plant], rules: &[Rule], epochs: u64) -> i64 {
     let mut all_plants = BTreeSet::new();
 
     let (mut lbound, mut rbound) = (0i64, (plants.len() - 1) as i64);
@@ -36,7 +77,7 @@ fn game_of_plants(plants: &[Plant], rules: &[Rule], epochs: u64) -> i64 {
     // find a pattern fixpoint (two identical generations modulo pot numbers).
     for epoch in 1..=epochs {
         let mut all_plants_ = BTreeSet::new();
-        let (mut nlbound, mut nrbound) = (std::i64::MAX, std::i64::MIN);
+        let (mut nlbound, mut nrbound) = (i64::MAX, i64::MIN);
         for k in lbound - 2..=rbound + 2 {
             let slice = [
                 all_plants.contains(&(k - 2)),
@@ -45,12 +86,13 @@ fn game_of_plants(plants: &[Plant], rules: &[Rule], epochs: u64) -> i64 {
                 all_plants.contains(&(k + 1)),
                 all_plants.contains(&(k + 2)),
             ];
-            if *rules
+            // Rules that are not listed do not produce a plant.
+            if rules
                 .iter()
                 .filter(|(rule, _)| slice == *rule)
-                .map(|(_, outcome)| outcome)
-                .nth(0)
-                .unwrap()
+                .map(|&(_, outcome)| outcome)
+                .next()
+                .unwrap_or(false)
             {
                 all_plants_.insert(k);
                 if k < nlbound {
@@ -88,6 +130,11 @@ fn game_of_plants(plants: &[Plant], rules: &[Rule], epochs: u64) -> i64 {
             }
         }
 
+        record::frame(|| {
+            let text = ascii_art(&all_plants_, nlbound.min(0), nrbound);
+            Image::from_text(&text, &PALETTE)
+        });
+
         lbound = nlbound;
         rbound = nrbound;
 
@@ -97,15 +144,125 @@ fn game_of_plants(plants: &[Plant], rules: &[Rule], epochs: u64) -> i64 {
     all_plants.iter().sum()
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december12.txt")?;
-    let (plants, rules) = parse(&lines)?;
+/// Whether the plants settle within the given number of generations into a
+/// pattern that only moves, without dying out or spreading too far.
+fn settles(plants: &[Plant], rules: &[Rule], generations: usize) -> bool {
+    let mut plants = (0..)
+        .zip(plants)
+        .filter(|&(_, &p)| p)
+        .map(|(k, _)| k)
+        .collect::<Vec<i64>>();
+
+    for _ in 0..generations {
+        let (first, last) = match (plants.first(), plants.last()) {
+            (Some(&first), Some(&last)) if last - first < 1000 => (first, last),
+            _ => return false,
+        };
+        let next = (first - 2..=last + 2)
+            .filter(|k| {
+                let mut slice = [false; 5];
+                for (i, plant) in slice.iter_mut().enumerate() {
+                    *plant = plants.binary_search(&(k - 2 + i as i64)).is_ok();
+                }
+                rules
+                    .iter()
+                    .any(|&(rule, outcome)| rule == slice && outcome)
+            })
+            .collect::<Vec<_>>();
+
+        let shift = next.first().map_or(0, |k| k - first);
+        if next.len() == plants.len()
+            && next.iter().zip(&plants).all(|(a, b)| a - b == shift)
+        {
+            return true;
+        }
+        plants = next;
+    }
+    false
+}
+
+impl Solution for December12 {
+    type Input = (Vec<Plant>, Vec<Rule>);
+    type Params = ();
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(lines: &[&str]) -> Result<Self::Input> {
+        parse(lines)
+    }
+
+    fn part1((plants, rules): &Self::Input, _: &()) -> Result<i64> {
+        Ok(game_of_plants(plants, rules, 20))
+    }
+
+    fn part2((plants, rules): &Self::Input, _: &()) -> Result<i64> {
+        Ok(game_of_plants(plants, rules, 50_000_000_000u64))
+    }
+
+    /// `size` pots and all 32 rules. Rules are drawn until the plants settle
+    /// into a pattern that moves at a constant speed within 1000
+    /// generations, which part 2 relies on, falling back to rules that move
+    /// every plant one pot to the right.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let patterns = (0..32)
+            .map(|n| {
+                let mut pattern = [false; 5];
+                for (i, plant) in pattern.iter_mut().enumerate() {
+                    *plant = n & (1 << (4 - i)) != 0;
+                }
+                pattern
+            })
+            .collect::<Vec<_>>();
 
-    println!("Part 1: {}", game_of_plants(&plants, &rules, 20));
-    println!(
-        "Part 2: {}",
-        game_of_plants(&plants, &rules, 50_000_000_000u64)
-    );
+        let plants = loop {
+            let plants = (0..size.max(1))
+                .map(|_| rng.chance(0.5))
+                .collect::<Vec<_>>();
+            if plants.contains(&true) {
+                break plants;
+            }
+        };
 
-    Ok(())
+        let mut rules = (0..1000)
+            .map(|_| {
+                patterns
+                    .iter()
+                    .map(|&pattern| {
+                        // Without a plant near the middle, no plant grows, so
+                        // the plants do not spread without bounds.
+                        let lonely =
+                            pattern.iter().filter(|&&p| p).count() <= 1;
+                        let outcome = if !pattern[2] && lonely {
+                            false
+                        } else if rng.chance(0.75) {
+                            pattern[2]
+                        } else {
+                            rng.chance(0.5)
+                        };
+                        (pattern, outcome)
+                    })
+                    .collect::<Vec<Rule>>()
+            })
+            .find(|rules| settles(&plants, rules, 1000))
+            .unwrap_or_else(|| {
+                patterns
+                    .iter()
+                    .map(|&pattern| (pattern, pattern[1]))
+                    .collect()
+            });
+        rng.shuffle(&mut rules);
+
+        let pots = |plants: &[Plant]| -> String {
+            plants.iter().map(|&p| if p { '#' } else { '.' }).collect()
+        };
+        let mut text = format!("initial state: {}\n\n", pots(&plants));
+        for (pattern, outcome) in rules {
+            text.push_str(&format!(
+                "{} => {}\n",
+                pots(&pattern),
+                pots(&[outcome])
+            ));
+        }
+        text
+    }
 }
@@ -1,71 +1,92 @@
-mod utils;
+use error::Error;
+use generate::Rng;
+use grid::{Grid, Pos};
+use record;
+use render::{Image, Palette, Rgb};
+use solution::{Result, Solution};
+use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-type Map = Vec<Vec<char>>;
-type Position = (usize, usize);
-type Direction = i32;
+pub struct December13;
 
-fn parse(lines: &[String]) -> Result<(Map, BTreeMap<Position, Direction>)> {
-    let mut map = Vec::new();
+pub type Map = Grid<char>;
+pub type Direction = i32;
+
+pub fn parse(lines: &[&str]) -> Result<(Map, BTreeMap<Pos, Direction>)> {
     let mut carts = BTreeMap::new();
-    for (i, line) in lines.iter().enumerate() {
-        let mut l = Vec::new();
-        for (j, c) in line.chars().enumerate() {
-            let (m, cart) = match c {
-                '^' => ('|', Some(0)),
-                '>' => ('-', Some(90)),
-                'v' => ('|', Some(180)),
-                '<' => ('-', Some(270)),
-                _ => (c, None),
-            };
+    let map = Grid::parse_with(lines, |pos, c| {
+        let (track, cart) = match c {
+            '^' => ('|', Some(0)),
+            '>' => ('-', Some(90)),
+            'v' => ('|', Some(180)),
+            '<' => ('-', Some(270)),
+            ' ' | '-' | '|' | '/' | '\\' | '+' => (c, None),
+            _ => Err(Error::input(
+                pos.y as usize,
+                pos.x as usize,
+                format!("invalid track `{}`", c),
+            ))?,
+        };
 
-            l.push(m);
-            if let Some(cart) = cart {
-                carts.insert((i, j), cart);
-            }
+        if let Some(cart) = cart {
+            carts.insert(pos, cart);
         }
-        map.push(l);
-    }
+        Ok(track)
+    })?;
 
     Ok((map, carts))
 }
 
-fn run(
-    map: Map,
-    carts: BTreeMap<Position, Direction>,
-) -> Result<(Option<Position>, Option<Position>)> {
+/// Run the carts until at most one is left, returning the position of the
+/// first crash and of the last cart standing. The tracks are recorded on every
+/// tick. With `max_ticks`, carts that are still running after that many ticks
+/// are an error.
+pub fn run(
+    map: &Map,
+    carts: &BTreeMap<Pos, Direction>,
+    max_ticks: Option<usize>,
+) -> Result<(Option<Pos>, Option<Pos>)> {
     let mut carts = carts
         .iter()
         .map(|(&k, &v)| (k, (v, -90)))
         .collect::<BTreeMap<_, _>>();
     let mut first_crash = None;
-    // Hide cursor for animation.
-    print!("{}[?25l", 27 as char);
+    let mut ticks = 0;
     loop {
-        // Animate!
-        println!("{}[2J{}", 27 as char, ascii_art(&map, &carts.iter().map(|(&k,&(d,_m))| (k,d)).collect())?);
-        std::thread::sleep(std::time::Duration::from_millis(84));
+        if max_ticks == Some(ticks) {
+            return Err(Error::unsolvable(format!(
+                "{} carts are still running after {} ticks",
+                carts.len(),
+                ticks
+            )));
+        }
+        ticks += 1;
+        record::frame(|| {
+            let directions = carts.iter().map(|(&k, &(d, _))| (k, d)).collect();
+            // Carts only ever turn by right angles.
+            let text = ascii_art(map, &directions).unwrap_or_default();
+            Image::from_text(&text, &PALETTE)
+        });
 
         let mut carts_new = BTreeMap::new();
-        for (&(i, j), &(dir, mem)) in carts.iter() {
-            let (ni, nj) = if carts_new.contains_key(&(i, j)) {
+        for (&pos, &(dir, mem)) in carts.iter() {
+            let Pos { x, y } = pos;
+            let next = if carts_new.contains_key(&pos) {
                 // Crashed.
-                (i, j)
+                pos
             } else {
                 match dir {
-                    0 => Ok((i - 1, j)),
-                    90 => Ok((i, j + 1)),
-                    180 => Ok((i + 1, j)),
-                    270 => Ok((i, j - 1)),
-                    _ => Err("Unknown direction"),
+                    0 => Ok(Pos::new(x, y - 1)),
+                    90 => Ok(Pos::new(x + 1, y)),
+                    180 => Ok(Pos::new(x, y + 1)),
+                    270 => Ok(Pos::new(x - 1, y)),
+                    _ => Err(Error::unsolvable("Unknown direction")),
                 }?
             };
 
-            let t = map
-                .get(ni)
-                .and_then(|l| l.get(nj))
-                .ok_or(format!("Cart derailed at {}, {}", nj, ni))?;
+            let t = map.get(next).filter(|&&t| t != ' ').ok_or_else(|| {
+                Error::unsolvable(format!("Cart derailed at {}, {}", x, y))
+            })?;
 
             let (dd, nmem) = match &t {
                 '/' => (if dir == 0 || dir == 180 { 90 } else { -90 }, mem),
@@ -81,58 +102,201 @@ fn run(
                 _ => (0, mem),
             };
 
-            if carts_new.contains_key(&(ni, nj)) {
+            if let Entry::Vacant(e) = carts_new.entry(next) {
+                e.insert(((dir + dd + 360) % 360, nmem));
+            } else {
                 // Crashed.
-                carts_new.remove(&(ni, nj));
-                if first_crash == None {
-                    first_crash = Some((ni, nj));
+                carts_new.remove(&next);
+                if first_crash.is_none() {
+                    first_crash = Some(next);
                 }
-            } else {
-                carts_new.insert((ni, nj), ((dir + dd + 360) % 360, nmem));
             }
         }
 
         carts = carts_new;
 
         if carts.len() <= 1 {
-            break Ok((first_crash, carts.keys().nth(0).map(|&v| v)));
+            break Ok((first_crash, carts.keys().next().copied()));
         }
     }
 }
 
-fn ascii_art(
-    map: &Map,
-    carts: &BTreeMap<Position, Direction>,
-) -> Result<String> {
-    let mut s = "".to_owned();
-
-    for i in 0..map.len() {
-        for j in 0..map[i].len() {
-            if let Some(d) = carts.get(&(i, j)) {
-                s.push(match d {
-                    0 => Ok('^'),
-                    90 => Ok('>'),
-                    180 => Ok('v'),
-                    270 => Ok('<'),
-                    _ => Err("Unknown direction"),
-                }?);
-            } else {
-                s.push(map[i][j]);
-            }
+static PALETTE: Palette = Palette(&[
+    (' ', Rgb(0x0f, 0x0f, 0x23)),
+    ('-', Rgb(0xcc, 0xcc, 0xcc)),
+    ('|', Rgb(0xcc, 0xcc, 0xcc)),
+    ('/', Rgb(0xcc, 0xcc, 0xcc)),
+    ('\\', Rgb(0xcc, 0xcc, 0xcc)),
+    ('+', Rgb(0xcc, 0xcc, 0xcc)),
+    ('^', Rgb(0xff, 0x50, 0x50)),
+    ('>', Rgb(0xff, 0x50, 0x50)),
+    ('v', Rgb(0xff, 0x50, 0x50)),
+    ('<', Rgb(0xff, 0x50, 0x50)),
+]);
+
+pub fn ascii_art(map: &Map, carts: &BTreeMap<Pos, Direction>) -> Result<String> {
+    let mut unknown = None;
+    let s = map.render(|pos, track| match carts.get(&pos) {
+        Some(0) => '^',
+        Some(90) => '>',
+        Some(180) => 'v',
+        Some(270) => '<',
+        Some(&d) => {
+            unknown = Some(d);
+            '?'
         }
-        s.push('\n');
+        None => *track.unwrap_or(&' '),
+    });
+
+    match unknown {
+        Some(_) => Err(Error::unsolvable("Unknown direction")),
+        None => Ok(s),
+    }
+}
+
+/// Formats a position as `x,y`.
+fn coordinates(Pos { x, y }: Pos) -> String {
+    format!("{},{}", x, y)
+}
+
+/// Add a rectangular loop of track in a random place, if it only crosses
+/// other loops at right angles.
+fn add_loop(tracks: &mut [Vec<char>], rng: &mut Rng) {
+    let side = tracks.len();
+    let (width, height) = (rng.index(side / 2) + 3, rng.index(side / 2) + 3);
+    let (left, top) = (rng.index(side - width), rng.index(side - height));
+    let (right, bottom) = (left + width, top + height);
+
+    let mut pieces = vec![
+        (left, top, '/'),
+        (right, top, '\\'),
+        (left, bottom, '\\'),
+        (right, bottom, '/'),
+    ];
+    for x in left + 1..right {
+        pieces.push((x, top, '-'));
+        pieces.push((x, bottom, '-'));
+    }
+    for y in top + 1..bottom {
+        pieces.push((left, y, '|'));
+        pieces.push((right, y, '|'));
+    }
+
+    let mut placed = Vec::with_capacity(pieces.len());
+    for (x, y, piece) in pieces {
+        let track = match (tracks[y][x], piece) {
+            (' ', _) => piece,
+            ('-', '|') | ('|', '-') => '+',
+            _ => return,
+        };
+        placed.push((x, y, track));
     }
+    for (x, y, track) in placed {
+        tracks[y][x] = track;
+    }
+}
 
-    Ok(s)
+fn rows(tracks: &[Vec<char>]) -> String {
+    tracks
+        .iter()
+        .map(|row| row.iter().chain(Some(&'\n')).collect::<String>())
+        .collect()
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december13forever.txt")?;
-    let (map, carts) = parse(&lines)?;
-    let (p1, p2) = run(map, carts)?;
+impl Solution for December13 {
+    type Input = (Map, BTreeMap<Pos, Direction>);
+    type Params = ();
+    type Part1 = String;
+    type Part2 = String;
+
+    fn parse(lines: &[&str]) -> Result<Self::Input> {
+        parse(lines)
+    }
+
+    /// The location of the first crash.
+    fn part1((map, carts): &Self::Input, _: &()) -> Result<String> {
+        let (first_crash, _) = run(map, carts, None)?;
+        Ok(coordinates(
+            first_crash.ok_or_else(|| Error::unsolvable("No carts crashed"))?,
+        ))
+    }
+
+    /// The location of the last cart.
+    fn part2((map, carts): &Self::Input, _: &()) -> Result<String> {
+        let (_, last_cart) = run(map, carts, None)?;
+        Ok(coordinates(
+            last_cart.ok_or_else(|| Error::unsolvable("No carts left"))?,
+        ))
+    }
+
+    /// The tracks with the carts at their starting positions.
+    fn render((map, carts): &Self::Input, _: &()) -> Result<Image> {
+        Ok(Image::from_text(&ascii_art(map, carts)?, &PALETTE))
+    }
+
+    /// Tracks of up to `size` overlapping rectangles on a square with sides
+    /// of `3 * size`, and an odd number of carts, `size / 5 + 3` or one
+    /// more. Carts are placed until they all but one crash within 100000
+    /// ticks, falling back to three carts on a single loop: two going one
+    /// way, which cannot crash into each other, and one going the other way.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let side = (size * 3).max(12);
+        let cart_count = (size / 5 + 3) | 1;
 
-    println!("Part 1: {:?}", p1);
-    println!("Part 2: {:?}", p2);
+        for _ in 0..100 {
+            let mut tracks = vec![vec![' '; side]; side];
+            for _ in 0..size.max(1) {
+                add_loop(&mut tracks, rng);
+            }
 
-    Ok(())
+            let mut straights = Vec::new();
+            for (y, row) in tracks.iter().enumerate() {
+                for (x, &track) in row.iter().enumerate() {
+                    if track == '-' || track == '|' {
+                        straights.push((x, y));
+                    }
+                }
+            }
+            if straights.len() < cart_count {
+                continue;
+            }
+            rng.shuffle(&mut straights);
+            for &(x, y) in &straights[..cart_count] {
+                let forward = rng.chance(0.5);
+                tracks[y][x] = match (tracks[y][x], forward) {
+                    ('-', true) => '>',
+                    ('-', false) => '<',
+                    (_, true) => 'v',
+                    (_, false) => '^',
+                };
+            }
+
+            let text = rows(&tracks);
+            let lines = text.lines().collect::<Vec<_>>();
+            let (map, carts) = parse(&lines).expect("generated tracks parse");
+            if let Ok((_, Some(_))) = run(&map, &carts, Some(100_000)) {
+                return text;
+            }
+        }
+
+        let last = side - 1;
+        let edge = |i| i == 0 || i == last;
+        let mut tracks = (0..side)
+            .map(|y| {
+                (0..side)
+                    .map(|x| match (edge(x), edge(y)) {
+                        (true, true) if x == y => '/',
+                        (true, true) => '\\',
+                        (false, true) => '-',
+                        (true, false) => '|',
+                        (false, false) => ' ',
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        tracks[0][1] = '>';
+        tracks[0][last / 2] = '>';
+        tracks[last][1] = '>';
+        rows(&tracks)
+    }
 }
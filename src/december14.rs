@@ -1,5 +1,11 @@
+use generate::Rng;
+use parse;
+use solution::{self, Result, Solution};
+
+pub struct December14;
+
 /// Perform one recipe-making step.
-fn step(recipes: &mut Vec<u8>, e1: &mut usize, e2: &mut usize) {
+pub fn step(recipes: &mut Vec<u8>, e1: &mut usize, e2: &mut usize) {
     let sum = recipes[*e1] + recipes[*e2];
     if sum >= 10 {
         recipes.push(sum / 10);
@@ -12,22 +18,25 @@ fn step(recipes: &mut Vec<u8>, e1: &mut usize, e2: &mut usize) {
 }
 
 /// Run for the given iterations to find the final pattern.
-fn run(input: usize) -> Vec<u8> {
+pub fn run(input: usize) -> Vec<u8> {
     let (mut recipes, mut e1, mut e2) = (vec![3, 7], 0, 1);
 
-    while recipes.len() < input as usize + 10 + 2 {
+    while recipes.len() < input + 10 + 2 {
         step(&mut recipes, &mut e1, &mut e2);
     }
 
-    recipes[input as usize..input as usize + 10].to_vec()
+    recipes[input..input + 10].to_vec()
 }
 
 /// Count iterations until the given pattern occurs.
-fn nur(input: &[u8]) -> usize {
+pub fn nur(input: &[u8]) -> usize {
     let (mut recipes, mut e1, mut e2) = (vec![3, 7], 0, 1);
 
     loop {
         step(&mut recipes, &mut e1, &mut e2);
+        if recipes.len() % 0x10000 < 2 {
+            solution::checkpoint();
+        }
 
         if recipes.len() >= input.len()
             && recipes[recipes.len() - input.len()..] == *input
@@ -42,7 +51,54 @@ fn nur(input: &[u8]) -> usize {
     }
 }
 
-fn main() {
-    println!("Part 1: {:?}", run(580741));
-    println!("Part 2: {:?}", nur(&vec![5, 8, 0, 7, 4, 1]));
+impl Solution for December14 {
+    /// The puzzle input, as a number and as a sequence of digits.
+    type Input = (usize, Vec<u8>);
+    type Params = ();
+    type Part1 = String;
+    type Part2 = usize;
+
+    fn parse(lines: &[&str]) -> Result<(usize, Vec<u8>)> {
+        let input = parse::single(lines, "a number")?.trim();
+        let digits = input
+            .text
+            .char_indices()
+            .map(|(offset, c)| {
+                let digit = c
+                    .to_digit(10)
+                    .ok_or_else(|| input.error_at(offset, "expected a digit"))?;
+                Ok(digit as u8)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok((input.parse()?, digits))
+    }
+
+    /// The scores of the ten recipes after the input number of recipes.
+    fn part1((input, _): &(usize, Vec<u8>), _: &()) -> Result<String> {
+        Ok(run(*input).iter().map(|score| score.to_string()).collect())
+    }
+
+    fn part2((_, digits): &(usize, Vec<u8>), _: &()) -> Result<usize> {
+        Ok(nur(digits))
+    }
+
+    /// A five-digit number that occurs among the first `1000 * size`
+    /// recipe scores, so that part 2 finds it there at the latest.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let (mut recipes, mut e1, mut e2) = (vec![3, 7], 0, 1);
+        while recipes.len() < (size * 1000).max(100) {
+            step(&mut recipes, &mut e1, &mut e2);
+        }
+
+        loop {
+            let at = rng.index(recipes.len() - 5);
+            if recipes[at] != 0 {
+                return recipes[at..at + 5]
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect();
+            }
+        }
+    }
 }
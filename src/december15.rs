@@ -1,78 +1,88 @@
-#[macro_use]
-extern crate maplit;
-
+use error::Error;
+use generate::Rng;
+use grid::{Grid, Pos};
+use profile;
+use record;
+use render::{Image, Palette, Rgb};
+use solution::{self, Result, Solution};
 use std::collections::{BTreeMap, HashMap, HashSet};
-mod utils;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December15;
 
 #[derive(Clone, Copy, Eq, PartialEq)]
-enum Unit {
+pub enum Unit {
     Elf,
     Goblin,
 }
 
 #[derive(Clone, Eq, PartialEq)]
-enum Field {
+pub enum Field {
     Wall,
     Cavern,
 }
 
-type Map = Vec<Vec<Field>>;
-type Position = (i16, i16);
-type Units = BTreeMap<Position, (Unit, i32)>;
+pub type Map = Grid<Field>;
+pub type Units = BTreeMap<Pos, (Unit, i32)>;
 
 static HP: i32 = 200;
 
-fn parse(lines: &[String]) -> Result<(Map, Units)> {
+pub fn parse(lines: &[&str]) -> Result<(Map, Units)> {
     let mut units = BTreeMap::new();
-    let mut map = vec![vec![Field::Cavern; lines[0].len()]; lines.len()];
+    let width = lines.first().map(|line| line.len()).unwrap_or(0);
 
     for (i, line) in lines.iter().enumerate() {
-        for (j, c) in line.chars().enumerate() {
-            if j > map[i].len() {
-                Err(format!("Line {} too wide!", i + 1))?
-            } else if c == '#' {
-                map[i][j] = Field::Wall;
-            } else if c == 'E' {
-                units.insert((i as i16, j as i16), (Unit::Elf, HP));
-            } else if c == 'G' {
-                units.insert((i as i16, j as i16), (Unit::Goblin, HP));
-            } else if c != '.' {
-                Err(format!("Could not parse character: `{}`", c))?;
-            }
+        if line.len() != width {
+            Err(Error::input(i, 0, format!("expected a row of width {}", width)))?
         }
     }
 
+    let map = Grid::parse_with(lines, |pos, c| {
+        let (i, j) = (pos.y as usize, pos.x as usize);
+        // The walls around the cave keep units from walking off the map.
+        let border = i == 0 || j == 0 || i == lines.len() - 1 || j == width - 1;
+        if c == '#' {
+            return Ok(Field::Wall);
+        } else if border {
+            Err(Error::input(i, j, "expected a wall around the cave"))?
+        } else if c == 'E' {
+            units.insert(pos, (Unit::Elf, HP));
+        } else if c == 'G' {
+            units.insert(pos, (Unit::Goblin, HP));
+        } else if c != '.' {
+            Err(Error::input(i, j, format!("invalid square `{}`", c)))?;
+        }
+        Ok(Field::Cavern)
+    })?;
+
     Ok((map, units))
 }
 
-fn ascii_art(map: &Map, units: &Units) -> String {
-    let mut s = "".to_owned();
-    for (i, row) in map.iter().enumerate() {
-        for (j, field) in row.iter().enumerate() {
-            match units.get(&(i as i16, j as i16)) {
-                Some((Unit::Elf, _)) => s.push('E'),
-                Some((Unit::Goblin, _)) => s.push('G'),
-                None => match field {
-                    Field::Wall => s.push('#'),
-                    Field::Cavern => s.push('.'),
-                },
-            }
-        }
-        s.push('\n');
-    }
-    s
+static PALETTE: Palette = Palette(&[
+    ('.', Rgb(0x28, 0x28, 0x3c)),
+    ('#', Rgb(0x64, 0x64, 0x64)),
+    ('E', Rgb(0x00, 0xcc, 0x00)),
+    ('G', Rgb(0xcc, 0x00, 0x00)),
+]);
+
+pub fn ascii_art(map: &Map, units: &Units) -> String {
+    map.render(|pos, field| match units.get(&pos) {
+        Some((Unit::Elf, _)) => 'E',
+        Some((Unit::Goblin, _)) => 'G',
+        None => match field {
+            Some(Field::Wall) => '#',
+            _ => '.',
+        },
+    })
 }
 
 /// Find an adjacent enemy unit to attack (if any).
-fn in_range((i, j): Position, unit: Unit, units: &Units) -> Option<Position> {
+pub fn in_range(position: Pos, unit: Unit, units: &Units) -> Option<Pos> {
     let mut pos = None;
     let mut hp = HP + 1;
-    for &(ti, tj) in &[(i - 1, j), (i, j - 1), (i, j + 1), (i + 1, j)] {
-        if let Some((tunit, thp)) = units.get(&(ti, tj)) {
+    for &target in &position.neighbours4() {
+        if let Some((tunit, thp)) = units.get(&target) {
             if *tunit != unit && *thp < hp {
-                pos = Some((ti, tj));
+                pos = Some(target);
                 hp = *thp;
             }
         }
@@ -81,23 +91,14 @@ fn in_range((i, j): Position, unit: Unit, units: &Units) -> Option<Position> {
 }
 
 /// Find the next position to walk to.
-fn next_pos(
-    (i, j): Position,
-    unit: Unit,
-    units: &Units,
-    map: &Map,
-) -> Option<Position> {
+pub fn next_pos(start: Pos, unit: Unit, units: &Units, map: &Map) -> Option<Pos> {
     // Find all goals.
     let mut goals = HashSet::new();
-    for (&(ti, tj), &(tunit, _)) in units {
+    for (&target, &(tunit, _)) in units {
         if tunit != unit {
-            for &(ati, atj) in
-                &[(ti - 1, tj), (ti, tj - 1), (ti, tj + 1), (ti + 1, tj)]
-            {
-                if !units.contains_key(&(ati, atj))
-                    && map[ati as usize][atj as usize] == Field::Cavern
-                {
-                    goals.insert((ati, atj));
+            for adjacent in map.neighbours4(target) {
+                if !units.contains_key(&adjacent) && map[adjacent] == Field::Cavern {
+                    goals.insert(adjacent);
                 }
             }
         }
@@ -107,17 +108,15 @@ fn next_pos(
         return None;
     }
 
-    let start = (i, j);
-
     let mut closed_set = HashSet::new();
-    let mut open_set = hashset! {(i, j)};
+    let mut open_set = hashset! {start};
     let mut came_from = HashMap::new();
-    let mut costs = hashmap! {(i, j) => 0};
+    let mut costs = hashmap! {start => 0};
 
     while !open_set.is_empty() {
         // Find position with least cost so far.
         let mut pos = None;
-        let mut cost = std::i16::MAX;
+        let mut cost = i16::MAX;
         for p in open_set.iter() {
             let cost_ = costs[p];
             if cost_ < cost || cost_ == cost && *p < pos.unwrap() {
@@ -126,13 +125,13 @@ fn next_pos(
             }
         }
 
-        let (i, j) = pos.unwrap();
-        open_set.remove(&(i, j));
-        closed_set.insert((i, j));
+        let pos = pos.unwrap();
+        open_set.remove(&pos);
+        closed_set.insert(pos);
 
-        if goals.contains(&(i, j)) {
+        if goals.contains(&pos) {
             // Arrived at goal. Retrace steps to arrive at first step.
-            let mut first = (i, j);
+            let mut first = pos;
             while let Some(p) = came_from.get(&first) {
                 if *p == start {
                     return Some(first);
@@ -143,24 +142,24 @@ fn next_pos(
             return Some(first);
         }
 
-        for &(ni, nj) in &[(i - 1, j), (i, j - 1), (i, j + 1), (i + 1, j)] {
-            if closed_set.contains(&(ni, nj))
-                || units.contains_key(&(ni, nj))
-                || map[ni as usize][nj as usize] != Field::Cavern
+        for next in map.neighbours4(pos) {
+            if closed_set.contains(&next)
+                || units.contains_key(&next)
+                || map[next] != Field::Cavern
             {
                 continue;
             }
 
             let tentative_cost = cost + 1;
 
-            if !open_set.contains(&(ni, nj)) {
-                open_set.insert((ni, nj));
-            } else if tentative_cost >= costs[&(ni, nj)] {
+            if !open_set.contains(&next) {
+                open_set.insert(next);
+            } else if tentative_cost >= costs[&next] {
                 continue;
             }
 
-            came_from.insert((ni, nj), (i, j));
-            costs.insert((ni, nj), tentative_cost);
+            came_from.insert(next, pos);
+            costs.insert(next, tentative_cost);
         }
     }
 
@@ -168,7 +167,7 @@ fn next_pos(
 }
 
 /// Perform one battle step.
-fn step(map: &Map, units: Units, elf_ap: i32, goblin_ap: i32) -> (Units, bool) {
+pub fn step(map: &Map, units: Units, elf_ap: i32, goblin_ap: i32) -> (Units, bool) {
     let mut new_units = units.clone();
     let mut died = HashSet::new();
 
@@ -192,19 +191,19 @@ fn step(map: &Map, units: Units, elf_ap: i32, goblin_ap: i32) -> (Units, bool) {
         let (_, hp) = new_units[&position];
 
         // In range of target? Don't move.
-        if in_range(position, unit, &new_units) == None {
-            if let Some(npos) = next_pos(position, unit, &new_units, &map) {
+        if in_range(position, unit, &new_units).is_none() {
+            if let Some(npos) = next_pos(position, unit, &new_units, map) {
                 new_units.remove(&position);
                 position = npos;
                 new_units.insert(position, (unit, hp));
             }
         }
 
-        if let Some((ti, tj)) = in_range(position, unit, &new_units) {
+        if let Some(target) = in_range(position, unit, &new_units) {
             let mut remove_target = false;
 
             {
-                let (_, thp) = new_units.get_mut(&(ti, tj)).unwrap();
+                let (_, thp) = new_units.get_mut(&target).unwrap();
                 if unit == Unit::Elf {
                     *thp -= elf_ap;
                 } else {
@@ -216,8 +215,8 @@ fn step(map: &Map, units: Units, elf_ap: i32, goblin_ap: i32) -> (Units, bool) {
             }
 
             if remove_target {
-                new_units.remove(&(ti, tj));
-                died.insert((ti, tj));
+                new_units.remove(&target);
+                died.insert(target);
             }
         }
     }
@@ -225,13 +224,15 @@ fn step(map: &Map, units: Units, elf_ap: i32, goblin_ap: i32) -> (Units, bool) {
     (new_units, true)
 }
 
-fn combat(map: &Map, units: &Units, elf_ap: i32) -> (i32, Units) {
+pub fn combat(map: &Map, units: &Units, elf_ap: i32) -> (i32, Units) {
     let mut units: Units = units.to_owned();
     let mut num_rounds = 0;
 
     loop {
-        let (nunits, full_round) = step(&map, units, elf_ap, 3);
+        solution::checkpoint();
+        let (nunits, full_round) = step(map, units, elf_ap, 3);
         units = nunits;
+        record::frame(|| Image::from_text(&ascii_art(map, &units), &PALETTE));
 
         if full_round {
             num_rounds += 1
@@ -243,12 +244,12 @@ fn combat(map: &Map, units: &Units, elf_ap: i32) -> (i32, Units) {
     (num_rounds, units)
 }
 
-fn part1(map: &Map, units: &Units) -> i32 {
+pub fn part1(map: &Map, units: &Units) -> i32 {
     let (num_rounds, units) = combat(map, units, 3);
     num_rounds * units.iter().map(|(_, (_, hp))| hp).sum::<i32>()
 }
 
-fn cheat(map: &Map, units: &Units) -> i32 {
+pub fn cheat(map: &Map, units: &Units) -> i32 {
     let num_elves = units
         .iter()
         .map(|(_, (u, _))| u)
@@ -258,7 +259,8 @@ fn cheat(map: &Map, units: &Units) -> i32 {
     let mut elf_attack_power = 0;
     loop {
         elf_attack_power += 1;
-        let (num_rounds, units) = combat(map, units, elf_attack_power);
+        let (num_rounds, units) =
+            profile::span("combat", || combat(map, units, elf_attack_power));
 
         let num_elves_ = units
             .iter()
@@ -266,10 +268,10 @@ fn cheat(map: &Map, units: &Units) -> i32 {
             .filter(|u| **u == Unit::Elf)
             .count();
 
-        println!(
-            "Attack power: {},\tsurviving elves: {}/{}",
+        solution::diagnostic(format!(
+            "Attack power: {}, surviving elves: {}/{}",
             elf_attack_power, num_elves_, num_elves
-        );
+        ));
 
         if num_elves == num_elves_ {
             break num_rounds * units.iter().map(|(_, (_, hp))| hp).sum::<i32>();
@@ -277,12 +279,96 @@ fn cheat(map: &Map, units: &Units) -> i32 {
     }
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december15.txt")?;
-    let (map, units) = parse(&lines)?;
-    println!("{}", ascii_art(&map, &units));
-    println!("Part 1: {:?}", part1(&map, &units));
-    println!("Part 2: {:?}", cheat(&map, &units));
+impl Solution for December15 {
+    type Input = (Map, Units);
+    type Params = ();
+    type Part1 = i32;
+    type Part2 = i32;
+
+    fn parse(lines: &[&str]) -> Result<(Map, Units)> {
+        parse(lines)
+    }
+
+    fn part1((map, units): &(Map, Units), _: &()) -> Result<i32> {
+        Ok(part1(map, units))
+    }
+
+    fn part2((map, units): &(Map, Units), _: &()) -> Result<i32> {
+        Ok(cheat(map, units))
+    }
+
+    /// The cave after the combat of part 1.
+    fn render((map, units): &(Map, Units), _: &()) -> Result<Image> {
+        let (_, survivors) = combat(map, units, 3);
+        Ok(Image::from_text(&ascii_art(map, &survivors), &PALETTE))
+    }
+
+    /// A cave with sides of `size` squares, at least 7, with `size / 2`
+    /// units, at least one of each kind. Only the largest connected part of
+    /// the cave is kept open, so all units can reach each other.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let side = size.max(7);
+        let units = (size / 2).max(2);
+
+        loop {
+            let mut cave = vec![vec!['#'; side]; side];
+            for row in &mut cave[1..side - 1] {
+                for square in &mut row[1..side - 1] {
+                    if rng.chance(0.75) {
+                        *square = '.';
+                    }
+                }
+            }
+
+            // Keep the largest part of the cave, walling in the others.
+            let mut seen = vec![vec![false; side]; side];
+            let mut largest = (0, Vec::new());
+            for y in 0..side {
+                for x in 0..side {
+                    if cave[y][x] != '.' || seen[y][x] {
+                        continue;
+                    }
+                    let mut part = vec![(x, y)];
+                    seen[y][x] = true;
+                    let mut n = 0;
+                    while let Some(&(x, y)) = part.get(n) {
+                        for &(nx, ny) in
+                            &[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                        {
+                            if cave[ny][nx] == '.' && !seen[ny][nx] {
+                                seen[ny][nx] = true;
+                                part.push((nx, ny));
+                            }
+                        }
+                        n += 1;
+                    }
+                    if part.len() > largest.0 {
+                        largest = (part.len(), part);
+                    }
+                }
+            }
+            let (_, mut open) = largest;
+            if open.len() < units * 2 {
+                continue;
+            }
+
+            for row in &mut cave {
+                for square in row.iter_mut() {
+                    *square = '#';
+                }
+            }
+            for &(x, y) in &open {
+                cave[y][x] = '.';
+            }
+            rng.shuffle(&mut open);
+            for (n, &(x, y)) in open[..units].iter().enumerate() {
+                cave[y][x] = if n % 2 == 0 { 'E' } else { 'G' };
+            }
 
-    Ok(())
+            return cave
+                .iter()
+                .map(|row| row.iter().chain(Some(&'\n')).collect::<String>())
+                .collect();
+        }
+    }
 }
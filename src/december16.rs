@@ -1,63 +1,31 @@
+use elfcode::{Instr, Machine, OpCode, OP_CODES};
+use error::Error;
+use generate::Rng;
+use parse::{self, Line, Section};
+use solution::{Result, Solution};
 use std::collections::HashSet;
-mod utils;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December16;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-enum OpCode {
-    Addr, Addi, Mulr, Muli,
-    Banr, Bani, Borr, Bori,
-    Setr, Seti,
-    Gtir, Gtri, Gtrr, Eqir, Eqri, Eqrr,
-}
-use OpCode::*;
-
-static OP_CODES: &[OpCode] = &[
-    Addr, Addi, Mulr, Muli,
-    Banr, Bani, Borr, Bori,
-    Setr, Seti,
-    Gtir, Gtri, Gtrr, Eqir, Eqri, Eqrr,
-];
-
-type Instr = (OpCode, i32, i32, i32);
-
-fn execute(instr: Instr, reg_bank: &mut [i32]) -> &mut [i32] {
-    macro_rules! r {
-        ( $x:expr ) => {
-            reg_bank[$x as usize]
-        };
-    }
+/// An instruction with its opcode number still unknown.
+pub type RawInstr = [i32; 4];
 
-    let (op_code, i1, i2, rr) = instr;
-    reg_bank[rr as usize] = match op_code {
-        Addr => r!(i1) + r!(i2),
-        Addi => r!(i1) + i2,
-        Mulr => r!(i1) * r!(i2),
-        Muli => r!(i1) * i2,
-        Banr => r!(i1) & r!(i2),
-        Bani => r!(i1) & i2,
-        Borr => r!(i1) | r!(i2),
-        Bori => r!(i1) | i2,
-        Setr => r!(i1),
-        Seti => i1,
-        Gtir => (i1 > r!(i2)).into(),
-        Gtri => (r!(i1) > i2).into(),
-        Gtrr => (r!(i1) > r!(i2)).into(),
-        Eqir => (i1 == r!(i2)).into(),
-        Eqri => (r!(i1) == i2).into(),
-        Eqrr => (r!(i1) == r!(i2)).into(),
-    };
-    reg_bank
+/// The registers before and after executing an instruction.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub before: Vec<i32>,
+    pub instr: RawInstr,
+    pub after: Vec<i32>,
 }
 
-fn solve_constraints(map: &mut Vec<HashSet<OpCode>>) {
+pub fn solve_constraints(map: &mut [HashSet<OpCode>]) {
     let mut mutated = true;
     while mutated {
         mutated = false;
 
-        for (idx, set) in map.clone().iter().enumerate() {
+        for (idx, set) in map.to_vec().iter().enumerate() {
             if set.len() == 1 {
-                let op_code = set.iter().nth(0).unwrap();
+                let op_code = set.iter().next().unwrap();
                 for (idx2, set2) in map.iter_mut().enumerate() {
                     if idx != idx2 && set2.remove(op_code) {
                         mutated = true;
@@ -68,33 +36,68 @@ fn solve_constraints(map: &mut Vec<HashSet<OpCode>>) {
     }
 }
 
-fn find_op_codes(lines: &[String]) -> Result<(u32, Vec<OpCode>)> {
+/// Parse registers like `Before: [3, 2, 1, 1]`.
+fn parse_registers(line: Line, prefix: &str) -> Result<Vec<i32>> {
+    let registers = line.after(prefix)?.trim();
+    let (registers, _) = registers
+        .after("[")
+        .and_then(|registers| registers.split_once("]"))
+        .map_err(|_| registers.error("expected `[a, b, c, d]`"))?;
+    let [a, b, c, d] = registers.signed_n("four registers")?;
+
+    Ok(vec![a, b, c, d])
+}
+
+/// Parse an instruction like `9 0 0 1`.
+fn parse_instr(line: Line) -> Result<RawInstr> {
+    let parts = line.split(" ");
+    if parts.len() != 4 {
+        return Err(line.error("expected an instruction like `9 0 0 1`"));
+    }
+
+    let mut instr = [0; 4];
+    for (n, part) in parts.iter().enumerate() {
+        instr[n] = part.parse()?;
+        let max = if n == 0 { 16 } else { 4 };
+        if instr[n] < 0 || instr[n] >= max {
+            return Err(part.error(format!("expected a value from 0 to {}", max - 1)));
+        }
+    }
+
+    Ok(instr)
+}
+
+/// Parse samples of three lines each, separated by blank lines: the
+/// registers before, the instruction, and the registers after.
+pub fn parse_samples(samples: Section) -> Result<Vec<Sample>> {
+    samples
+        .sections(1)
+        .iter()
+        .map(|sample| {
+            if sample.lines.len() > 3 {
+                return Err(sample.line(3).error("expected a blank line"));
+            }
+
+            Ok(Sample {
+                before: parse_registers(sample.line(0), "Before:")?,
+                instr: parse_instr(sample.line(1))?,
+                after: parse_registers(sample.line(2), "After:")?,
+            })
+        })
+        .collect()
+}
+
+pub fn find_op_codes(samples: &[Sample]) -> Result<(u32, Vec<OpCode>)> {
     let mut valid: Vec<HashSet<OpCode>> =
         vec![OP_CODES.iter().cloned().collect(); 16];
     let mut count = 0;
 
-    for idx in (0..=lines.len()).step_by(4) {
-        let before = lines[idx][9..=18]
-            .split(", ")
-            .map(|s| Ok(s.parse::<i32>()?))
-            .collect::<Result<Vec<_>>>()?;
-        let instr = lines[idx + 1]
-            .split(' ')
-            .map(|s| Ok(s.parse::<i32>()?))
-            .collect::<Result<Vec<_>>>()?;
-        let after = lines[idx + 2][9..=18]
-            .split(", ")
-            .map(|s| Ok(s.parse::<i32>()?))
-            .collect::<Result<Vec<_>>>()?;
-
+    for Sample { before, instr, after } in samples {
         let mut count_valid = 0;
         for op_code in OP_CODES {
-            if after
-                == execute(
-                    (*op_code, instr[1], instr[2], instr[3]),
-                    &mut before.clone(),
-                )
-            {
+            let mut machine = Machine::with_registers(before.clone());
+            let executed = machine.execute(Instr::new(*op_code, instr[1], instr[2], instr[3]));
+            if executed.is_ok() && machine.registers == *after {
                 count_valid += 1;
             } else {
                 valid[instr[0] as usize].remove(op_code);
@@ -107,35 +110,127 @@ fn find_op_codes(lines: &[String]) -> Result<(u32, Vec<OpCode>)> {
     }
     solve_constraints(&mut valid);
 
-    Ok((
-        count,
-        valid.iter().map(|s| *s.iter().nth(0).unwrap()).collect(),
-    ))
+    let mapping = valid
+        .iter()
+        .map(|s| {
+            s.iter().next().cloned().ok_or_else(|| {
+                Error::unsolvable("The samples do not match any opcode")
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((count, mapping))
 }
 
-fn run(lines: &[String], mapping: Vec<OpCode>) -> Result<i32> {
-    let mut reg_bank = vec![0, 0, 0, 0];
+pub fn run(program: &[RawInstr], mapping: Vec<OpCode>) -> Result<i32> {
+    let program = program
+        .iter()
+        .map(|instr| Instr::new(mapping[instr[0] as usize], instr[1], instr[2], instr[3]))
+        .collect::<Vec<_>>();
+
+    let mut machine = Machine::new(4);
+    machine.run(&program)?;
+    Ok(machine.registers[0])
+}
 
-    for line in lines {
-        let instr = line
-            .split(' ')
-            .map(|s| Ok(s.parse::<i32>()?))
+impl Solution for December16 {
+    /// The samples and the test program.
+    type Input = (Vec<Sample>, Vec<RawInstr>);
+    type Params = ();
+    type Part1 = u32;
+    type Part2 = i32;
+
+    fn parse(lines: &[&str]) -> Result<Self::Input> {
+        // The samples are separated by a blank line, and from the test
+        // program by several.
+        let sections = parse::sections(lines, 2);
+        let samples = match sections.first() {
+            Some(samples) => parse_samples(*samples)?,
+            None => Vec::new(),
+        };
+        let program = sections
+            .iter()
+            .skip(1)
+            .flat_map(|section| section.lines())
+            .map(parse_instr)
             .collect::<Result<Vec<_>>>()?;
-        execute(
-            (mapping[instr[0] as usize], instr[1], instr[2], instr[3]),
-            &mut reg_bank,
-        );
+
+        Ok((samples, program))
     }
 
-    Ok(reg_bank[0])
-}
+    fn part1((samples, _): &Self::Input, _: &()) -> Result<u32> {
+        let (count_many_matches, _) = find_op_codes(samples)?;
+        Ok(count_many_matches)
+    }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december16.txt")?;
-    let (count_many_matches, mapping) = find_op_codes(&lines[..3259])?;
+    fn part2((samples, program): &Self::Input, _: &()) -> Result<i32> {
+        let (_, mapping) = find_op_codes(samples)?;
+        run(program, mapping)
+    }
 
-    println!("Part 1: {:?}", count_many_matches);
-    println!("Part 2: {:?}", run(&lines[3262..], mapping)?);
+    /// `20 * size` samples of a random assignment of opcode numbers, with
+    /// more added until they pin down the assignment, and a test program of
+    /// `20 * size` instructions.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut op_codes = OP_CODES.to_vec();
+        rng.shuffle(&mut op_codes);
+
+        let random_instr = |rng: &mut Rng| -> RawInstr {
+            [
+                rng.range(0, 15) as i32,
+                rng.range(0, 3) as i32,
+                rng.range(0, 3) as i32,
+                rng.range(0, 3) as i32,
+            ]
+        };
 
-    Ok(())
+        let mut text = String::new();
+        let mut valid: Vec<HashSet<OpCode>> =
+            vec![OP_CODES.iter().cloned().collect(); 16];
+        let mut samples = 0;
+        while samples < (size * 20).max(1)
+            || valid.iter().any(|set| set.len() > 1)
+        {
+            let before =
+                (0..4).map(|_| rng.range(0, 3) as i32).collect::<Vec<_>>();
+            let instr = random_instr(rng);
+            let execute = |op_code| {
+                let mut machine = Machine::with_registers(before.clone());
+                machine
+                    .execute(Instr::new(op_code, instr[1], instr[2], instr[3]))
+                    .map(|_| machine.registers)
+            };
+            let after = execute(op_codes[instr[0] as usize])
+                .expect("operands are registers 0 to 3");
+
+            valid[instr[0] as usize].retain(|&op_code| {
+                execute(op_code).ok().as_ref() == Some(&after)
+            });
+            solve_constraints(&mut valid);
+            samples += 1;
+
+            let registers = |r: &[i32]| {
+                r.iter().map(i32::to_string).collect::<Vec<_>>().join(", ")
+            };
+            text.push_str(&format!(
+                "Before: [{}]\n{} {} {} {}\nAfter:  [{}]\n\n",
+                registers(&before),
+                instr[0],
+                instr[1],
+                instr[2],
+                instr[3],
+                registers(&after)
+            ));
+        }
+
+        text.push_str("\n\n");
+        for _ in 0..(size * 20).max(1) {
+            let instr = random_instr(rng);
+            text.push_str(&format!(
+                "{} {} {} {}\n",
+                instr[0], instr[1], instr[2], instr[3]
+            ));
+        }
+        text
+    }
 }
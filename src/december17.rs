@@ -1,47 +1,65 @@
-use std::collections::HashMap;
-mod utils;
+use error::Error;
+use generate::Rng;
+use grid::{Pos, SparseGrid};
+use parse::{self, Line};
+use record;
+use render::{Image, Palette, Rgb};
+use solution::{Result, Solution};
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-type Position = (i32, i32);
+pub struct December17;
+
+pub type Map = SparseGrid<Tile>;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-enum Tile {
+pub enum Tile {
     Clay,
     SettledWater,
     ActiveWater,
 }
 
-fn parse(lines: &[String]) -> Result<HashMap<Position, Tile>> {
-    let mut map = HashMap::new();
-    for line in lines {
-        let parts = line.split(", ").collect::<Vec<_>>();
-        let i = parts
-            .get(0)
-            .and_then(|p| p.get(2..))
-            .ok_or("Malformed data")?
-            .parse::<i32>()?;
-        let js = parts
-            .get(1)
-            .and_then(|p| p.get(2..))
-            .ok_or("Malformed data")?
-            .split("..")
-            .map(|x| Ok(x.parse::<i32>()?))
-            .collect::<Result<Vec<_>>>()?;
-
-        for j in js[0]..=js[1] {
-            if line.chars().nth(0).unwrap() == 'y' {
-                map.insert((j, i), Tile::Clay);
+/// Parse a coordinate like `x=495` or a range like `y=2..7`.
+fn parse_range(range: Line) -> Result<(i32, i32)> {
+    let values = range
+        .split("..")
+        .iter()
+        .map(Line::parse)
+        .collect::<Result<Vec<i32>>>()?;
+
+    match values[..] {
+        [x] => Ok((x, x)),
+        [from, to] if from <= to => Ok((from, to)),
+        _ => Err(range.error(format!("invalid range `{}`", range.text))),
+    }
+}
+
+pub fn parse(lines: &[&str]) -> Result<Map> {
+    let mut map = SparseGrid::sparse();
+    for line in parse::lines(lines) {
+        let (xs, ys) = (line.field("x")?, line.field("y")?);
+        // Veins are horizontal when `y` comes first, with a single value.
+        let horizontal = ys.offset() < xs.offset();
+        let (i, js) = if horizontal { (ys, xs) } else { (xs, ys) };
+        let (i, _) = parse_range(i)?;
+        let (from, to) = parse_range(js)?;
+
+        for j in from..=to {
+            if horizontal {
+                map.insert(Pos::new(j, i), Tile::Clay);
             } else {
-                map.insert((i, j), Tile::Clay);
+                map.insert(Pos::new(i, j), Tile::Clay);
             }
         }
     }
 
+    if map.is_empty() {
+        return Err(Error::input(0, 0, "expected at least one vein of clay"));
+    }
+
     Ok(map)
 }
 
-fn simulate(
-    map: &mut HashMap<Position, Tile>,
+pub fn simulate(
+    map: &mut Map,
     spring_x: i32,
     spring_y: i32,
     max_y: i32,
@@ -67,8 +85,8 @@ fn simulate(
             }
 
             let x = spring_x + if dir == LEFT { -dx } else { dx };
-            let tile = map.get(&(x, spring_y)).cloned();
-            if tile != None {
+            let tile = map.get(Pos::new(x, spring_y)).cloned();
+            if tile.is_some() {
                 assert!(dx != 0, "Spring is in clay!");
                 if dir == LEFT {
                     spread_left = false;
@@ -78,11 +96,11 @@ fn simulate(
                 continue;
             }
 
-            tiles.push((x, spring_y));
-            let mut below = map.get(&(x, spring_y + 1)).cloned();
-            if below == None {
+            tiles.push(Pos::new(x, spring_y));
+            let mut below = map.get(Pos::new(x, spring_y + 1)).cloned();
+            if below.is_none() {
                 simulate(map, x, spring_y + 1, max_y);
-                below = map.get(&(x, spring_y + 1)).cloned();
+                below = map.get(Pos::new(x, spring_y + 1)).cloned();
             }
 
             if below == Some(Tile::ActiveWater) || spring_y == max_y {
@@ -109,46 +127,113 @@ fn simulate(
             },
         );
     }
+    record::frame(|| Image::from_text(&ascii_art(map), &PALETTE));
+}
+
+static PALETTE: Palette = Palette(&[
+    (' ', Rgb(0xc2, 0xb2, 0x80)),
+    ('#', Rgb(0x8b, 0x45, 0x13)),
+    ('~', Rgb(0x00, 0x50, 0xc8)),
+    ('|', Rgb(0x64, 0xaa, 0xff)),
+]);
+
+pub fn ascii_art(map: &Map) -> String {
+    map.render(|_, tile| match tile {
+        Some(Tile::Clay) => '#',
+        Some(Tile::SettledWater) => '~',
+        Some(Tile::ActiveWater) => '|',
+        None => ' ',
+    })
+}
+
+/// Let the water flow from the spring at x=500 down to the lowest clay.
+pub fn flood(map: &Map) -> Map {
+    let mut flooded = map.clone();
+    // Parsing checks there is clay.
+    let bounds = map.bounds().unwrap();
+    simulate(&mut flooded, 500, bounds.min.y, bounds.max.y);
+    flooded
 }
 
-fn ascii_art(map: &HashMap<Position, Tile>) -> String {
-    let min_x = map.keys().map(|&(x, _)| x).min().unwrap();
-    let max_x = map.keys().map(|&(x, _)| x).max().unwrap();
-    let min_y = map.keys().map(|&(_, y)| y).min().unwrap();
-    let max_y = map.keys().map(|&(_, y)| y).max().unwrap();
-
-    let mut s = "".to_string();
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            match map.get(&(x, y)) {
-                Some(Tile::Clay) => s.push('#'),
-                Some(Tile::SettledWater) => s.push('~'),
-                Some(Tile::ActiveWater) => s.push('|'),
-                None => s.push(' '),
+impl Solution for December17 {
+    type Input = Map;
+    type Params = ();
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(lines: &[&str]) -> Result<Self::Input> {
+        parse(lines)
+    }
+
+    fn part1(map: &Self::Input, _: &()) -> Result<usize> {
+        Ok(flood(map)
+            .iter()
+            .filter(|&(_, &v)| v == Tile::ActiveWater || v == Tile::SettledWater)
+            .count())
+    }
+
+    fn part2(map: &Self::Input, _: &()) -> Result<usize> {
+        Ok(flood(map)
+            .iter()
+            .filter(|&(_, &v)| v == Tile::SettledWater)
+            .count())
+    }
+
+    /// The ground after the water has flowed.
+    fn render(map: &Map, _: &()) -> Result<Image> {
+        Ok(Image::from_text(&ascii_art(&flood(map)), &PALETTE))
+    }
+
+    /// About `8 * size` veins of clay: buckets, that is two walls and a floor,
+    /// and loose horizontal veins, spread below the spring over `30 * size`
+    /// rows. Veins do not touch, and none has clay at x=500 in its top row,
+    /// so the spring, which is level with the highest clay, is in sand.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let depth = (size as i64 * 30).max(30);
+        let spread = (size as i64 * 4).max(20);
+
+        let mut veins: Vec<(i64, i64, i64, i64)> = Vec::new();
+        let mut lines = Vec::new();
+        let mut tries = 0;
+        while veins.len() < 8 * size.max(1) && tries < 100 * size.max(1) {
+            tries += 1;
+            let bucket = rng.chance(0.7);
+            let (width, height) = if bucket {
+                (rng.range(2, 20), rng.range(2, 15))
+            } else {
+                (rng.range(1, 10), 0)
+            };
+            let left = 500 + rng.range(-spread, spread - width);
+            let top = rng.range(1, depth - height);
+            let (right, bottom) = (left + width, top + height);
+
+            // Keep sand around every vein.
+            let touches = |&(l, t, r, b): &(i64, i64, i64, i64)| {
+                left <= r + 1
+                    && l <= right + 1
+                    && top <= b + 1
+                    && t <= bottom + 1
+            };
+            let below_spring = if bucket {
+                left == 500 || right == 500
+            } else {
+                left <= 500 && 500 <= right
+            };
+            if veins.iter().any(touches) || below_spring {
+                continue;
+            }
+            veins.push((left, top, right, bottom));
+
+            if bucket {
+                lines.push(format!("x={}, y={}..{}", left, top, bottom));
+                lines.push(format!("x={}, y={}..{}", right, top, bottom));
+                lines.push(format!("y={}, x={}..{}", bottom, left, right));
+            } else {
+                lines.push(format!("y={}, x={}..{}", top, left, right));
             }
         }
-        s.push('\n');
-    }
-    s
-}
+        rng.shuffle(&mut lines);
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december17.txt")?;
-    let mut map = parse(&lines)?;
-    let min_y = map.keys().map(|&(_, y)| y).min().unwrap();
-    let max_y = map.keys().map(|&(_, y)| y).max().unwrap();
-    simulate(&mut map, 500, min_y, max_y);
-    println!("{}", ascii_art(&map));
-
-    let count_all = map
-        .values()
-        .filter(|&&v| v == Tile::ActiveWater || v == Tile::SettledWater)
-        .count();
-    let count_settled =
-        map.values().filter(|&&v| v == Tile::SettledWater).count();
-
-    println!("Part 1: {}", count_all);
-    println!("Part 2: {}", count_settled);
-
-    Ok(())
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
 }
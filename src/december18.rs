@@ -1,62 +1,61 @@
-mod utils;
+use error::Error;
+use generate::Rng;
+use grid::{Grid, Pos};
+use record;
+use render::{Image, Palette, Rgb};
+use solution::{Result, Solution};
 use std::collections::HashMap;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December18;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
-enum Tile {
+pub enum Tile {
     Open,
     Trees,
     Lumberyard,
 }
 
-type Map = Vec<Vec<Tile>>;
+pub type Map = Grid<Tile>;
 
-fn parse(lines: &[String]) -> Result<Map> {
-    let mut map =
-        vec![
-            vec![Tile::Open; lines.get(0).ok_or("Malformed input")?.len()];
-            lines.len()
-        ];
+pub fn parse(lines: &[&str]) -> Result<Map> {
+    let width = lines
+        .first()
+        .map(|line| line.len())
+        .ok_or_else(|| Error::input(0, 0, "expected a map of the area"))?;
 
     for (i, line) in lines.iter().enumerate() {
-        for (j, c) in line.chars().enumerate() {
-            map[i][j] = match c {
-                '.' => Tile::Open,
-                '|' => Tile::Trees,
-                '#' => Tile::Lumberyard,
-                _ => Err("Malformed input")?,
-            };
+        if line.len() != width {
+            Err(Error::input(i, 0, format!("expected a row of width {}", width)))?
         }
     }
 
-    Ok(map)
+    Grid::parse_with(lines, |pos, c| match c {
+        '.' => Ok(Tile::Open),
+        '|' => Ok(Tile::Trees),
+        '#' => Ok(Tile::Lumberyard),
+        _ => Err(Error::input(
+            pos.y as usize,
+            pos.x as usize,
+            format!("invalid acre `{}`", c),
+        )),
+    })
 }
 
-fn count(map: &Map, (i, j): (usize, usize)) -> (u8, u8, u8) {
-    let (i, j) = (i as i32, j as i32);
+pub fn count(map: &Map, pos: Pos) -> (u8, u8, u8) {
     let (mut open, mut trees, mut lumberyard) = (0, 0, 0);
-    for di in -1..=1 {
-        for dj in -1..=1 {
-            if di == 0 && dj == 0
-                || i + di < 0
-                || i + di >= map.len() as i32
-                || j + dj < 0
-                || j + dj >= map[0].len() as i32
-            {
-                continue;
-            }
-            match map[(i + di) as usize][(j + dj) as usize] {
-                Tile::Open => open += 1,
-                Tile::Trees => trees += 1,
-                Tile::Lumberyard => lumberyard += 1,
-            }
+    for neighbour in map.neighbours8(pos) {
+        match map[neighbour] {
+            Tile::Open => open += 1,
+            Tile::Trees => trees += 1,
+            Tile::Lumberyard => lumberyard += 1,
         }
     }
     (open, trees, lumberyard)
 }
 
-fn run(mut map: Map, iterations: u64) -> i32 {
+/// The area after the given number of minutes. Areas repeat after a while,
+/// so the minutes after the first repeat are skipped.
+pub fn evolve(mut map: Map, iterations: u64) -> Map {
     let mut visited = HashMap::new();
 
     for epoch in 0..iterations {
@@ -67,8 +66,7 @@ fn run(mut map: Map, iterations: u64) -> i32 {
             map = visited
                 .iter()
                 .filter(|&(&_, &v): &(&Map, &u64)| v == first_epoch + idx)
-                .map(|(k, _)| k.clone())
-                .nth(0)
+                .map(|(k, _)| k.clone()).next()
                 .unwrap();
             break;
         } else {
@@ -76,59 +74,82 @@ fn run(mut map: Map, iterations: u64) -> i32 {
         }
 
         let mut map_ = map.clone();
-        for (i, row) in map_.iter_mut().enumerate() {
-            for (j, tile) in row.iter_mut().enumerate() {
-                let (_open, trees, lumberyard) = count(&map, (i, j));
-                if *tile == Tile::Open && trees >= 3 {
-                    *tile = Tile::Trees;
-                } else if *tile == Tile::Trees && lumberyard >= 3 {
-                    *tile = Tile::Lumberyard;
-                } else if *tile == Tile::Lumberyard
-                    && (trees == 0 || lumberyard == 0)
-                {
-                    *tile = Tile::Open;
-                }
+        for (pos, &tile) in map.iter() {
+            let (_open, trees, lumberyard) = count(&map, pos);
+            if tile == Tile::Open && trees >= 3 {
+                map_[pos] = Tile::Trees;
+            } else if tile == Tile::Trees && lumberyard >= 3 {
+                map_[pos] = Tile::Lumberyard;
+            } else if tile == Tile::Lumberyard && (trees == 0 || lumberyard == 0) {
+                map_[pos] = Tile::Open;
             }
         }
         map = map_;
+        record::frame(|| Image::from_text(&ascii_art(&map), &PALETTE));
     }
 
-    let trees: i32 = map
-        .iter()
-        .map(|row| row.iter().filter(|&&t| t == Tile::Trees).count() as i32)
-        .sum();
-    let lumberyards: i32 = map
-        .iter()
-        .map(|row| {
-            row.iter().filter(|&&t| t == Tile::Lumberyard).count() as i32
-        })
-        .sum();
+    map
+}
+
+pub fn run(map: Map, iterations: u64) -> i32 {
+    let map = evolve(map, iterations);
+    let trees = map.iter().filter(|&(_, &t)| t == Tile::Trees).count() as i32;
+    let lumberyards =
+        map.iter().filter(|&(_, &t)| t == Tile::Lumberyard).count() as i32;
 
     trees * lumberyards
 }
 
-fn ascii_art(map: &Map) -> String {
-    let mut s = "".to_owned();
+static PALETTE: Palette = Palette(&[
+    (' ', Rgb(0xc8, 0xbe, 0x8c)),
+    ('|', Rgb(0x00, 0x78, 0x00)),
+    ('#', Rgb(0x8c, 0x5a, 0x28)),
+]);
+
+pub fn ascii_art(map: &Map) -> String {
+    map.render(|_, tile| match tile {
+        Some(Tile::Trees) => '|',
+        Some(Tile::Lumberyard) => '#',
+        _ => ' ',
+    })
+}
 
-    for row in map.iter() {
-        for tile in row {
-            match tile {
-                Tile::Open => s.push(' '),
-                Tile::Trees => s.push('|'),
-                Tile::Lumberyard => s.push('#'),
-            }
-        }
-        s.push('\n');
+impl Solution for December18 {
+    type Input = Map;
+    type Params = ();
+    type Part1 = i32;
+    type Part2 = i32;
+
+    fn parse(lines: &[&str]) -> Result<Map> {
+        parse(lines)
     }
-    s
-}
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december18.txt")?;
-    let map = parse(&lines)?;
-    println!("{}", ascii_art(&map));
+    fn part1(map: &Map, _: &()) -> Result<i32> {
+        Ok(run(map.clone(), 10))
+    }
+
+    fn part2(map: &Map, _: &()) -> Result<i32> {
+        Ok(run(map.clone(), 1_000_000_000))
+    }
+
+    /// The area after ten minutes.
+    fn render(map: &Map, _: &()) -> Result<Image> {
+        Ok(Image::from_text(&ascii_art(&evolve(map.clone(), 10)), &PALETTE))
+    }
 
-    println!("Part 1: {:?}", run(map.clone(), 10));
-    println!("Part 2: {:?}", run(map.clone(), 1_000_000_000));
-    Ok(())
+    /// An area with sides of `size` acres, at least three, of randomly
+    /// mixed open ground, trees and lumberyards.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let side = size.max(3);
+        let mut text = String::with_capacity((side + 1) * side);
+        for _ in 0..side {
+            for _ in 0..side {
+                text.push(
+                    *rng.choose(&['.', '.', '.', '.', '|', '|', '|', '#', '#']),
+                );
+            }
+            text.push('\n');
+        }
+        text
+    }
 }
@@ -1,167 +1,67 @@
-#[macro_use]
-extern crate maplit;
-use std::collections::HashMap;
-mod utils;
-
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-enum OpCode {
-    Addr,
-    Addi,
-    Mulr,
-    Muli,
-    Banr,
-    Bani,
-    Borr,
-    Bori,
-    Setr,
-    Seti,
-    Gtir,
-    Gtri,
-    Gtrr,
-    Eqir,
-    Eqri,
-    Eqrr,
-}
-use OpCode::*;
-
-type Instr = (OpCode, i32, i32, i32);
-
-fn execute(instr: Instr, reg_bank: &mut [i32]) -> &mut [i32] {
-    macro_rules! r {
-        ( $x:expr ) => {
-            reg_bank[$x as usize]
-        };
+use elfcode::{Instr, Machine, OpCode, Program, Word};
+use error::Error;
+use generate::Rng;
+use optimise::{Compiled, Idiom};
+use solution::{Result, Solution};
+use std::fmt::Display;
+use std::str::FromStr;
+
+pub struct December19;
+
+static REGISTERS: usize = 6;
+
+pub fn parse<W>(lines: &[&str]) -> Result<Program<W>>
+where
+    W: Word + FromStr,
+    W::Err: Display,
+{
+    let program = Program::parse(lines, REGISTERS)?;
+    if program.ip_register.is_none() {
+        return Err(Error::input(0, 0, "expected `#ip <register>`"));
     }
-
-    let (op_code, i1, i2, rr) = instr;
-    reg_bank[rr as usize] = match op_code {
-        Addr => r!(i1) + r!(i2),
-        Addi => r!(i1) + i2,
-        Mulr => r!(i1) * r!(i2),
-        Muli => r!(i1) * i2,
-        Banr => r!(i1) & r!(i2),
-        Bani => r!(i1) & i2,
-        Borr => r!(i1) | r!(i2),
-        Bori => r!(i1) | i2,
-        Setr => r!(i1),
-        Seti => i1,
-        Gtir => (i1 > r!(i2)).into(),
-        Gtri => (r!(i1) > i2).into(),
-        Gtrr => (r!(i1) > r!(i2)).into(),
-        Eqir => (i1 == r!(i2)).into(),
-        Eqri => (r!(i1) == i2).into(),
-        Eqrr => (r!(i1) == r!(i2)).into(),
-    };
-    reg_bank
+    Ok(program)
 }
 
-fn parse(lines: &[String]) -> Result<(usize, Vec<Instr>)> {
-    let ip = lines
-        .get(0)
-        .and_then(|s| s.split(" ").nth(1))
-        .ok_or("Malformed input")?
-        .parse::<usize>()?;
-    let mut instructions = Vec::new();
-    let str_to_op: HashMap<&str, OpCode> = hashmap! {
-        "addr" => Addr,
-        "addi" => Addi,
-        "mulr" => Mulr,
-        "muli" => Muli,
-        "banr" => Banr,
-        "bani" => Bani,
-        "borr" => Borr,
-        "bori" => Bori,
-        "setr" => Setr,
-        "seti" => Seti,
-        "gtir" => Gtir,
-        "gtri" => Gtri,
-        "gtrr" => Gtrr,
-        "eqir" => Eqir,
-        "eqri" => Eqri,
-        "eqrr" => Eqrr
-    };
-
-    for line in &lines[1..] {
-        let split = line.split(' ').collect::<Vec<_>>();
-        let args = split[1..]
-            .iter()
-            .map(|s| Ok(s.parse::<i32>()?))
-            .collect::<Result<Vec<i32>>>()?;
-        let instr: Instr = (
-            *str_to_op.get(split[0]).ok_or("Malformed input")?,
-            args[0],
-            args[1],
-            args[2],
-        );
-        instructions.push(instr);
-    }
-
-    Ok((ip, instructions))
+pub fn run(program: &Program<i32>) -> Result<i32> {
+    let mut machine = Machine::for_program(program, REGISTERS)?;
+    machine.run(&program.instrs)?;
+    Ok(machine.registers[0])
 }
 
-fn run(ip_reg: usize, instrs: &[Instr]) -> Result<i32> {
-    let mut ip = 0;
-    let mut reg_bank = vec![0, 0, 0, 0, 0, 0];
+/// The number whose divisors the program sums. The program first works it out
+/// from register 0, and then jumps to instruction 1 to start summing, with
+/// the number in the register that instruction 4 compares with.
+pub fn number(program: &Program<i32>, r0: i32) -> Result<i32> {
+    let register = match program.instrs.get(4) {
+        Some(&Instr { op: OpCode::Eqrr, b, .. }) => b,
+        _ => {
+            return Err(Error::unsolvable(
+                "expected the program to compare with the number at instruction 4",
+            ))
+        }
+    };
 
-    while ip >= 0 && ip < instrs.len() as i32 {
-        reg_bank[ip_reg] = ip;
-        execute(instrs[ip as usize], &mut reg_bank);
-        ip = reg_bank[ip_reg] + 1;
+    let mut machine = Machine::for_program(program, REGISTERS)?;
+    machine.registers[0] = r0;
+    // Working out the number takes a few dozen instructions.
+    let mut steps = 0;
+    machine.run_until(&program.instrs, |machine| {
+        steps += 1;
+        machine.ip == 1 || steps > 1000
+    })?;
+
+    match register.to_index().and_then(|r| machine.registers.get(r)) {
+        Some(&n) if machine.ip == 1 => Ok(n),
+        _ => Err(Error::unsolvable(
+            "expected the program to jump to instruction 1 with the number",
+        )),
     }
-
-    Ok(reg_bank[0])
 }
 
-fn decompiled(return_number_to_factorize: bool) -> i32 {
-    let mut r = vec![1, 0, 0, 0, 0, 0];
-
-    // 17 - 20
-    r[4] += 2;
-    r[4] *= r[4];
-    r[4] *= 19;
-    r[4] *= 11;
-
-    // 21 - 23
-    r[1] += 6;
-    r[1] *= 22;
-    r[1] += 10;
-
-    // 24
-    r[4] += r[1];
-
-    if r[0] > 0 {
-        // 27
-        r[1] = 27;
-
-        // 28
-        r[1] *= 28;
-
-        // 29
-        r[1] += 29;
-
-        // 30
-        r[1] *= 30;
-
-        // 31
-        r[1] *= 14;
-
-        // 32
-        r[1] *= 32;
-
-        // 33
-        r[4] += r[1];
-
-        // 34
-        r[0] = 0;
-
-        // 35
-    }
-
-    if return_number_to_factorize {
-        return r[4];
-    }
+/// The sum of the divisors of `n`, computed like the program does from
+/// instruction 1 on, by trying every pair of numbers up to `n`.
+pub fn decompiled(n: i32) -> i32 {
+    let mut r = [0, 0, 0, 0, n, 0];
 
     // 01
     r[5] = 1;
@@ -191,16 +91,109 @@ fn decompiled(return_number_to_factorize: bool) -> i32 {
     r[0]
 }
 
-fn factorize(n: i32) -> i32 {
+/// Run the program with the given value in register 0 on the optimising
+/// interpreter, which sums the divisors natively, and return register 0. Its
+/// values are widened, so that the products it tries do not overflow. Without
+/// the loop to sum them natively the program would run practically forever,
+/// so it is an error.
+pub fn optimised(program: &Program<i32>, r0: i64) -> Result<i64> {
+    let program = program.widened::<i64>();
+    let compiled = Compiled::new(&program, REGISTERS)?;
+    let divisor_sum = compiled
+        .idioms()
+        .iter()
+        .any(|&(_, idiom)| matches!(idiom, Idiom::DivisorSum { .. }));
+    if !divisor_sum {
+        return Err(Error::unsolvable("expected the divisor-sum loop"));
+    }
+    let mut machine = Machine::for_program(&program, REGISTERS)?;
+    machine.registers[0] = r0;
+    compiled.run(&mut machine)?;
+    Ok(machine.registers[0])
+}
+
+pub fn factorize(n: i32) -> i32 {
     (1..=n).filter(|m| n % m == 0).sum()
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december19.txt")?;
-    let (ip_reg, instrs) = parse(&lines)?;
+/// The program of the puzzle, with placeholders for the constants that make
+/// up the number whose divisors are summed. Generated inputs fill them in.
+static TEMPLATE: &str = "\
+#ip 3
+addi 3 16 3
+seti 1 6 5
+seti 1 8 2
+mulr 5 2 1
+eqrr 1 4 1
+addr 1 3 3
+addi 3 1 3
+addr 5 0 0
+addi 2 1 2
+gtrr 2 4 1
+addr 3 1 3
+seti 2 3 3
+addi 5 1 5
+gtrr 5 4 1
+addr 1 3 3
+seti 1 8 3
+mulr 3 3 3
+addi 4 {square} 4
+mulr 4 4 4
+mulr 3 4 4
+muli 4 {factor} 4
+addi 1 {multiple} 1
+mulr 1 3 1
+addi 1 {offset} 1
+addr 4 1 4
+addr 3 0 3
+seti 0 0 3
+setr 3 9 1
+mulr 1 3 1
+addr 3 1 1
+mulr 3 1 1
+muli 1 14 1
+mulr 1 3 1
+addr 4 1 4
+seti 0 4 0
+seti 0 0 3
+";
+
+impl Solution for December19 {
+    type Input = Program<i32>;
+    type Params = ();
+    type Part1 = i32;
+    type Part2 = i64;
+
+    fn parse(lines: &[&str]) -> Result<Self::Input> {
+        parse(lines)
+    }
+
+    fn part1(program: &Self::Input, _: &()) -> Result<i32> {
+        run(program)
+    }
 
-    println!("Part 1: {}", run(ip_reg, &instrs)?);
-    println!("Part 2: {}", factorize(decompiled(true)));
+    fn part2(program: &Self::Input, _: &()) -> Result<i64> {
+        optimised(program, 1)
+    }
 
-    Ok(())
+    /// The program of the puzzle with its registers other than 0 renamed at
+    /// random, and new constants, which make the number whose divisors it
+    /// sums up to about `20 * size`. Part 1 takes time quadratic in that.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut renaming = vec![1, 2, 3, 4, 5];
+        rng.shuffle(&mut renaming);
+        renaming.insert(0, 0);
+
+        let square = rng.range(1, 2);
+        let factor = rng.range(1, (size as i64 / 4).max(1));
+        let text = TEMPLATE
+            .replace("{square}", &square.to_string())
+            .replace("{factor}", &factor.to_string())
+            .replace("{multiple}", &rng.range(1, 9).to_string())
+            .replace("{offset}", &rng.range(1, 20).to_string());
+        let lines = text.lines().collect::<Vec<_>>();
+        let program = Program::<i32>::parse(&lines, REGISTERS)
+            .expect("the template parses");
+        program.renamed(&renaming).to_string()
+    }
 }
@@ -1,25 +1,26 @@
-#[macro_use]
-extern crate maplit;
+use generate::Rng;
+use parse::{self, Line};
+use solution::{Result, Solution};
 use std::collections::{HashSet, VecDeque};
-mod utils;
+
+pub struct December20;
 
 #[derive(Debug, PartialEq, Eq, Hash)]
-enum RRegex {
+pub enum RRegex {
     Concat(Vec<RRegex>),
     Options(Vec<RRegex>),
     Literal(char),
 }
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-type Position = (i32, i32);
+pub type Position = (i32, i32);
 
-fn parse(regex: &str) -> RRegex {
+pub fn parse(regex: &str) -> RRegex {
     fn parse_(mut regex: &str) -> (RRegex, &str) {
         let mut options: Vec<RRegex> = vec![];
         let mut concat: Vec<RRegex> = vec![];
 
         while !regex.is_empty() {
-            match regex.chars().nth(0).unwrap() {
+            match regex.chars().next().unwrap() {
                 '|' => {
                     options.push(RRegex::Concat(concat));
                     concat = vec![];
@@ -49,7 +50,37 @@ fn parse(regex: &str) -> RRegex {
     r
 }
 
-fn find_edges(regex: RRegex) -> HashSet<(Position, Position)> {
+/// Check that the line is a route regex like `^ENWWW(NEEE|SSE(EE|N))$`, so
+/// that it can be parsed by `parse`.
+pub fn check_regex<'a>(line: Line<'a>) -> Result<&'a str> {
+    let text = line.text;
+    if !text.starts_with('^') {
+        return Err(line.error("expected `^`"));
+    }
+    if !text.ends_with('$') || text.len() < 2 {
+        return Err(line.error_at(text.len(), "expected `$`"));
+    }
+
+    let mut depth = 0;
+    for (offset, c) in text.char_indices().skip(1).take(text.len() - 2) {
+        match c {
+            'N' | 'E' | 'S' | 'W' | '|' => {}
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ')' => return Err(line.error_at(offset, "unmatched `)`")),
+            _ => {
+                return Err(line.error_at(offset, format!("invalid direction `{}`", c)))
+            }
+        }
+    }
+    if depth > 0 {
+        return Err(line.error_at(text.len() - 1, "expected `)`"));
+    }
+
+    Ok(&text[1..text.len() - 1])
+}
+
+pub fn find_edges(regex: RRegex) -> HashSet<(Position, Position)> {
     let mut edges = HashSet::new();
 
     fn run_(
@@ -81,7 +112,7 @@ fn find_edges(regex: RRegex) -> HashSet<(Position, Position)> {
                         'E' => (x + 1, y),
                         'S' => (x, y + 1),
                         'W' => (x - 1, y),
-                        _ => panic!(format!("Unexpected literal {}", c)),
+                        _ => panic!("Unexpected literal {}", c),
                     };
 
                     positions_.insert((nx, ny));
@@ -99,12 +130,11 @@ fn find_edges(regex: RRegex) -> HashSet<(Position, Position)> {
     edges
 }
 
-fn longest_path(from: Position, edges: HashSet<(Position, Position)>) -> usize {
+pub fn longest_path(from: Position, edges: HashSet<(Position, Position)>) -> usize {
     let mut visited = hashmap! {from => 0};
 
     let mut queue = vec![(from, 0, hashset! {})];
-    while !queue.is_empty() {
-        let ((x, y), len, mut path) = queue.pop().unwrap();
+    while let Some(((x, y), len, mut path)) = queue.pop() {
         path.insert((x, y));
 
         for (dx, dy) in &[(0, -1), (1, 0), (0, 1), (-1, 0)] {
@@ -126,7 +156,7 @@ fn longest_path(from: Position, edges: HashSet<(Position, Position)>) -> usize {
     *visited.values().max().unwrap()
 }
 
-fn far_rooms(from: Position, edges: HashSet<(Position, Position)>) -> usize {
+pub fn far_rooms(from: Position, edges: HashSet<(Position, Position)>) -> usize {
     let mut rooms = hashset! {};
 
     // Find all rooms.
@@ -157,17 +187,97 @@ fn far_rooms(from: Position, edges: HashSet<(Position, Position)>) -> usize {
     rooms.len() - (visited.len() - 1)
 }
 
-fn main() -> Result<()> {
-    let re = utils::lines_from_file("input/december20.txt")?
-        .get(0)
-        .ok_or("Err")?
-        .clone();
-    let regex = parse(&re[1..re.len() - 1]);
-    let edges = find_edges(regex);
+impl Solution for December20 {
+    /// The doors between rooms, in both directions.
+    type Input = HashSet<(Position, Position)>;
+    type Params = ();
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(lines: &[&str]) -> Result<Self::Input> {
+        let re = parse::single(lines, "a route regex")?;
+        Ok(find_edges(parse(check_regex(re)?)))
+    }
+
+    fn part1(edges: &Self::Input, _: &()) -> Result<usize> {
+        Ok(longest_path((0, 0), edges.clone()))
+    }
+
+    fn part2(edges: &Self::Input, _: &()) -> Result<usize> {
+        Ok(far_rooms((0, 0), edges.clone()))
+    }
 
-    println!("Starting p1");
-    println!("Part 1: {}", longest_path((0, 0), edges.clone()));
-    println!("Part 2: {}", far_rooms((0, 0), edges));
+    /// A maze of `size` by `size` rooms, at least two by two, with one way
+    /// between every two rooms. Dead ends are often visited as a detour,
+    /// like `(NS|)`, as in the puzzle.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        /// The route through the rooms below `room` in the maze.
+        fn route(
+            room: usize,
+            doors: &[Vec<(char, usize)>],
+            rng: &mut Rng,
+        ) -> String {
+            let mut text = String::new();
+            let mut branches = Vec::new();
+            for &(direction, next) in &doors[room] {
+                if doors[next].is_empty() && rng.chance(0.5) {
+                    let back = match direction {
+                        'N' => 'S',
+                        'S' => 'N',
+                        'E' => 'W',
+                        _ => 'E',
+                    };
+                    text.push_str(&format!("({}{}|)", direction, back));
+                } else {
+                    branches.push(format!(
+                        "{}{}",
+                        direction,
+                        route(next, doors, rng)
+                    ));
+                }
+            }
+            match branches.len() {
+                0 => {}
+                1 => text.push_str(&branches[0]),
+                _ => text.push_str(&format!("({})", branches.join("|"))),
+            }
+            text
+        }
 
-    Ok(())
+        // A random spanning tree of the rooms, grown depth first from the
+        // middle.
+        let side = size.max(2);
+        let mut doors = vec![Vec::new(); side * side];
+        let mut visited = vec![false; side * side];
+        let start = side / 2 * side + side / 2;
+        visited[start] = true;
+        let mut path = vec![start];
+        while let Some(&room) = path.last() {
+            let (x, y) = (room % side, room / side);
+            let mut next = Vec::new();
+            if y > 0 {
+                next.push(('N', room - side));
+            }
+            if y + 1 < side {
+                next.push(('S', room + side));
+            }
+            if x + 1 < side {
+                next.push(('E', room + 1));
+            }
+            if x > 0 {
+                next.push(('W', room - 1));
+            }
+            next.retain(|&(_, next)| !visited[next]);
+            if next.is_empty() {
+                path.pop();
+            } else {
+                let (direction, next) = *rng.choose(&next);
+                visited[next] = true;
+                doors[room].push((direction, next));
+                path.push(next);
+            }
+        }
+
+        format!("^{}$", route(start, &doors, rng))
+    }
 }
@@ -1,125 +1,96 @@
-#[macro_use]
-extern crate maplit;
-use std::collections::HashMap;
-mod utils;
-
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-enum OpCode {
-    Addr,
-    Addi,
-    Mulr,
-    Muli,
-    Banr,
-    Bani,
-    Borr,
-    Bori,
-    Setr,
-    Seti,
-    Gtir,
-    Gtri,
-    Gtrr,
-    Eqir,
-    Eqri,
-    Eqrr,
-}
-use OpCode::*;
-
-type Instr = (OpCode, i64, i64, i64);
-
-fn execute(instr: Instr, reg_bank: &mut [i64]) -> &mut [i64] {
-    macro_rules! r {
-        ( $x:expr ) => {
-            reg_bank[$x as usize]
-        };
+use elfcode::{Instr, Machine, OpCode, Program, Word};
+use error::Error;
+use generate::Rng;
+use optimise::Compiled;
+use solution::{Result, Solution};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+
+pub struct December21;
+
+static REGISTERS: usize = 6;
+
+pub fn parse<W>(lines: &[&str]) -> Result<Program<W>>
+where
+    W: Word + FromStr,
+    W::Err: Display,
+{
+    let program = Program::parse(lines, REGISTERS)?;
+    if program.ip_register.is_none() {
+        return Err(Error::input(0, 0, "expected `#ip <register>`"));
     }
-
-    let (op_code, i1, i2, rr) = instr;
-    reg_bank[rr as usize] = match op_code {
-        Addr => r!(i1) + r!(i2),
-        Addi => r!(i1) + i2,
-        Mulr => r!(i1) * r!(i2),
-        Muli => r!(i1) * i2,
-        Banr => r!(i1) & r!(i2),
-        Bani => r!(i1) & i2,
-        Borr => r!(i1) | r!(i2),
-        Bori => r!(i1) | i2,
-        Setr => r!(i1),
-        Seti => i1,
-        Gtir => (i1 > r!(i2)).into(),
-        Gtri => (r!(i1) > i2).into(),
-        Gtrr => (r!(i1) > r!(i2)).into(),
-        Eqir => (i1 == r!(i2)).into(),
-        Eqri => (r!(i1) == i2).into(),
-        Eqrr => (r!(i1) == r!(i2)).into(),
-    };
-    reg_bank
+    Ok(program)
 }
 
-fn parse(lines: &[String]) -> Result<(usize, Vec<Instr>)> {
-    let ip = lines
-        .get(0)
-        .and_then(|s| s.split(" ").nth(1))
-        .ok_or("Malformed input")?
-        .parse::<usize>()?;
-    let mut instructions = Vec::new();
-    let str_to_op: HashMap<&str, OpCode> = hashmap! {
-        "addr" => Addr,
-        "addi" => Addi,
-        "mulr" => Mulr,
-        "muli" => Muli,
-        "banr" => Banr,
-        "bani" => Bani,
-        "borr" => Borr,
-        "bori" => Bori,
-        "setr" => Setr,
-        "seti" => Seti,
-        "gtir" => Gtir,
-        "gtri" => Gtri,
-        "gtrr" => Gtrr,
-        "eqir" => Eqir,
-        "eqri" => Eqri,
-        "eqrr" => Eqrr
-    };
-
-    for line in &lines[1..] {
-        let split = line.split(' ').collect::<Vec<_>>();
-        let args = split[1..]
-            .iter()
-            .map(|s| Ok(s.parse::<i64>()?))
-            .collect::<Result<Vec<i64>>>()?;
-        let instr: Instr = (
-            *str_to_op.get(split[0]).ok_or("Malformed input")?,
-            args[0],
-            args[1],
-            args[2],
-        );
-        instructions.push(instr);
-    }
-
-    Ok((ip, instructions))
+/// Run the program with the given value in register 0. With `stop`, it is
+/// stopped at instruction 28, where register 5 is compared with register 0.
+pub fn run(program: &Program<i64>, r0: i64, stop: bool) -> Result<i64> {
+    let mut machine = Machine::for_program(program, REGISTERS)?;
+    machine.registers[0] = r0;
+    machine.run_until(&program.instrs, |machine| stop && machine.ip == 28)?;
+    Ok(machine.registers[5])
 }
 
-fn run(ip_reg: usize, instrs: &[Instr], r0: i64, stop: bool) -> Result<i64> {
-    let mut ip = 0;
-    let mut reg_bank = vec![r0, 0, 0, 0, 0, 0];
+/// Run the program with the given value in register 0, until it halts or has
+/// compared register 5 with register 0 at instruction 28 `count` times, and
+/// return the values register 5 had there.
+pub fn compared(program: &Program<i64>, r0: i64, count: usize) -> Result<Vec<i64>> {
+    let mut machine = Machine::for_program(program, REGISTERS)?;
+    machine.registers[0] = r0;
+    let mut values = Vec::new();
+    machine.run_until(&program.instrs, |machine| {
+        if machine.ip == 28 {
+            values.push(machine.registers[5]);
+        }
+        values.len() > count
+    })?;
+    values.truncate(count);
+    Ok(values)
+}
 
-    while ip >= 0 && ip < instrs.len() as i64 {
-        reg_bank[ip_reg] = ip;
-        if ip == 28 && stop {
-            break;
+/// The last new value the program compares with register 0 at instruction 28
+/// before they start to repeat, found by running it on the optimising
+/// interpreter, which divides by 256 natively.
+pub fn last_compared(program: &Program<i64>) -> Result<i64> {
+    let compiled = Compiled::new(program, REGISTERS)?;
+    let mut machine = Machine::for_program(program, REGISTERS)?;
+    let mut seen = HashSet::new();
+    let mut last = None;
+    compiled.run_until(&mut machine, |machine| {
+        if machine.ip != 28 {
+            return false;
         }
+        let value = machine.registers[5];
+        if !seen.insert(value) {
+            return true;
+        }
+        last = Some(value);
+        false
+    })?;
+    last.ok_or_else(|| {
+        Error::unsolvable("expected the program to reach instruction 28")
+    })
+}
 
-        execute(instrs[ip as usize], &mut reg_bank);
-        ip = reg_bank[ip_reg] + 1;
+/// The constants of the hash the program computes: the number it starts from
+/// at instruction 7, and the one it multiplies by at instruction 11.
+pub fn constants(program: &Program<i64>) -> Result<(i64, i64)> {
+    match (program.instrs.get(7), program.instrs.get(11)) {
+        (
+            Some(&Instr { op: OpCode::Seti, a: seed, .. }),
+            Some(&Instr { op: OpCode::Muli, b: multiplier, .. }),
+        ) => Ok((seed, multiplier)),
+        _ => Err(Error::unsolvable(
+            "expected `seti` at instruction 7 and `muli` at instruction 11",
+        )),
     }
-
-    Ok(reg_bank[5])
 }
 
-fn simplified(r0: i64) -> i64 {
-    let mut r = vec![r0, 0, 0, 0, 0, 0];
+/// The values the program compares with register 0 at instruction 28, for the
+/// given hash constants, until it halts or they start to repeat.
+pub fn simplified((seed, multiplier): (i64, i64), r0: i64) -> Vec<i64> {
+    let mut r = [r0, 0, 0, 0, 0, 0];
 
     // 00 - 04
     while r[5] != 72 {
@@ -127,7 +98,7 @@ fn simplified(r0: i64) -> i64 {
         r[5] &= 456;
     }
 
-    let mut prev = 0;
+    let mut values = Vec::new();
 
     // 05
     r[5] = 0;
@@ -140,7 +111,7 @@ fn simplified(r0: i64) -> i64 {
         r[4] = r[5] | 0b10000000000000000;
 
         // 07
-        r[5] = 15466939; //0b111011000000000110111011;
+        r[5] = seed;
 
         // 08 - 27
         while r[4] > 0 {
@@ -154,7 +125,7 @@ fn simplified(r0: i64) -> i64 {
             r[5] &= 0b111111111111111111111111;
 
             // 11
-            r[5] *= 65899; //0b10000000101101011;
+            r[5] *= multiplier;
 
             // 12
             r[5] &= 0b111111111111111111111111;
@@ -163,12 +134,10 @@ fn simplified(r0: i64) -> i64 {
             r[4] /= 256;
         }
 
-        if set.contains(&r[5]) {
-            return prev;
-        } else {
-            prev = r[5];
-            set.insert(r[5]);
+        if !set.insert(r[5]) {
+            break;
         }
+        values.push(r[5]);
 
         // 28 - 30
         if r[5] == r[0] {
@@ -176,15 +145,75 @@ fn simplified(r0: i64) -> i64 {
         }
     }
 
-    return -1;
+    values
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december21.txt")?;
-    let (ip_reg, instrs) = parse(&lines)?;
+/// The program of the puzzle, with placeholders for the number that the
+/// hash starts from and the one it is multiplied by. Generated inputs fill
+/// them in.
+static TEMPLATE: &str = "\
+#ip 2
+seti 123 0 5
+bani 5 456 5
+eqri 5 72 5
+addr 5 2 2
+seti 0 0 2
+seti 0 4 5
+bori 5 65536 4
+seti {seed} 9 5
+bani 4 255 3
+addr 5 3 5
+bani 5 16777215 5
+muli 5 {multiplier} 5
+bani 5 16777215 5
+gtir 256 4 3
+addr 3 2 2
+addi 2 1 2
+seti 27 8 2
+seti 0 7 3
+addi 3 1 1
+muli 1 256 1
+gtrr 1 4 1
+addr 1 2 2
+addi 2 1 2
+seti 25 2 2
+addi 3 1 3
+seti 17 7 2
+setr 3 7 4
+seti 7 3 2
+eqrr 5 0 3
+addr 3 2 2
+seti 5 9 2
+";
+
+impl Solution for December21 {
+    type Input = Program<i64>;
+    type Params = ();
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(lines: &[&str]) -> Result<Self::Input> {
+        parse(lines)
+    }
 
-    println!("Part 1: {}", run(ip_reg, &instrs, 0, true)?);
-    println!("Part 2: {}", simplified(0));
+    fn part1(program: &Self::Input, _: &()) -> Result<i64> {
+        run(program, 0, true)
+    }
+
+    /// The last new value compared with register 0 takes the most
+    /// instructions to halt at.
+    fn part2(program: &Self::Input, _: &()) -> Result<i64> {
+        last_compared(program)
+    }
 
-    Ok(())
+    /// The program of the puzzle with a new 24-bit starting number and an odd
+    /// multiplier for its hash. `size` is ignored.
+    fn generate(rng: &mut Rng, _: usize) -> String {
+        TEMPLATE
+            .replace("{seed}", &rng.range(1, 0xff_ffff).to_string())
+            .replace(
+                "{multiplier}",
+                &(rng.range(1 << 15, 1 << 17) | 1).to_string(),
+            )
+    }
 }
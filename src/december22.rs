@@ -1,23 +1,24 @@
-#[macro_use]
-extern crate maplit;
+use generate::Rng;
+use parse;
+use solution::{self, Result, Solution};
 use std::collections::{HashSet, HashMap};
-mod utils;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-type Position = (u64, u64);
+pub struct December22;
+
+pub type Position = (u64, u64);
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
-enum Tool {
+pub enum Tool {
     Neither,
     Torch,
     Climbing
 }
 
-fn erosion_level(geologic_index: u64, depth: u64) -> u64 {
+pub fn erosion_level(geologic_index: u64, depth: u64) -> u64 {
     (geologic_index + depth) % 20183
 }
 
-fn geologic_index(
+pub fn geologic_index(
     (x, y): Position,
     target: Position,
     depth: u64,
@@ -29,9 +30,7 @@ fn geologic_index(
     }
 
     let idx = 
-        if (x, y) == (0, 0) {
-            0
-        } else if (x, y) == target {
+        if (x, y) == (0, 0) || (x, y) == target {
             0
         } else if y == 0 {
             (x * 16807) % 20183
@@ -48,7 +47,7 @@ fn geologic_index(
     idx
 }
 
-fn tile_type(
+pub fn tile_type(
     position: Position,
     target: Position,
     depth: u64,
@@ -58,7 +57,7 @@ fn tile_type(
     erosion_level(geologic_index(position, target, depth, geo_idx), depth) % 3
 }
 
-fn total_risk_level(depth: u64, (tx, ty): Position) -> u64 {
+pub fn total_risk_level(depth: u64, (tx, ty): Position) -> u64 {
     let mut geo_idx = hashmap!{};
     let mut risk = 0;
 
@@ -71,7 +70,7 @@ fn total_risk_level(depth: u64, (tx, ty): Position) -> u64 {
     risk
 }
 
-fn shortest_path(depth: u64, target: Position) -> u64 {
+pub fn shortest_path(depth: u64, target: Position) -> u64 {
     let (tx, ty) = target;
     let mut geo_idx = hashmap!{};
 
@@ -80,14 +79,16 @@ fn shortest_path(depth: u64, target: Position) -> u64 {
     let mut costs = hashmap! {((0, 0), Tool::Torch) => 0};
 
     while !open_set.is_empty() {
+        solution::checkpoint();
+
         // Find position with least cost so far.
         let mut pos = None;
-        let mut heur_cost = std::u64::MAX;
+        let mut heur_cost = u64::MAX;
         for &p in open_set.iter() {
             let ((nx, ny), t) = p;
 
-            let mut f = if nx < tx { tx - nx } else { nx - tx }
-                + if ny < ty { ty - ny } else { ny - ty };
+            let mut f = tx.abs_diff(nx)
+                + ty.abs_diff(ny);
             if t != Tool::Torch {
                 f += 7;
             }
@@ -134,11 +135,10 @@ fn shortest_path(depth: u64, target: Position) -> u64 {
 
             let tile = tile_type((nx, ny), target, depth, &mut geo_idx);
 
-            if tile == 0 && ntool == Tool::Neither {
-                continue;
-            } else if (nx, ny) != target && tile == 1 && ntool == Tool::Torch {
-                continue;
-            } else if tile == 2 && ntool == Tool::Climbing {
+            if tile == 0 && ntool == Tool::Neither
+                || (nx, ny) != target && tile == 1 && ntool == Tool::Torch
+                || tile == 2 && ntool == Tool::Climbing
+            {
                 continue;
             }
 
@@ -160,15 +160,41 @@ fn shortest_path(depth: u64, target: Position) -> u64 {
         }
     }
 
-    std::u64::MAX
+    u64::MAX
 }
 
-fn main() -> Result<()> {
-    let (depth, target): (u64, Position) = (11991, (6,797));
-    //let (depth, target): (u64, Position) = (510, (10,10));
+impl Solution for December22 {
+    /// The cave depth and target coordinates.
+    type Input = (u64, Position);
+    type Params = ();
+    type Part1 = u64;
+    type Part2 = u64;
+
+    fn parse(lines: &[&str]) -> Result<(u64, Position)> {
+        let depth = parse::line(lines, 0).after("depth: ")?.parse()?;
+        let [x, y] = parse::line(lines, 1)
+            .after("target: ")?
+            .unsigned_n("`<x>,<y>`")?;
 
-    println!("Part 1: {}", total_risk_level(depth, target));
-    println!("Part 2: {}", shortest_path(depth, target));
+        Ok((depth, (x, y)))
+    }
+
+    fn part1(&(depth, target): &(u64, Position), _: &()) -> Result<u64> {
+        Ok(total_risk_level(depth, target))
+    }
 
-    Ok(())
+    fn part2(&(depth, target): &(u64, Position), _: &()) -> Result<u64> {
+        Ok(shortest_path(depth, target))
+    }
+
+
+    /// A cave with a random depth and its target about `size / 4` to the
+    /// right and `15 * size` down, like the puzzle's.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let size = size.max(1) as i64;
+        let depth = rng.range(1000, 20_000);
+        let x = rng.range(1, (size / 2).max(1));
+        let y = rng.range(10 * size, 20 * size);
+        format!("depth: {}\ntarget: {},{}\n", depth, x, y)
+    }
 }
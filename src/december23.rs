@@ -1,33 +1,30 @@
-extern crate euclid;
-extern crate regex;
 use euclid::Point3D;
-mod utils;
+use error::Error;
+use generate::Rng;
+use parse;
+use solution::{Result, Solution};
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
-type Constellation = Vec<(Point3D<i64>, i64)>;
+pub struct December23;
 
-fn parse(lines: &[String]) -> Result<Constellation> {
-    let re = regex::Regex::new(
-        r"^pos=<\s*(-?[0-9]+),\s*(-?[0-9]+),\s*(-?[0-9]+)>, r=([0-9]+)$",
-    )?;
-    lines
-        .iter()
-        .map(|s| {
-            let captures = re
-                .captures(s)
-                .ok_or_else(|| format!("String {} does not parse", s))?;
+pub type Constellation = Vec<(Point3D<i64>, i64)>;
+
+pub fn parse(lines: &[&str]) -> Result<Constellation> {
+    if lines.is_empty() {
+        return Err(Error::input(0, 0, "expected at least one nanobot"));
+    }
 
-            let x1 = captures[1].parse::<i64>()?;
-            let x2 = captures[2].parse::<i64>()?;
-            let x3 = captures[3].parse::<i64>()?;
-            let r = captures[4].parse::<i64>()?;
+    parse::lines(lines)
+        .iter()
+        .map(|line| {
+            let [x1, x2, x3] = line.vector("pos")?;
+            let r = line.value("r")?;
 
             Ok((Point3D::new(x1, x2, x3), r))
         })
         .collect()
 }
 
-fn in_range_largest(constellation: &Constellation) -> usize {
+pub fn in_range_largest(constellation: &Constellation) -> usize {
     let (pos, strength) = constellation.iter().max_by_key(|(_, s)| s).unwrap();
 
     constellation
@@ -39,7 +36,7 @@ fn in_range_largest(constellation: &Constellation) -> usize {
         .count()
 }
 
-fn count_within_cube(
+pub fn count_within_cube(
     constellation: &Constellation,
     origin: Point3D<i64>,
     size: i64,
@@ -59,7 +56,7 @@ fn count_within_cube(
     count
 }
 
-fn closest_in_best_range(constellation: &Constellation) -> i64 {
+pub fn closest_in_best_range(constellation: &Constellation) -> i64 {
     let mut min_x = constellation.iter().map(|(p, _)| p.x).min().unwrap();
     let mut max_x = constellation.iter().map(|(p, _)| p.x).max().unwrap();
     let mut min_y = constellation.iter().map(|(p, _)| p.y).min().unwrap();
@@ -75,7 +72,7 @@ fn closest_in_best_range(constellation: &Constellation) -> i64 {
 
     let mut size = 1i64;
     while size < size_ {
-        size = size * 2;
+        size *= 2;
     }
 
     loop {
@@ -89,7 +86,7 @@ fn closest_in_best_range(constellation: &Constellation) -> i64 {
                     let count = count_within_cube(constellation, origin, size);
                     if count > best
                         || count == best
-                            && (candidate == None
+                            && (candidate.is_none()
                                 || origin.x.abs()
                                     + origin.y.abs()
                                     + origin.z.abs()
@@ -121,12 +118,38 @@ fn closest_in_best_range(constellation: &Constellation) -> i64 {
     }
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december23.txt")?;
-    let constellation = parse(&lines)?;
+impl Solution for December23 {
+    type Input = Constellation;
+    type Params = ();
+    type Part1 = usize;
+    type Part2 = i64;
+
+    fn parse(lines: &[&str]) -> Result<Constellation> {
+        parse(lines)
+    }
+
+    fn part1(constellation: &Constellation, _: &()) -> Result<usize> {
+        Ok(in_range_largest(constellation))
+    }
+
+    fn part2(constellation: &Constellation, _: &()) -> Result<i64> {
+        Ok(closest_in_best_range(constellation))
+    }
 
-    println!("Part 1: {}", in_range_largest(&constellation));
-    println!("Part 2: {:?}", closest_in_best_range(&constellation));
 
-    Ok(())
+    /// `20 * size` nanobots spread over a few hundred million units, with
+    /// ranges of a fifth of that, like the puzzle's.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut text = String::new();
+        for _ in 0..(20 * size).max(1) {
+            text.push_str(&format!(
+                "pos=<{},{},{}>, r={}\n",
+                rng.range(-200_000_000, 200_000_000),
+                rng.range(-200_000_000, 200_000_000),
+                rng.range(-200_000_000, 200_000_000),
+                rng.range(50_000_000, 100_000_000),
+            ));
+        }
+        text
+    }
 }
@@ -1,19 +1,22 @@
-extern crate regex;
+use generate::Rng;
+use parse::{self, Line};
+use record;
+use render::{Image, Palette, Rgb};
+use solution::{self, Result, Solution};
 use std::collections::{HashMap, HashSet};
-mod utils;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December24;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
-struct Group {
-    id: usize,
-    units: i64,
-    hitpoints: i64,
-    weaknesses: HashSet<String>,
-    immunities: HashSet<String>,
-    attack_power: i64,
-    attack_type: String,
-    initiative: i64
+pub struct Group {
+    pub id: usize,
+    pub units: i64,
+    pub hitpoints: i64,
+    pub weaknesses: HashSet<String>,
+    pub immunities: HashSet<String>,
+    pub attack_power: i64,
+    pub attack_type: String,
+    pub initiative: i64
 }
 
 impl std::hash::Hash for Group {
@@ -22,90 +25,83 @@ impl std::hash::Hash for Group {
     }
 }
 
-fn parse(lines: &[String]) -> Result<(HashMap<usize, Group>, HashMap<usize, Group>)> {
-    let re = regex::Regex::new(
-        r"^(?P<units>[0-9]+) units each with (?P<hp>[0-9]+) hit points (?:\((?P<defense>.+)\) )?with an attack that does (?P<ap>[0-9]+) (?P<atype>[a-z]+) damage at initiative (?P<initiative>[0-9]+)$",
-    )?;
+/// Parse damage types like `cold, fire`.
+fn parse_types(types: Line) -> HashSet<String> {
+    types.split(", ").iter().map(|t| t.text.to_owned()).collect()
+}
 
+/// Parse a group like `17 units each with 5390 hit points (weak to
+/// radiation, bludgeoning) with an attack that does 4507 fire damage at
+/// initiative 2`, with the given id.
+fn parse_group(line: Line, id: usize) -> Result<Group> {
+    let (units, rest) = line.split_once(" units each with ")?;
+    let (hp, rest) = rest.split_once(" hit points ")?;
+
+    let mut weaknesses = HashSet::new();
+    let mut immunities = HashSet::new();
+    let rest = match rest.after("(") {
+        Ok(defenses) => {
+            let (defenses, rest) = defenses.split_once(") ")?;
+            for defense in defenses.split("; ") {
+                if let Ok(types) = defense.after("weak to ") {
+                    weaknesses = parse_types(types);
+                } else if let Ok(types) = defense.after("immune to ") {
+                    immunities = parse_types(types);
+                } else {
+                    return Err(defense.error("expected `weak to` or `immune to`"));
+                }
+            }
+            rest
+        }
+        Err(_) => rest,
+    };
+
+    let (ap, rest) = rest.after("with an attack that does ")?.split_once(" ")?;
+    let (attack_type, initiative) = rest.split_once(" damage at initiative ")?;
+
+    Ok(Group {
+        id,
+        units: units.parse()?,
+        hitpoints: hp.parse()?,
+        weaknesses,
+        immunities,
+        attack_power: ap.parse()?,
+        attack_type: attack_type.text.to_owned(),
+        initiative: initiative.parse()?
+    })
+}
+
+pub fn parse(lines: &[&str]) -> Result<(HashMap<usize, Group>, HashMap<usize, Group>)> {
     let mut immune_army = HashMap::new();
     let mut infection_army = HashMap::new();
 
     let mut n = 0;
-    let mut in_immune = true;
-    for line in lines {
-        if line == "Immune System:" || line == "" {
-            continue;
-        } else if line == "Infection:" {
-            in_immune = false;
-            continue;
-        }
-
-        let captures = re
-            .captures(line)
-            .ok_or_else(|| format!("String `{}` does not parse", line))?;
-
-        let units = captures.name("units")
-            .ok_or("Parse err")?
-            .as_str()
-            .parse::<i64>()?;
-        let hp = captures.name("hp")
-            .ok_or("Parse err")?
-            .as_str()
-            .parse::<i64>()?;
-        let ap = captures.name("ap")
-            .ok_or("Parse err")?
-            .as_str()
-            .parse::<i64>()?;
-        let initiative = captures.name("initiative")
-            .ok_or("Parse err")?
-            .as_str()
-            .parse::<i64>()?;
-
-        let mut weaknesses = HashSet::new();
-        let mut immunities = HashSet::new();
-
-        if let Some(defense) = captures.name("defense") {
-            for defense in defense.as_str().split("; ") {
-                if &defense[..7] == "weak to" {
-                    weaknesses = defense[8..].split(", ")
-                        .map(|s| s.to_owned())
-                        .collect()
-                } else if &defense[..9] == "immune to" {
-                    immunities = defense[10..].split(", ")
-                        .map(|s| s.to_owned())
-                        .collect()
-                }
+    for section in parse::sections(lines, 1) {
+        let header = section.line(0);
+        let army = match header.text {
+            "Immune System:" => &mut immune_army,
+            "Infection:" => &mut infection_army,
+            _ => {
+                return Err(
+                    header.error("expected `Immune System:` or `Infection:`")
+                )
             }
-        }
-
-        let group = Group {
-            id: n,
-            units: units,
-            hitpoints: hp,
-            weaknesses: weaknesses,
-            immunities: immunities,
-            attack_power: ap,
-            attack_type: captures.name("atype").ok_or("Parse err")?.as_str().to_string(),
-            initiative: initiative
         };
 
-        if in_immune {
-            immune_army.insert(n, group);
-        } else {
-            infection_army.insert(n, group);
+        for line in section.lines().into_iter().skip(1) {
+            army.insert(n, parse_group(line, n)?);
+            n += 1;
         }
-
-        n += 1;
     }
 
     Ok((immune_army, infection_army))
 }
 
-fn effective_power(group: &Group) -> i64 {
+pub fn effective_power(group: &Group) -> i64 {
     group.units * group.attack_power
 }
 
-fn damage(attacker: &Group, defender: &Group) -> i64 {
+pub fn damage(attacker: &Group, defender: &Group) -> i64 {
     effective_power(attacker) * 
     if defender.weaknesses.contains(&attacker.attack_type) {
         2
@@ -116,12 +112,65 @@ fn damage(attacker: &Group, defender: &Group) -> i64 {
     }
 }
 
+static PALETTE: Palette = Palette(&[
+    (' ', Rgb(0x0f, 0x0f, 0x23)),
+    ('I', Rgb(0x00, 0xcc, 0x00)),
+    ('X', Rgb(0xcc, 0x00, 0x00)),
+]);
+
+/// How `ascii_art` draws the groups: the ids of the groups of both armies at
+/// the start of the battle, and how many units a character stands for, so
+/// that the largest group takes at most 100 characters.
+pub struct Layout {
+    immune_ids: Vec<usize>,
+    infection_ids: Vec<usize>,
+    units_per_char: i64,
+}
+
+impl Layout {
+    pub fn new(immune_army: &HashMap<usize, Group>, infection_army: &HashMap<usize, Group>) -> Layout {
+        let ids = |army: &HashMap<usize, Group>| {
+            let mut ids = army.keys().cloned().collect::<Vec<_>>();
+            ids.sort_unstable();
+            ids
+        };
+        let max = immune_army.values().chain(infection_army.values()).map(|g| g.units).max();
+
+        Layout {
+            immune_ids: ids(immune_army),
+            infection_ids: ids(infection_army),
+            units_per_char: ((max.unwrap_or(0) + 99) / 100).max(1),
+        }
+    }
+}
+
+/// The groups as bars of their units, `I` for the immune system and `X` for
+/// the infection.
+pub fn ascii_art(immune_army: &HashMap<usize, Group>, infection_army: &HashMap<usize, Group>, layout: &Layout) -> String {
+    let mut s = String::new();
+    for (army, ids, c) in &[
+        (immune_army, &layout.immune_ids, 'I'),
+        (infection_army, &layout.infection_ids, 'X'),
+    ] {
+        for id in ids.iter() {
+            let units = army.get(id).map(|g| g.units).unwrap_or(0);
+            let len = (units + layout.units_per_char - 1) / layout.units_per_char;
+            s.extend(std::iter::repeat_n(*c, len as usize));
+            s.push('\n');
+        }
+        s.push('\n');
+    }
+    s
+}
+
 /// Returns a tuple of a boolean and number of units alive.
 /// Boolean true indicates the immune army has won, false the infection.
-fn battle(mut immune_army: HashMap<usize, Group>, mut infection_army: HashMap<usize, Group>) -> (bool, i64) {
+pub fn battle(mut immune_army: HashMap<usize, Group>, mut infection_army: HashMap<usize, Group>) -> (bool, i64) {
     let mut prev_all_groups = Vec::new();
+    let layout = Layout::new(&immune_army, &infection_army);
 
     loop {
+        solution::checkpoint();
         let mut immune_groups = immune_army.values().cloned().collect::<Vec<Group>>();
         let mut infection_groups = infection_army.values().cloned().collect::<Vec<Group>>();
 
@@ -134,11 +183,11 @@ fn battle(mut immune_army: HashMap<usize, Group>, mut infection_army: HashMap<us
 
         for attacking_group in immune_groups.clone().iter().rev() {
             infection_groups.sort_unstable_by_key(|g| {
-                (damage(&attacking_group, g), effective_power(g), g.initiative)
+                (damage(attacking_group, g), effective_power(g), g.initiative)
             });
 
             for defending_group in infection_groups.clone().iter().rev() {
-                if damage(&attacking_group, &defending_group) == 0 {
+                if damage(attacking_group, defending_group) == 0 {
                     continue;
                 }
 
@@ -155,11 +204,11 @@ fn battle(mut immune_army: HashMap<usize, Group>, mut infection_army: HashMap<us
 
         for attacking_group in infection_groups.clone().iter().rev() {
             immune_groups.sort_unstable_by_key(|g| {
-                (damage(&attacking_group, g), effective_power(g), g.initiative)
+                (damage(attacking_group, g), effective_power(g), g.initiative)
             });
 
             for defending_group in immune_groups.clone().iter().rev() {
-                if damage(&attacking_group, &defending_group) == 0 {
+                if damage(attacking_group, defending_group) == 0 {
                     continue;
                 }
 
@@ -210,22 +259,27 @@ fn battle(mut immune_army: HashMap<usize, Group>, mut infection_army: HashMap<us
             }
         }
 
-        if infection_army.len() == 0 {
+        record::frame(|| {
+            let text = ascii_art(&immune_army, &infection_army, &layout);
+            Image::from_text(&text, &PALETTE)
+        });
+
+        if infection_army.is_empty() {
             break (true, immune_army.values().map(|g| g.units).sum())
-        } else if immune_army.len() == 0 {
+        } else if immune_army.is_empty() {
             break (false, infection_army.values().map(|g| g.units).sum())
         }
     }
 }
 
-fn find_boost(immune_army: HashMap<usize, Group>, infection_army: HashMap<usize, Group>) -> i64 {   
+pub fn find_boost(immune_army: HashMap<usize, Group>, infection_army: HashMap<usize, Group>) -> i64 {   
     let mut boost = 0;
 
     loop {
         let ia = immune_army.iter().map(|(key, g)| {
             let mut g = g.clone();
             g.attack_power += boost;
-            return (*key, g);
+            (*key, g)
         }).collect::<HashMap<usize,Group>>();
 
         let (won, alive) = battle(ia, infection_army.clone());
@@ -238,12 +292,91 @@ fn find_boost(immune_army: HashMap<usize, Group>, infection_army: HashMap<usize,
     }
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december24.txt")?;
-    let (immune_army, infection_army) = parse(&lines)?;
+impl Solution for December24 {
+    /// The immune system and infection armies.
+    type Input = (HashMap<usize, Group>, HashMap<usize, Group>);
+    type Params = ();
+    type Part1 = i64;
+    type Part2 = i64;
 
-    println!("Part 1: {:?}", battle(immune_army.clone(), infection_army.clone()));
-    println!("Part 2: {:?}", find_boost(immune_army, infection_army));
+    fn parse(lines: &[&str]) -> Result<Self::Input> {
+        parse(lines)
+    }
+
+    fn part1((immune_army, infection_army): &Self::Input, _: &()) -> Result<i64> {
+        let (_, alive) = battle(immune_army.clone(), infection_army.clone());
+        Ok(alive)
+    }
+
+    fn part2((immune_army, infection_army): &Self::Input, _: &()) -> Result<i64> {
+        Ok(find_boost(immune_army.clone(), infection_army.clone()))
+    }
 
-    Ok(())
+
+    /// Two armies of `size / 5` groups each, like the puzzle's. The immune
+    /// system wins with a boost of at most 100, so that part 2 ends.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        const TYPES: [&str; 5] =
+            ["bludgeoning", "cold", "fire", "radiation", "slashing"];
+        let count = (size / 5).max(1);
+
+        loop {
+            let mut initiatives = (1..=2 * count).collect::<Vec<_>>();
+            rng.shuffle(&mut initiatives);
+
+            let mut text = String::new();
+            for (army, initiatives) in initiatives.chunks(count).enumerate() {
+                text.push_str(if army == 0 {
+                    "Immune System:\n"
+                } else {
+                    "\nInfection:\n"
+                });
+                for &initiative in initiatives {
+                    let mut types = TYPES.to_vec();
+                    rng.shuffle(&mut types);
+                    let weak = rng.index(3);
+                    let immune = rng.index(3);
+                    let mut defenses = Vec::new();
+                    if weak > 0 {
+                        defenses.push(format!(
+                            "weak to {}",
+                            types[..weak].join(", ")
+                        ));
+                    }
+                    if immune > 0 {
+                        defenses.push(format!(
+                            "immune to {}",
+                            types[2..2 + immune].join(", ")
+                        ));
+                    }
+                    rng.shuffle(&mut defenses);
+                    let units = rng.range(100, 9000);
+                    text.push_str(&format!(
+                        "{} units each with {} hit points {}with an attack that does {} {} damage at initiative {}\n",
+                        units,
+                        rng.range(1000, 60_000),
+                        if defenses.is_empty() { String::new() } else { format!("({}) ", defenses.join("; ")) },
+                        rng.range(10_000, 100_000) / units,
+                        rng.choose(&TYPES),
+                        initiative,
+                    ));
+                }
+            }
+
+            // Without a big enough boost, the search for it would never end.
+            let lines = text.lines().collect::<Vec<_>>();
+            let (immune_army, infection_army) =
+                parse(&lines).expect("the input parses");
+            let boosted = immune_army
+                .into_iter()
+                .map(|(id, mut group)| {
+                    group.attack_power += 100;
+                    (id, group)
+                })
+                .collect();
+            if battle(boosted, infection_army).0 {
+                return text;
+            }
+        }
+    }
 }
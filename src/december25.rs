@@ -1,18 +1,20 @@
+use generate::Rng;
+use parse;
+use solution::{Result, Solution};
 use std::collections::HashSet;
-mod utils;
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+pub struct December25;
 
 #[derive(Debug, Eq, PartialEq)]
-struct Point4D<T> {
-    x: T,
-    y: T,
-    z: T,
-    w: T
+pub struct Point4D<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+    pub w: T
 }
 
 impl <T> Point4D<T> {
-    fn new(x: T, y: T, z: T, w: T) -> Point4D<T> {
+    pub fn new(x: T, y: T, z: T, w: T) -> Point4D<T> {
         Point4D {
             x, y, z, w
         }
@@ -20,13 +22,13 @@ impl <T> Point4D<T> {
 }
 
 impl Point4D<i64> {
-    fn distance(&self, other: &Point4D<i64>) -> i64 {
+    pub fn distance(&self, other: &Point4D<i64>) -> i64 {
         (self.x - other.x).abs() + (self.y - other.y).abs()
         + (self.z - other.z).abs()  + (self.w - other.w).abs()
     }
 }
 
-fn min_distance(ps1: &[&Point4D<i64>], ps2: &[&Point4D<i64>]) -> Option<i64> {
+pub fn min_distance(ps1: &[&Point4D<i64>], ps2: &[&Point4D<i64>]) -> Option<i64> {
     if ps2.is_empty() {
         return None
     }
@@ -41,23 +43,18 @@ fn min_distance(ps1: &[&Point4D<i64>], ps2: &[&Point4D<i64>]) -> Option<i64> {
 }
 
 
-fn parse_points(lines: &[String]) -> Result<Vec<Point4D<i64>>> {
-    lines.iter()
-        .map(|s| {
-            let parts: Vec<&str> = s.split(",").collect();
-
-            Ok(Point4D::new(
-                parts[0].trim().parse()?,
-                parts[1].trim().parse()?,
-                parts[2].trim().parse()?,
-                parts[3].trim().parse()?
-            ))
+pub fn parse_points(lines: &[&str]) -> Result<Vec<Point4D<i64>>> {
+    parse::lines(lines)
+        .iter()
+        .map(|line| {
+            let [x, y, z, w] = line.signed_n("four coordinates")?;
+            Ok(Point4D::new(x, y, z, w))
         })
         .collect()
 }
 
-fn num_constellations(points: &[Point4D<i64>]) -> usize {
-    let mut constellations = points.clone().iter().map(|p| vec![p]).collect::<Vec<_>>();
+pub fn num_constellations(points: &[Point4D<i64>]) -> usize {
+    let mut constellations = points.iter().map(|p| vec![p]).collect::<Vec<_>>();
 
     let mut mutated = true;
     while mutated {
@@ -69,12 +66,11 @@ fn num_constellations(points: &[Point4D<i64>]) -> usize {
                 let mut constellation = constellation.clone();
 
                 for (idx_, constellation_) in constellations.iter().enumerate() {
-                    if idx_ != idx && !merged.contains(&idx_) {
-                        if min_distance(&constellation, constellation_) <= Some(3) {
+                    if idx_ != idx && !merged.contains(&idx_)
+                        && min_distance(&constellation, constellation_) <= Some(3) {
                             constellation.append(&mut constellation_.clone());
                             merged.insert(idx_);
                         }
-                    }
                 }
 
                 new_constellations.push(constellation);
@@ -88,13 +84,35 @@ fn num_constellations(points: &[Point4D<i64>]) -> usize {
     constellations.len()
 }
 
-fn main() -> Result<()> {
-    let lines = utils::lines_from_file("input/december25.txt")?;
-    let points = parse_points(&lines)?;
+impl Solution for December25 {
+    type Input = Vec<Point4D<i64>>;
+    type Params = ();
+    type Part1 = usize;
+    type Part2 = &'static str;
 
-    println!("Part 1: {:#?}", num_constellations(&points));
-    println!("Part 2: {:#?}", "Thanks Rudolph! ❤");
-    
-    Ok(())
-}
+    fn parse(lines: &[&str]) -> Result<Vec<Point4D<i64>>> {
+        parse_points(lines)
+    }
 
+    fn part1(points: &Vec<Point4D<i64>>, _: &()) -> Result<usize> {
+        Ok(num_constellations(points))
+    }
+
+    fn part2(_: &Vec<Point4D<i64>>, _: &()) -> Result<&'static str> {
+        Ok("Thanks Rudolph! ❤")
+    }
+
+
+    /// `25 * size` points with coordinates from -8 to 8, like the puzzle's.
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        let mut text = String::new();
+        for _ in 0..(25 * size).max(1) {
+            let point = (0..4)
+                .map(|_| rng.range(-8, 8).to_string())
+                .collect::<Vec<_>>();
+            text.push_str(&point.join(","));
+            text.push('\n');
+        }
+        text
+    }
+}
@@ -0,0 +1,1112 @@
+//! Decompiling ElfCode programs into Rust functions with `if`s, `loop`s and
+//! `match`es in place of the jumps. The control flow is recovered from the
+//! jumps that `disasm` finds: loops are the natural loops of the control-flow
+//! graph, and the branches of an `if` meet again at the nearest instruction
+//! every way out of the program goes through.
+//!
+//! Programs that jump to computed instructions, or whose loops cannot be
+//! told apart, are decompiled into a `match` on the instruction pointer in a
+//! loop instead, which is as unreadable as the program but still compiles.
+use disasm::{self, Jump};
+use elfcode::{OpCode, Operand, Program, Word};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A statement of a decompiled program. Instructions are referred to by
+/// their index.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Stmt<W> {
+    /// An instruction that does not jump.
+    Instr(usize),
+    /// The jump at `idx`, which adds the register to the instruction pointer
+    /// after a comparison set it: `then` runs if it is 1.
+    If {
+        idx: usize,
+        register: usize,
+        then: Vec<Stmt<W>>,
+        otherwise: Vec<Stmt<W>>,
+    },
+    /// The jump at `idx`, which adds the register to the instruction pointer:
+    /// the arm with its value runs, and any other value halts.
+    Match {
+        idx: usize,
+        register: usize,
+        arms: Vec<(Vec<W>, Vec<Stmt<W>>)>,
+    },
+    /// A loop starting at the instruction `header`.
+    Loop { header: usize, body: Vec<Stmt<W>> },
+    /// Leave the loop starting at the instruction.
+    Break(usize),
+    /// Go back to the start of the loop starting at the instruction.
+    Continue(usize),
+    /// Halt with the instruction pointer's register at `ip`, plus another
+    /// register if there is one.
+    Halt { ip: W, plus: Option<usize> },
+}
+
+/// For every node of a graph, whether each node dominates it, or `None` if
+/// the node cannot be reached from the root.
+fn dominators(succs: &[Vec<usize>], root: usize) -> Vec<Option<Vec<bool>>> {
+    let n = succs.len();
+    let mut preds = vec![Vec::new(); n];
+    for (from, tos) in succs.iter().enumerate() {
+        for &to in tos {
+            preds[to].push(from);
+        }
+    }
+
+    let mut reachable = vec![false; n];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if !reachable[node] {
+            reachable[node] = true;
+            stack.extend(&succs[node]);
+        }
+    }
+
+    let mut doms = vec![vec![true; n]; n];
+    doms[root] = (0..n).map(|node| node == root).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for node in (0..n).filter(|&node| node != root && reachable[node]) {
+            let mut new = vec![true; n];
+            for &pred in preds[node].iter().filter(|&&pred| reachable[pred]) {
+                for (dom, &pred_dom) in new.iter_mut().zip(&doms[pred]) {
+                    *dom &= pred_dom;
+                }
+            }
+            new[node] = true;
+            if new != doms[node] {
+                doms[node] = new;
+                changed = true;
+            }
+        }
+    }
+
+    doms.into_iter()
+        .zip(reachable)
+        .map(|(doms, reachable)| if reachable { Some(doms) } else { None })
+        .collect()
+}
+
+struct Decompiler<'a, W: 'a> {
+    program: &'a Program<W>,
+    /// The number of instructions, which is also the node for halting.
+    len: usize,
+    /// Where every instruction goes, if it is a jump.
+    jumps: Vec<Option<Jump>>,
+    /// The instructions every instruction may go to next.
+    succs: Vec<Vec<usize>>,
+    /// For every instruction, whether each one post-dominates it, or `None`
+    /// if it cannot halt.
+    post_doms: Vec<Option<Vec<bool>>>,
+    /// The instruction every natural loop is left for, by the instruction it
+    /// starts at.
+    loops: BTreeMap<usize, Option<usize>>,
+    /// How many more instructions may be decompiled, so that control flow
+    /// that cannot be structured gives up rather than going on forever.
+    budget: usize,
+}
+
+impl<'a, W: Word> Decompiler<'a, W> {
+    /// Build the control-flow graph, or `None` if a jump goes to a computed
+    /// instruction.
+    fn new(program: &'a Program<W>) -> Option<Decompiler<'a, W>> {
+        let len = program.instrs.len();
+        let non_negative = program.instrs.iter().all(|instr| {
+            let (a, b) = instr.op.operands();
+            (a != Operand::Immediate || instr.a >= W::default())
+                && (b != Operand::Immediate || instr.b >= W::default())
+        });
+
+        let jumps = disasm::jumps(program);
+        let mut succs = Vec::new();
+        for (idx, &jump) in jumps.iter().enumerate() {
+            succs.push(match jump {
+                None => vec![idx + 1],
+                Some(Jump::To(target)) => vec![target],
+                Some(Jump::If { target, .. }) => vec![idx + 1, target],
+                // Registers only go negative with negative operands.
+                Some(Jump::Offset { base, .. }) if non_negative => {
+                    (base..=len).collect()
+                }
+                Some(Jump::Offset { .. }) | Some(Jump::Computed) => {
+                    return None
+                }
+            });
+        }
+        succs.push(Vec::new());
+
+        let mut preds = vec![Vec::new(); len + 1];
+        for (from, tos) in succs.iter().enumerate() {
+            for &to in tos {
+                preds[to].push(from);
+            }
+        }
+        let doms = dominators(&succs, 0);
+        let post_doms = dominators(&preds, len);
+
+        let mut decompiler = Decompiler {
+            program,
+            len,
+            jumps,
+            succs,
+            post_doms,
+            loops: BTreeMap::new(),
+            budget: 32 * len + 256,
+        };
+
+        // A jump back to an instruction that dominates it closes a loop,
+        // made of the instructions that lead to the jump from there.
+        let mut bodies = BTreeMap::new();
+        for (from, from_doms) in doms.iter().enumerate() {
+            let from_doms = match from_doms {
+                Some(from_doms) => from_doms,
+                None => continue,
+            };
+            for &header in &decompiler.succs[from] {
+                if !from_doms[header] {
+                    continue;
+                }
+                let body = bodies
+                    .entry(header)
+                    .or_insert_with(|| vec![false; len + 1]);
+                body[header] = true;
+                let mut stack = vec![from];
+                while let Some(node) = stack.pop() {
+                    if !body[node] && doms[node].is_some() {
+                        body[node] = true;
+                        stack.extend(&preds[node]);
+                    }
+                }
+            }
+        }
+        for (header, body) in bodies {
+            let exits = (0..len)
+                .filter(|&node| body[node])
+                .flat_map(|node| decompiler.succs[node].iter().cloned())
+                .filter(|&exit| exit < len && !body[exit])
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            let follow =
+                decompiler.join(&exits).or_else(|| exits.first().cloned());
+            decompiler.loops.insert(header, follow);
+        }
+
+        Some(decompiler)
+    }
+
+    /// The nearest instruction that every way from the given ones to halting
+    /// goes through, if there is one.
+    fn join(&self, nodes: &[usize]) -> Option<usize> {
+        let mut common = vec![true; self.len + 1];
+        for &node in nodes.iter().filter(|&&node| node < self.len) {
+            let post_doms = self.post_doms[node].as_ref()?;
+            for (common, &post_dom) in common.iter_mut().zip(post_doms) {
+                *common &= post_dom;
+            }
+        }
+        if nodes.iter().all(|&node| node >= self.len) {
+            return None;
+        }
+
+        // The nearest one is post-dominated by all the others.
+        (0..self.len)
+            .filter(|&node| common[node])
+            .max_by_key(|&node| {
+                let post_doms =
+                    self.post_doms[node].as_ref().expect("it halts");
+                post_doms.iter().filter(|&&post_dom| post_dom).count()
+            })
+    }
+
+    /// Halt after the instruction at `idx` wrote `ip` to the instruction
+    /// pointer.
+    fn halt(ip: usize, plus: Option<usize>) -> Vec<Stmt<W>> {
+        vec![Stmt::Halt {
+            ip: W::from_index(ip),
+            plus,
+        }]
+    }
+
+    /// The statements from the instruction `start` until `stop`, or until
+    /// control leaves the loops being decompiled, which are `stack`
+    /// innermost last. If `entered`, `start` is the header of the innermost
+    /// loop.
+    fn statements(
+        &mut self,
+        start: usize,
+        stop: Option<usize>,
+        stack: &mut Vec<usize>,
+        entered: bool,
+    ) -> Option<Vec<Stmt<W>>> {
+        let mut stmts = Vec::new();
+        let mut node = start;
+        let mut entered = entered;
+        loop {
+            self.budget = self.budget.checked_sub(1)?;
+            if !entered {
+                if Some(node) == stop {
+                    return Some(stmts);
+                }
+                for &header in stack.iter().rev() {
+                    if node == header {
+                        stmts.push(Stmt::Continue(header));
+                        return Some(stmts);
+                    }
+                    if Some(node) == self.loops[&header] {
+                        stmts.push(Stmt::Break(header));
+                        return Some(stmts);
+                    }
+                }
+                if self.loops.contains_key(&node) {
+                    stack.push(node);
+                    let body = self.statements(node, None, stack, true);
+                    stack.pop();
+                    stmts.push(Stmt::Loop {
+                        header: node,
+                        body: body?,
+                    });
+                    match self.loops[&node] {
+                        Some(follow) => {
+                            node = follow;
+                            continue;
+                        }
+                        None => return Some(stmts),
+                    }
+                }
+            }
+            entered = false;
+
+            match self.jumps[node] {
+                None => {
+                    stmts.push(Stmt::Instr(node));
+                    if node + 1 == self.len {
+                        stmts.extend(Self::halt(node, None));
+                        return Some(stmts);
+                    }
+                    node += 1;
+                }
+                Some(Jump::To(target)) if target == self.len => {
+                    let ip = disasm::constant(self.program, node)
+                        .expect("the jump is constant");
+                    stmts.push(Stmt::Halt { ip, plus: None });
+                    return Some(stmts);
+                }
+                Some(Jump::To(target)) => node = target,
+                Some(Jump::If { register, target }) => {
+                    let join = self.join(&[node + 1, target]);
+                    let then = match target {
+                        target if target == self.len => {
+                            Self::halt(node + 1, None)
+                        }
+                        target => {
+                            self.statements(target, join, stack, false)?
+                        }
+                    };
+                    let otherwise = match node + 1 {
+                        next if next == self.len => Self::halt(node, None),
+                        next => self.statements(next, join, stack, false)?,
+                    };
+                    stmts.push(Stmt::If {
+                        idx: node,
+                        register,
+                        then,
+                        otherwise,
+                    });
+                    match join {
+                        Some(join) => node = join,
+                        None => return Some(stmts),
+                    }
+                }
+                Some(Jump::Offset { register, base }) => {
+                    let targets = (base..self.len).collect::<Vec<_>>();
+                    let join = self.join(&targets);
+                    let mut arms: Vec<(Vec<W>, Vec<Stmt<W>>)> = Vec::new();
+                    for target in targets {
+                        let value = W::from_index(target - base);
+                        let body =
+                            self.statements(target, join, stack, false)?;
+                        match arms.iter_mut().find(|(_, arm)| *arm == body) {
+                            Some((values, _)) => values.push(value),
+                            None => arms.push((vec![value], body)),
+                        }
+                    }
+                    stmts.push(Stmt::Match {
+                        idx: node,
+                        register,
+                        arms,
+                    });
+                    match join {
+                        Some(join) => node = join,
+                        None => return Some(stmts),
+                    }
+                }
+                Some(Jump::Computed) => return None,
+            }
+        }
+    }
+}
+
+/// Whether control never runs past the end of the statements.
+fn terminates<W>(stmts: &[Stmt<W>]) -> bool {
+    match stmts.last() {
+        Some(Stmt::Halt { .. })
+        | Some(Stmt::Break(_))
+        | Some(Stmt::Continue(_)) => true,
+        Some(Stmt::If {
+            then, otherwise, ..
+        }) => terminates(then) && terminates(otherwise),
+        Some(Stmt::Match { arms, .. }) => {
+            arms.iter().all(|(_, arm)| terminates(arm))
+        }
+        Some(Stmt::Loop { header, body }) => !breaks(body, *header),
+        Some(Stmt::Instr(_)) | None => false,
+    }
+}
+
+/// Whether the statements leave the loop starting at `header`.
+fn breaks<W>(stmts: &[Stmt<W>], header: usize) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::Break(target) => *target == header,
+        Stmt::If {
+            then, otherwise, ..
+        } => breaks(then, header) || breaks(otherwise, header),
+        Stmt::Match { arms, .. } => {
+            arms.iter().any(|(_, arm)| breaks(arm, header))
+        }
+        Stmt::Loop { body, .. } => breaks(body, header),
+        _ => false,
+    })
+}
+
+/// Turn an `if` at the end of the body of the loop at `header` that goes on
+/// with the loop one way and leaves it the other into an `if` that leaves
+/// it, followed by the rest of the loop.
+fn invert_exit<W>(stmts: &mut Vec<Stmt<W>>, header: usize) {
+    let n = stmts.len();
+    match stmts.last() {
+        Some(Stmt::Break(target)) if n >= 2 && *target == header => {}
+        _ => return,
+    }
+    let continues = |branch: &[Stmt<W>]| {
+        if let Some(Stmt::Continue(target)) = branch.last() {
+            *target == header
+        } else {
+            false
+        }
+    };
+    let rest = match &mut stmts[n - 2] {
+        Stmt::If {
+            then, otherwise, ..
+        } if then.is_empty() && continues(otherwise) => {
+            then.push(Stmt::Break(header));
+            otherwise.split_off(0)
+        }
+        Stmt::If {
+            then, otherwise, ..
+        } if otherwise.is_empty() && continues(then) => {
+            otherwise.push(Stmt::Break(header));
+            then.split_off(0)
+        }
+        _ => return,
+    };
+    stmts.pop();
+    stmts.extend(rest);
+}
+
+/// Drop the `continue` at the end of the body of the loop at `header`,
+/// which it does anyway.
+fn drop_continue<W>(stmts: &mut Vec<Stmt<W>>, header: usize) {
+    match stmts.last_mut() {
+        Some(Stmt::Continue(target)) if *target == header => {
+            stmts.pop();
+        }
+        Some(Stmt::If {
+            then, otherwise, ..
+        }) => {
+            drop_continue(then, header);
+            drop_continue(otherwise, header);
+        }
+        _ => {}
+    }
+    if let Some(Stmt::If {
+        then, otherwise, ..
+    }) = stmts.last()
+    {
+        if then.is_empty() && otherwise.is_empty() {
+            stmts.pop();
+        }
+    }
+}
+
+/// Make the statements easier to read: an `else` after a `then` that
+/// does not run past its end follows the `if` instead, and empty `if`s and
+/// needless `continue`s go.
+fn tidy<W>(stmts: Vec<Stmt<W>>) -> Vec<Stmt<W>> {
+    let mut tidied = Vec::new();
+    for stmt in stmts {
+        match stmt {
+            Stmt::If {
+                idx,
+                register,
+                then,
+                otherwise,
+            } => {
+                let (then, otherwise) = (tidy(then), tidy(otherwise));
+                if then.is_empty() && otherwise.is_empty() {
+                    continue;
+                }
+                if terminates(&then) && !otherwise.is_empty() {
+                    tidied.push(Stmt::If {
+                        idx,
+                        register,
+                        then,
+                        otherwise: Vec::new(),
+                    });
+                    tidied.extend(otherwise);
+                } else {
+                    tidied.push(Stmt::If {
+                        idx,
+                        register,
+                        then,
+                        otherwise,
+                    });
+                }
+            }
+            Stmt::Match {
+                idx,
+                register,
+                arms,
+            } => tidied.push(Stmt::Match {
+                idx,
+                register,
+                arms: arms
+                    .into_iter()
+                    .map(|(values, arm)| (values, tidy(arm)))
+                    .collect(),
+            }),
+            Stmt::Loop { header, body } => {
+                let mut body = tidy(body);
+                invert_exit(&mut body, header);
+                drop_continue(&mut body, header);
+                tidied.push(Stmt::Loop { header, body });
+            }
+            stmt => tidied.push(stmt),
+        }
+    }
+    tidied
+}
+
+/// The program as structured statements, or `None` if its control flow
+/// cannot be structured.
+pub fn decompile<W: Word>(program: &Program<W>) -> Option<Vec<Stmt<W>>> {
+    let mut decompiler = Decompiler::new(program)?;
+    if program.instrs.is_empty() {
+        return Some(vec![]);
+    }
+    let stmts = decompiler.statements(0, None, &mut Vec::new(), false)?;
+    Some(tidy(stmts))
+}
+
+/// The number of registers the program uses.
+fn registers<W: Word>(program: &Program<W>) -> usize {
+    let mut used = program.ip_register.map_or(0, |ip| ip + 1);
+    for instr in &program.instrs {
+        let (a, b) = instr.op.operands();
+        for &(operand, value) in &[(a, instr.a), (b, instr.b)] {
+            if operand == Operand::Register {
+                used = used.max(value.to_index().expect("a register") + 1);
+            }
+        }
+        used = used.max(instr.c.to_index().expect("a register") + 1);
+    }
+    used
+}
+
+/// Writes the Rust code of decompiled programs.
+struct Writer<'a, W: 'a> {
+    program: &'a Program<W>,
+    code: String,
+    /// The loops that `break` or `continue` refer to from inner loops.
+    labelled: BTreeSet<usize>,
+}
+
+impl<'a, W: Word> Writer<'a, W> {
+    fn line(&mut self, depth: usize, line: &str) {
+        for _ in 0..depth {
+            self.code.push_str("    ");
+        }
+        self.code.push_str(line);
+        self.code.push('\n');
+    }
+
+    /// An operand as Rust. The instruction pointer reads as the index of the
+    /// instruction, and the masks of bitwise instructions are binary.
+    fn operand(&self, idx: usize, operand: Operand, value: W) -> String {
+        let op = self.program.instrs[idx].op;
+        match operand {
+            Operand::Register
+                if value.to_index() == self.program.ip_register =>
+            {
+                idx.to_string()
+            }
+            Operand::Register => format!("r[{}]", value),
+            Operand::Immediate
+                if (op == OpCode::Bani || op == OpCode::Bori)
+                    && value >= W::default() =>
+            {
+                format!("{:#b}", value)
+            }
+            Operand::Immediate | Operand::Unused => value.to_string(),
+        }
+    }
+
+    /// The instruction at `idx` as an assignment to `target`.
+    fn assignment(&self, idx: usize, target: &str) -> String {
+        let instr = self.program.instrs[idx];
+        if let Some(value) = disasm::constant(self.program, idx) {
+            return format!("{} = {}", target, value);
+        }
+
+        let (a_operand, b_operand) = instr.op.operands();
+        let a = self.operand(idx, a_operand, instr.a);
+        let b = self.operand(idx, b_operand, instr.b);
+        let (operator, commutes) = match instr.op {
+            OpCode::Setr | OpCode::Seti => {
+                return format!("{} = {}", target, a)
+            }
+            OpCode::Gtir | OpCode::Gtri | OpCode::Gtrr => {
+                return format!("{} = ({} > {}) as i64", target, a, b)
+            }
+            OpCode::Eqir | OpCode::Eqri | OpCode::Eqrr => {
+                return format!("{} = ({} == {}) as i64", target, a, b)
+            }
+            OpCode::Addr | OpCode::Addi => ("+", true),
+            OpCode::Mulr | OpCode::Muli => ("*", true),
+            OpCode::Banr | OpCode::Bani => ("&", true),
+            OpCode::Borr | OpCode::Bori => ("|", true),
+        };
+        if a == target {
+            format!("{} {}= {}", target, operator, b)
+        } else if commutes && b == target {
+            format!("{} {}= {}", target, operator, a)
+        } else {
+            format!("{} = {} {} {}", target, a, operator, b)
+        }
+    }
+
+    /// Find the loops that are referred to from inner loops.
+    fn label(&mut self, stmts: &[Stmt<W>], stack: &mut Vec<usize>) {
+        for stmt in stmts {
+            match stmt {
+                Stmt::Break(header) | Stmt::Continue(header)
+                    if stack.last() != Some(header) =>
+                {
+                    self.labelled.insert(*header);
+                }
+                Stmt::If {
+                    then, otherwise, ..
+                } => {
+                    self.label(then, stack);
+                    self.label(otherwise, stack);
+                }
+                Stmt::Match { arms, .. } => {
+                    for (_, arm) in arms {
+                        self.label(arm, stack);
+                    }
+                }
+                Stmt::Loop { header, body } => {
+                    stack.push(*header);
+                    self.label(body, stack);
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn jump(&self, keyword: &str, header: usize, stack: &[usize]) -> String {
+        if stack.last() == Some(&header) {
+            format!("{};", keyword)
+        } else {
+            format!("{} 'l{:02};", keyword, header)
+        }
+    }
+
+    fn halt(&mut self, depth: usize, ip: W, plus: Option<usize>, tail: bool) {
+        if let Some(register) = self.program.ip_register {
+            let line = match plus {
+                Some(plus) => {
+                    format!("r[{}] = {} + r[{}];", register, ip, plus)
+                }
+                None => format!("r[{}] = {};", register, ip),
+            };
+            self.line(depth, &line);
+        }
+        self.line(depth, if tail { "r" } else { "return r;" });
+    }
+
+    /// The first and last instruction of the statements.
+    fn span(stmts: &[Stmt<W>]) -> (usize, usize) {
+        let mut span = (usize::MAX, 0);
+        for stmt in stmts {
+            let (first, last) = match stmt {
+                Stmt::Instr(idx) => (*idx, *idx),
+                Stmt::If {
+                    idx,
+                    then,
+                    otherwise,
+                    ..
+                } => {
+                    let (then, otherwise) =
+                        (Self::span(then), Self::span(otherwise));
+                    (
+                        then.0.min(otherwise.0).min(*idx),
+                        then.1.max(otherwise.1).max(*idx),
+                    )
+                }
+                Stmt::Match { idx, arms, .. } => {
+                    arms.iter().map(|(_, arm)| Self::span(arm)).fold(
+                        (*idx, *idx),
+                        |(first, last), (arm_first, arm_last)| {
+                            (first.min(arm_first), last.max(arm_last))
+                        },
+                    )
+                }
+                Stmt::Loop { body, .. } => Self::span(body),
+                _ => continue,
+            };
+            span = (span.0.min(first), span.1.max(last));
+        }
+        span
+    }
+
+    fn statements(
+        &mut self,
+        stmts: &[Stmt<W>],
+        depth: usize,
+        stack: &mut Vec<usize>,
+        tail: bool,
+    ) {
+        for (n, stmt) in stmts.iter().enumerate() {
+            let tail = tail && n + 1 == stmts.len();
+            match stmt {
+                Stmt::Instr(idx) => {
+                    let c = format!("r[{}]", self.program.instrs[*idx].c);
+                    let line =
+                        format!("{}; // {:02}", self.assignment(*idx, &c), idx);
+                    self.line(depth, &line);
+                }
+                Stmt::If {
+                    idx,
+                    register,
+                    then,
+                    otherwise,
+                } => {
+                    let comment = format!("// {:02}", idx);
+                    if then.is_empty() {
+                        self.line(
+                            depth,
+                            &format!("if r[{}] == 0 {{", register),
+                        );
+                        self.line(depth + 1, &comment);
+                        self.statements(otherwise, depth + 1, stack, false);
+                    } else {
+                        self.line(
+                            depth,
+                            &format!("if r[{}] == 1 {{", register),
+                        );
+                        self.line(depth + 1, &comment);
+                        self.statements(then, depth + 1, stack, false);
+                        if !otherwise.is_empty() {
+                            self.line(depth, "} else {");
+                            self.statements(otherwise, depth + 1, stack, false);
+                        }
+                    }
+                    self.line(depth, "}");
+                }
+                Stmt::Match {
+                    idx,
+                    register,
+                    arms,
+                } => {
+                    self.line(depth, &format!("match r[{}] {{", register));
+                    self.line(depth + 1, &format!("// {:02}", idx));
+                    for (values, arm) in arms {
+                        let values = values
+                            .iter()
+                            .map(|value| value.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" | ");
+                        if arm.is_empty() {
+                            self.line(
+                                depth + 1,
+                                &format!("{} => {{}}", values),
+                            );
+                        } else {
+                            self.line(depth + 1, &format!("{} => {{", values));
+                            self.statements(arm, depth + 2, stack, false);
+                            self.line(depth + 1, "}");
+                        }
+                    }
+                    self.line(depth + 1, "_ => {");
+                    self.halt(
+                        depth + 2,
+                        W::from_index(*idx),
+                        Some(*register),
+                        false,
+                    );
+                    self.line(depth + 1, "}");
+                    self.line(depth, "}");
+                }
+                Stmt::Loop { header, body } => {
+                    let (first, last) = Self::span(body);
+                    let label = if self.labelled.contains(header) {
+                        format!("'l{:02}: ", header)
+                    } else {
+                        String::new()
+                    };
+                    self.line(depth, &format!("{}loop {{", label));
+                    let comment = format!("// {:02} - {:02}", first, last);
+                    self.line(depth + 1, &comment);
+                    stack.push(*header);
+                    self.statements(body, depth + 1, stack, false);
+                    stack.pop();
+                    self.line(depth, "}");
+                }
+                Stmt::Break(header) => {
+                    let line = self.jump("break", *header, stack);
+                    self.line(depth, &line);
+                }
+                Stmt::Continue(header) => {
+                    let line = self.jump("continue", *header, stack);
+                    self.line(depth, &line);
+                }
+                Stmt::Halt { ip, plus } => self.halt(depth, *ip, *plus, tail),
+            }
+        }
+    }
+}
+
+/// The program as a Rust function `run`, from the registers it starts with
+/// to those it halts with. Registers are `i64`s, and unlike on the machine,
+/// arithmetic does not wrap around. If the program cannot be structured, the
+/// function steps through it with a `match` on the instruction pointer.
+pub fn rust<W: Word>(program: &Program<W>) -> String {
+    let registers = registers(program);
+    let mut writer = Writer {
+        program,
+        code: String::new(),
+        labelled: BTreeSet::new(),
+    };
+    writer.line(
+        0,
+        "/// The ElfCode program as a function from the registers it starts \
+         with to",
+    );
+    writer.line(0, "/// those it halts with, decompiled by `aoc decompile`.");
+    writer.line(
+        0,
+        &format!(
+            "pub fn run(mut r: [i64; {}]) -> [i64; {}] {{",
+            registers, registers
+        ),
+    );
+
+    match decompile(program) {
+        Some(ref stmts) if stmts.is_empty() => writer.line(1, "r"),
+        Some(stmts) => {
+            writer.label(&stmts, &mut Vec::new());
+            writer.statements(&stmts, 1, &mut Vec::new(), true);
+        }
+        None => {
+            writer.line(1, "let mut ip = 0;");
+            writer.line(1, "loop {");
+            writer.line(2, "match ip {");
+            let jumps = disasm::jumps(program);
+            for (idx, jump) in jumps.into_iter().enumerate() {
+                let target = match jump {
+                    Some(_) => "ip".to_owned(),
+                    None => format!("r[{}]", program.instrs[idx].c),
+                };
+                let line =
+                    format!("{} => {},", idx, writer.assignment(idx, &target));
+                writer.line(3, &line);
+            }
+            writer.line(3, "_ => {");
+            if let Some(register) = program.ip_register {
+                writer.line(4, &format!("r[{}] = ip - 1;", register));
+            }
+            writer.line(4, "return r;");
+            writer.line(3, "}");
+            writer.line(2, "}");
+            writer.line(2, "ip += 1;");
+            writer.line(1, "}");
+        }
+    }
+    writer.line(0, "}");
+    writer.code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use days;
+    use elfcode::Machine;
+    use generate::{self, Options};
+    use input::Input;
+    use std::fs;
+
+    fn program(lines: &[&str]) -> Program<i64> {
+        Program::parse(lines, 6).unwrap()
+    }
+
+    /// How control leaves statements.
+    enum Flow {
+        Next,
+        Break(usize),
+        Continue(usize),
+        Halt,
+    }
+
+    /// Run statements on the registers of a machine, the way the Rust code
+    /// they decompile into would.
+    fn eval(
+        program: &Program<i64>,
+        stmts: &[Stmt<i64>],
+        r: &mut Machine<i64>,
+    ) -> Flow {
+        for stmt in stmts {
+            let flow = match stmt {
+                Stmt::Instr(idx) => {
+                    if let Some(ip) = program.ip_register {
+                        r.registers[ip] = *idx as i64;
+                    }
+                    r.execute(program.instrs[*idx]).unwrap();
+                    Flow::Next
+                }
+                Stmt::If {
+                    register,
+                    then,
+                    otherwise,
+                    ..
+                } => match r.registers[*register] {
+                    1 => eval(program, then, r),
+                    _ => eval(program, otherwise, r),
+                },
+                Stmt::Match {
+                    idx,
+                    register,
+                    arms,
+                } => {
+                    let value = r.registers[*register];
+                    match arms
+                        .iter()
+                        .find(|(values, _)| values.contains(&value))
+                    {
+                        Some((_, arm)) => eval(program, arm, r),
+                        None => {
+                            let ip = program.ip_register.unwrap();
+                            r.registers[ip] = *idx as i64 + value;
+                            Flow::Halt
+                        }
+                    }
+                }
+                Stmt::Loop { header, body } => loop {
+                    match eval(program, body, r) {
+                        Flow::Next => {}
+                        Flow::Continue(target) if target == *header => {}
+                        Flow::Break(target) if target == *header => {
+                            break Flow::Next;
+                        }
+                        flow => break flow,
+                    }
+                },
+                Stmt::Break(header) => Flow::Break(*header),
+                Stmt::Continue(header) => Flow::Continue(*header),
+                Stmt::Halt { ip, plus } => {
+                    if let Some(register) = program.ip_register {
+                        let plus = plus.map_or(0, |plus| r.registers[plus]);
+                        r.registers[register] = ip + plus;
+                    }
+                    Flow::Halt
+                }
+            };
+            match flow {
+                Flow::Next => {}
+                flow => return flow,
+            }
+        }
+        Flow::Next
+    }
+
+    /// Whether the decompiled program leaves the same registers as the
+    /// machine, starting with `r0` in register 0.
+    fn agrees(program: &Program<i64>, r0: i64) {
+        let mut machine = Machine::for_program(program, 6).unwrap();
+        machine.registers[0] = r0;
+        let mut registers = machine.registers.clone();
+        machine.run(&program.instrs).unwrap();
+
+        let stmts = decompile(program).expect("structured control flow");
+        let mut decompiled = Machine::with_registers(registers.split_off(0));
+        eval(program, &stmts, &mut decompiled);
+        assert_eq!(decompiled.registers, machine.registers, "{}", program);
+    }
+
+    #[test]
+    fn structured() {
+        let program = program(&[
+            "#ip 5",
+            "seti 10 0 1",
+            "gtri 0 3 2",
+            "addr 2 5 5",
+            "addi 3 1 3",
+            "addi 1 -1 1",
+            "eqri 1 0 2",
+            "addr 5 2 5",
+            "seti 0 0 5",
+            "gtri 3 5 2",
+            "addr 5 2 5",
+            "addi 0 7 0",
+        ]);
+        assert_eq!(
+            decompile(&program).unwrap(),
+            [
+                Stmt::Instr(0),
+                Stmt::Loop {
+                    header: 1,
+                    body: vec![
+                        Stmt::Instr(1),
+                        Stmt::If {
+                            idx: 2,
+                            register: 2,
+                            then: vec![],
+                            otherwise: vec![Stmt::Instr(3)],
+                        },
+                        Stmt::Instr(4),
+                        Stmt::Instr(5),
+                        Stmt::If {
+                            idx: 6,
+                            register: 2,
+                            then: vec![Stmt::Break(1)],
+                            otherwise: vec![],
+                        },
+                    ],
+                },
+                Stmt::Instr(8),
+                Stmt::If {
+                    idx: 9,
+                    register: 2,
+                    then: vec![Stmt::Halt { ip: 10, plus: None }],
+                    otherwise: vec![],
+                },
+                Stmt::Instr(10),
+                Stmt::Halt { ip: 10, plus: None },
+            ]
+        );
+        for r0 in -2..6 {
+            agrees(&program, r0);
+        }
+    }
+
+    #[test]
+    fn offsets() {
+        let program = program(&[
+            "#ip 4",
+            "addr 4 0 4",
+            "addi 1 1 1",
+            "addi 1 2 1",
+            "addr 4 0 4",
+            "seti 9 0 4",
+            "seti 0 0 2",
+            "muli 1 3 1",
+        ]);
+        for r0 in 0..10 {
+            agrees(&program, r0);
+        }
+    }
+
+    /// A comparison before an `addr` is not the only way to it when another
+    /// instruction jumps there directly, with the register set elsewhere.
+    #[test]
+    fn entered() {
+        let program = program(&[
+            "#ip 5",
+            "seti 3 0 1",
+            "seti 3 0 5",
+            "seti 0 0 0",
+            "eqri 2 7 1",
+            "addr 1 5 5",
+            "addi 0 1 0",
+            "addi 0 10 0",
+            "addi 0 100 0",
+            "addi 0 1000 0",
+        ]);
+        assert_eq!(
+            disasm::jumps(&program)[4],
+            Some(Jump::Offset {
+                register: 1,
+                base: 5
+            })
+        );
+        agrees(&program, 0);
+    }
+
+    /// The Rust code of the puzzle inputs is generated, and agrees with the
+    /// machine on generated inputs.
+    #[test]
+    fn decompiled() {
+        for &day in &[19, 21] {
+            let input = Input::for_day(day, None).unwrap();
+            let parse = days::program(day).unwrap();
+            let program = parse(&input.lines()).unwrap();
+            let path = format!("input/december{}decompiled.rs", day);
+            let decompiled = fs::read_to_string(path).unwrap();
+            assert_eq!(rust(&program), decompiled, "day {}", day);
+        }
+
+        for seed in 0..5 {
+            let options = Options { seed, size: 10 };
+            let input = generate::input(19, options).unwrap();
+            agrees(&days::program(19).unwrap()(&input.lines()).unwrap(), 0);
+
+            let input = generate::input(21, options).unwrap();
+            let program = days::program(21).unwrap()(&input.lines()).unwrap();
+            let r0 = ::december21::compared(&program, 0, 1).unwrap()[0];
+            agrees(&program, r0);
+        }
+    }
+
+    /// Programs that jump to computed instructions step through a `match`
+    /// on the instruction pointer.
+    #[test]
+    fn unstructured() {
+        let program =
+            program(&["#ip 1", "seti 2 0 2", "mulr 2 2 1", "addi 0 1 0"]);
+        assert_eq!(decompile(&program), None);
+        assert_eq!(
+            rust(&program),
+            "/// The ElfCode program as a function from the registers it starts \
+             with to\n\
+             /// those it halts with, decompiled by `aoc decompile`.\n\
+             pub fn run(mut r: [i64; 3]) -> [i64; 3] {\n    \
+                 let mut ip = 0;\n    \
+                 loop {\n        \
+                     match ip {\n            \
+                         0 => r[2] = 2,\n            \
+                         1 => ip = r[2] * r[2],\n            \
+                         2 => r[0] += 1,\n            \
+                         _ => {\n                \
+                             r[1] = ip - 1;\n                \
+                             return r;\n            \
+                         }\n        \
+                     }\n        \
+                     ip += 1;\n    \
+                 }\n\
+             }\n"
+        );
+    }
+}
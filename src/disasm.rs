@@ -0,0 +1,465 @@
+//! Listings of ElfCode programs, with every instruction in pseudocode. When
+//! the instruction pointer is bound to a register, the instructions that
+//! write to it are jumps: they are listed with where they go, and arrows are
+//! drawn from them to the instructions they go to.
+//!
+//! ```text
+//! #ip 3  # R3 is the instruction pointer
+//! 00 addi 3 16 3  # goto 17  <-\
+//! 01 seti 1 6 5   # R5 = 1     |
+//! ```
+use elfcode::{Instr, Machine, OpCode, Operand, Program, Word};
+
+/// Where an instruction that writes to the instruction pointer goes. The
+/// number of instructions stands for halting, by leaving the program.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Jump {
+    /// To an instruction known without running the program.
+    To(usize),
+    /// To the target if the register is 1, and to the next instruction if it
+    /// is 0: the register was set by a comparison just before, and is added
+    /// to the instruction pointer. The comparison is the only way in, or the
+    /// register could hold anything and the jump is an `Offset`.
+    If { register: usize, target: usize },
+    /// To the instruction `base` plus the register.
+    Offset { register: usize, base: usize },
+    /// To an instruction worked out some other way.
+    Computed,
+}
+
+fn register<W: Word>(r: W) -> usize {
+    r.to_index()
+        .expect("programs only name registers that exist")
+}
+
+fn is_comparison(op: OpCode) -> bool {
+    matches!(
+        op,
+        OpCode::Gtir
+            | OpCode::Gtri
+            | OpCode::Gtrr
+            | OpCode::Eqir
+            | OpCode::Eqri
+            | OpCode::Eqrr
+    )
+}
+
+/// The registers an instruction reads.
+pub fn reads<W: Word>(instr: Instr<W>) -> Vec<usize> {
+    let (a, b) = instr.op.operands();
+    let mut reads = Vec::new();
+    for (operand, value) in [(a, instr.a), (b, instr.b)].iter() {
+        if *operand == Operand::Register {
+            reads.push(register(*value));
+        }
+    }
+    reads
+}
+
+/// The value the instruction at `idx` writes, if it reads no register but
+/// the instruction pointer, which holds the index of the instruction.
+pub fn constant<W: Word>(program: &Program<W>, idx: usize) -> Option<W> {
+    let instr = program.instrs[idx];
+    let ip = program.ip_register;
+    if !reads(instr).iter().all(|&r| Some(r) == ip) {
+        return None;
+    }
+    let c = register(instr.c);
+    let mut machine = Machine::new(c.max(ip.unwrap_or(0)) + 1);
+    if let Some(ip) = ip {
+        machine.registers[ip] = W::from_index(idx);
+    }
+    machine.execute(instr).expect("the registers exist");
+    Some(machine.registers[c])
+}
+
+/// Where every instruction goes, if it is a jump.
+pub fn jumps<W: Word>(program: &Program<W>) -> Vec<Option<Jump>> {
+    let len = program.instrs.len();
+    let mut jumps = (0..len)
+        .map(|idx| jump_from(program, idx))
+        .collect::<Vec<_>>();
+
+    // Turning an `If` into an `Offset` may let more instructions be entered
+    // from elsewhere, so go on until none changes.
+    loop {
+        let mut entered = vec![false; len + 1];
+        for jump in &jumps {
+            match *jump {
+                None => {}
+                Some(Jump::To(target)) => entered[target] = true,
+                Some(Jump::If { target, .. }) => entered[target] = true,
+                Some(Jump::Offset { base, .. }) => {
+                    for entered in &mut entered[base.min(len)..] {
+                        *entered = true;
+                    }
+                }
+                Some(Jump::Computed) => entered = vec![true; len + 1],
+            }
+        }
+
+        let mut changed = false;
+        for (idx, jump) in jumps.iter_mut().enumerate() {
+            if let Some(Jump::If { register, .. }) = *jump {
+                if entered[idx] {
+                    *jump = Some(Jump::Offset {
+                        register,
+                        base: idx + 1,
+                    });
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return jumps;
+        }
+    }
+}
+
+/// Where the instruction at `idx` goes, if it is a jump, judging by it and
+/// the instruction before alone.
+fn jump_from<W: Word>(program: &Program<W>, idx: usize) -> Option<Jump> {
+    let ip = program.ip_register?;
+    let instr = program.instrs[idx];
+    if register(instr.c) != ip {
+        return None;
+    }
+    let len = program.instrs.len();
+    if let Some(value) = constant(program, idx) {
+        let target = value
+            .to_index()
+            .map_or(len, |target| target.saturating_add(1).min(len));
+        return Some(Jump::To(target));
+    }
+
+    let reads = reads(instr);
+    let other = match reads[..] {
+        [a, b] if a == ip && b != ip => Some(b),
+        [a, b] if b == ip && a != ip => Some(a),
+        _ => None,
+    };
+    match (instr.op, other) {
+        (OpCode::Addr, Some(register)) => {
+            let compared = idx
+                .checked_sub(1)
+                .map(|before| program.instrs[before])
+                .is_some_and(|before| {
+                    is_comparison(before.op)
+                        && self::register(before.c) == register
+                });
+            if compared {
+                Some(Jump::If {
+                    register,
+                    target: (idx + 2).min(len),
+                })
+            } else {
+                Some(Jump::Offset {
+                    register,
+                    base: idx + 1,
+                })
+            }
+        }
+        _ => Some(Jump::Computed),
+    }
+}
+
+/// An operand as pseudocode. The instruction pointer reads as the index of
+/// the instruction, and the masks of bitwise instructions are binary.
+fn operand<W: Word>(
+    program: &Program<W>,
+    idx: usize,
+    operand: Operand,
+    value: W,
+) -> String {
+    let op = program.instrs[idx].op;
+    match operand {
+        Operand::Register if program.ip_register == Some(register(value)) => {
+            idx.to_string()
+        }
+        Operand::Register => format!("R{}", value),
+        Operand::Immediate if op == OpCode::Bani || op == OpCode::Bori => {
+            format!("{:#b}", value)
+        }
+        Operand::Immediate | Operand::Unused => value.to_string(),
+    }
+}
+
+/// The operator of an instruction.
+fn operator(op: OpCode) -> Option<&'static str> {
+    match op {
+        OpCode::Addr | OpCode::Addi => Some("+"),
+        OpCode::Mulr | OpCode::Muli => Some("*"),
+        OpCode::Banr | OpCode::Bani => Some("&"),
+        OpCode::Borr | OpCode::Bori => Some("|"),
+        OpCode::Gtir | OpCode::Gtri | OpCode::Gtrr => Some(">"),
+        OpCode::Eqir | OpCode::Eqri | OpCode::Eqrr => Some("=="),
+        OpCode::Setr | OpCode::Seti => None,
+    }
+}
+
+fn goto(target: usize, len: usize) -> String {
+    if target >= len {
+        "halt".to_owned()
+    } else {
+        format!("goto {:02}", target)
+    }
+}
+
+/// The instruction at `idx` as pseudocode, like `R5 += R2`, `R1 = R2 > R4`
+/// or `goto 07 if R1`, given the `jumps` of the program.
+pub fn pseudocode<W: Word>(
+    program: &Program<W>,
+    jumps: &[Option<Jump>],
+    idx: usize,
+) -> String {
+    let len = program.instrs.len();
+    let instr = program.instrs[idx];
+    let (a_operand, b_operand) = instr.op.operands();
+    let a = operand(program, idx, a_operand, instr.a);
+    let b = operand(program, idx, b_operand, instr.b);
+    let c = format!("R{}", instr.c);
+    let expression = match operator(instr.op) {
+        Some(operator) => format!("{} {} {}", a, operator, b),
+        None => a.clone(),
+    };
+
+    match jumps[idx] {
+        Some(Jump::To(target)) => goto(target, len),
+        Some(Jump::If { register, target }) => {
+            format!("{} if R{}", goto(target, len), register)
+        }
+        Some(Jump::Offset { register, base }) => {
+            format!("goto {:02} + R{}", base, register)
+        }
+        Some(Jump::Computed) => format!("goto {} + 1", expression),
+        None => match operator(instr.op) {
+            Some(operator) if a == c && !is_comparison(instr.op) => {
+                format!("{} {}= {}", c, operator, b)
+            }
+            _ => format!("{} = {}", c, expression),
+        },
+    }
+}
+
+/// Give every arrow a lane, the innermost one it does not overlap another
+/// arrow in. Shorter arrows are placed first, so they are on the inside.
+fn lanes(arrows: &[(usize, usize)]) -> Vec<usize> {
+    let span = |&(from, to): &(usize, usize)| (from.min(to), from.max(to));
+    let mut order = (0..arrows.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&n| {
+        let (top, bottom) = span(&arrows[n]);
+        (bottom - top, top)
+    });
+
+    let mut lanes = vec![0; arrows.len()];
+    let mut taken: Vec<Vec<(usize, usize)>> = Vec::new();
+    for n in order {
+        let (top, bottom) = span(&arrows[n]);
+        let free = taken.iter().position(|spans| {
+            spans.iter().all(|&(t, b)| bottom < t || b < top)
+        });
+        let lane = free.unwrap_or(taken.len());
+        if lane == taken.len() {
+            taken.push(Vec::new());
+        }
+        taken[lane].push((top, bottom));
+        lanes[n] = lane;
+    }
+    lanes
+}
+
+/// The arrows from jumps to the instructions they go to, a row of text per
+/// instruction. Jumps to the next instruction and out of the program have
+/// none.
+fn arrows(jumps: &[Option<Jump>]) -> Vec<String> {
+    let len = jumps.len();
+    let mut arrows = Vec::new();
+    for (idx, &jump) in jumps.iter().enumerate() {
+        let targets = match jump {
+            Some(Jump::To(target)) => vec![target],
+            Some(Jump::If { target, .. }) => vec![target],
+            _ => vec![],
+        };
+        for target in targets {
+            if target < len && target != idx && target != idx + 1 {
+                arrows.push((idx, target));
+            }
+        }
+    }
+
+    let lanes = lanes(&arrows);
+    let width = 3 * lanes.iter().map(|&lane| lane + 1).max().unwrap_or(0);
+    let mut grid = vec![vec![' '; width]; len];
+    for &(_, to) in &arrows {
+        grid[to][0] = '<';
+    }
+    for (&(from, to), &lane) in arrows.iter().zip(&lanes) {
+        for &row in &[from, to] {
+            for cell in &mut grid[row][..3 * lane + 2] {
+                if *cell == ' ' {
+                    *cell = '-';
+                }
+            }
+        }
+    }
+    for (&(from, to), &lane) in arrows.iter().zip(&lanes) {
+        let (top, bottom) = (from.min(to), from.max(to));
+        for row in &mut grid[top + 1..bottom] {
+            row[3 * lane + 2] = '|';
+        }
+    }
+    for (&(from, to), &lane) in arrows.iter().zip(&lanes) {
+        grid[from.min(to)][3 * lane + 2] = '\\';
+        grid[from.max(to)][3 * lane + 2] = '/';
+    }
+
+    grid.into_iter()
+        .map(|row| row.into_iter().collect::<String>().trim_end().to_owned())
+        .collect()
+}
+
+/// The program with every instruction numbered and in pseudocode, and the
+/// jumps drawn as arrows.
+pub fn listing<W: Word>(program: &Program<W>) -> String {
+    let len = program.instrs.len();
+    let instrs = program
+        .instrs
+        .iter()
+        .enumerate()
+        .map(|(idx, instr)| format!("{:02} {}", idx, instr))
+        .collect::<Vec<_>>();
+    let jumps = jumps(program);
+    let pseudocode = (0..len)
+        .map(|idx| pseudocode(program, &jumps, idx))
+        .collect::<Vec<_>>();
+    let arrows = arrows(&jumps);
+    let instr_width = instrs.iter().map(String::len).max().unwrap_or(0);
+    let pseudo_width = pseudocode.iter().map(String::len).max().unwrap_or(0);
+
+    let mut listing = String::new();
+    if let Some(ip) = program.ip_register {
+        listing +=
+            &format!("#ip {}  # R{} is the instruction pointer\n", ip, ip);
+    }
+    for idx in 0..len {
+        let line = format!(
+            "{:iw$}  # {:pw$}  {}",
+            instrs[idx],
+            pseudocode[idx],
+            arrows[idx],
+            iw = instr_width,
+            pw = pseudo_width
+        );
+        listing += line.trim_end();
+        listing.push('\n');
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use days;
+    use input::Input;
+    use std::fs;
+
+    fn program(lines: &[&str]) -> Program<i64> {
+        Program::parse(lines, 6).unwrap()
+    }
+
+    #[test]
+    fn jumps() {
+        let program = program(&[
+            "#ip 3",
+            "addi 3 16 3",
+            "gtrr 2 4 1",
+            "addr 3 1 3",
+            "addr 1 3 3",
+            "addr 3 0 3",
+            "mulr 3 3 3",
+            "mulr 3 1 3",
+            "setr 3 9 1",
+            "bani 5 456 5",
+        ]);
+        // The jumps at 04 and 06 may go to 02, so R1 may be anything there.
+        let len = program.instrs.len();
+        assert_eq!(
+            super::jumps(&program),
+            [
+                Some(Jump::To(9)),
+                None,
+                Some(Jump::Offset {
+                    register: 1,
+                    base: 3
+                }),
+                Some(Jump::Offset {
+                    register: 1,
+                    base: 4
+                }),
+                Some(Jump::Offset {
+                    register: 0,
+                    base: 5
+                }),
+                Some(Jump::To(9)),
+                Some(Jump::Computed),
+                None,
+                None,
+            ]
+        );
+
+        let jumps = super::jumps(&program);
+        let pseudocode = (0..len)
+            .map(|idx| pseudocode(&program, &jumps, idx))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pseudocode,
+            [
+                "halt",
+                "R1 = R2 > R4",
+                "goto 03 + R1",
+                "goto 04 + R1",
+                "goto 05 + R0",
+                "halt",
+                "goto 6 * R1 + 1",
+                "R1 = 7",
+                "R5 &= 0b111001000",
+            ]
+        );
+    }
+
+    #[test]
+    fn arrows() {
+        let program = program(&[
+            "#ip 2",
+            "seti 123 0 5",
+            "eqri 5 72 5",
+            "addr 5 2 2",
+            "seti 0 0 2",
+            "seti 0 0 2",
+            "seti 0 0 5",
+        ]);
+        assert_eq!(
+            listing(&program),
+            "#ip 2  # R2 is the instruction pointer\n\
+             00 seti 123 0 5  # R5 = 123\n\
+             01 eqri 5 72 5   # R5 = R5 == 72  <-\\-----\\\n\
+             02 addr 5 2 2    # goto 04 if R5  --|--\\  |\n\
+             03 seti 0 0 2    # goto 01        --/  |  |\n\
+             04 seti 0 0 2    # goto 01        <----/--/\n\
+             05 seti 0 0 5    # R5 = 0\n"
+        );
+    }
+
+    /// The annotated listings of the puzzle inputs are generated.
+    #[test]
+    fn annotated() {
+        for &day in &[19, 21] {
+            let input = Input::for_day(day, None).unwrap();
+            let parse = days::program(day).unwrap();
+            let program = parse(&input.lines()).unwrap();
+            let path = format!("input/december{}annotated.txt", day);
+            let annotated = fs::read_to_string(path).unwrap();
+            assert_eq!(listing(&program), annotated, "day {}", day);
+        }
+    }
+}
@@ -0,0 +1,567 @@
+//! The ElfCode machine of days 16, 19 and 21: a bank of registers, sixteen
+//! opcodes, and an instruction pointer that can be bound to a register.
+use error::Error;
+use parse;
+use solution::{self, Result};
+use std::convert::TryFrom;
+use std::fmt::{self, Binary, Debug, Display};
+use std::ops::{BitAnd, BitOr, Div, Rem};
+use std::str::FromStr;
+
+/// The type of the values in registers and operands.
+pub trait Word:
+    Copy
+    + Eq
+    + Ord
+    + Default
+    + Debug
+    + Display
+    + Binary
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+{
+    fn wrapping_add(self, other: Self) -> Self;
+    fn wrapping_mul(self, other: Self) -> Self;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn from_bool(value: bool) -> Self;
+    fn from_index(index: usize) -> Self;
+    /// The value as an index, or `None` if it is negative or too large.
+    fn to_index(self) -> Option<usize>;
+}
+
+macro_rules! impl_word {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl Word for $t {
+                fn wrapping_add(self, other: Self) -> Self {
+                    <$t>::wrapping_add(self, other)
+                }
+
+                fn wrapping_mul(self, other: Self) -> Self {
+                    <$t>::wrapping_mul(self, other)
+                }
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+
+                fn from_bool(value: bool) -> Self {
+                    value.into()
+                }
+
+                fn from_index(index: usize) -> Self {
+                    index as $t
+                }
+
+                fn to_index(self) -> Option<usize> {
+                    usize::try_from(self).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_word!(i32, i64, u32, u64);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum OpCode {
+    Addr,
+    Addi,
+    Mulr,
+    Muli,
+    Banr,
+    Bani,
+    Borr,
+    Bori,
+    Setr,
+    Seti,
+    Gtir,
+    Gtri,
+    Gtrr,
+    Eqir,
+    Eqri,
+    Eqrr,
+}
+use self::OpCode::*;
+
+pub static OP_CODES: &[OpCode] = &[
+    Addr, Addi, Mulr, Muli, Banr, Bani, Borr, Bori, Setr, Seti, Gtir, Gtri,
+    Gtrr, Eqir, Eqri, Eqrr,
+];
+
+/// How an instruction uses its A or B operand.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Operand {
+    Register,
+    Immediate,
+    Unused,
+}
+
+impl OpCode {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Addr => "addr",
+            Addi => "addi",
+            Mulr => "mulr",
+            Muli => "muli",
+            Banr => "banr",
+            Bani => "bani",
+            Borr => "borr",
+            Bori => "bori",
+            Setr => "setr",
+            Seti => "seti",
+            Gtir => "gtir",
+            Gtri => "gtri",
+            Gtrr => "gtrr",
+            Eqir => "eqir",
+            Eqri => "eqri",
+            Eqrr => "eqrr",
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<OpCode> {
+        OP_CODES
+            .iter()
+            .cloned()
+            .find(|op| op.mnemonic() == mnemonic)
+    }
+
+    /// How the operands A and B are used. Operand C always names a register.
+    pub fn operands(self) -> (Operand, Operand) {
+        use self::Operand::*;
+        match self {
+            Addr | Mulr | Banr | Borr | Gtrr | Eqrr => (Register, Register),
+            Addi | Muli | Bani | Bori | Gtri | Eqri => (Register, Immediate),
+            Gtir | Eqir => (Immediate, Register),
+            Setr => (Register, Unused),
+            Seti => (Immediate, Unused),
+        }
+    }
+
+    /// The value the instruction writes to register C, given the values of
+    /// its operands: the registers they name, or the operands themselves if
+    /// they are immediate.
+    pub fn apply<W: Word>(self, a: W, b: W) -> W {
+        match self {
+            Addr | Addi => a.wrapping_add(b),
+            Mulr | Muli => a.wrapping_mul(b),
+            Banr | Bani => a & b,
+            Borr | Bori => a | b,
+            Setr | Seti => a,
+            Gtir | Gtri | Gtrr => W::from_bool(a > b),
+            Eqir | Eqri | Eqrr => W::from_bool(a == b),
+        }
+    }
+}
+
+impl Display for OpCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.mnemonic())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Instr<W> {
+    pub op: OpCode,
+    pub a: W,
+    pub b: W,
+    pub c: W,
+}
+
+impl<W> Instr<W> {
+    pub fn new(op: OpCode, a: W, b: W, c: W) -> Instr<W> {
+        Instr { op, a, b, c }
+    }
+}
+
+impl<W: Display> Display for Instr<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.op, self.a, self.b, self.c)
+    }
+}
+
+/// Why the machine stopped in the middle of a program.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fault<W> {
+    /// An operand names a register the machine does not have.
+    BadRegister { ip: W, register: W },
+    /// The machine was stepped with its instruction pointer outside the
+    /// program.
+    IpOutOfRange(W),
+}
+
+impl<W: Display> Display for Fault<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::BadRegister { ip, register } => {
+                write!(f, "no register {} (at instruction {})", register, ip)
+            }
+            Fault::IpOutOfRange(ip) => {
+                write!(f, "instruction pointer {} is outside the program", ip)
+            }
+        }
+    }
+}
+
+impl<W: Display> From<Fault<W>> for Error {
+    fn from(fault: Fault<W>) -> Error {
+        Error::unsolvable(format!("The ElfCode program faulted: {}", fault))
+    }
+}
+
+/// A program, with the register the instruction pointer is bound to if it
+/// starts with an `#ip <register>` directive.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Program<W> {
+    pub ip_register: Option<usize>,
+    pub instrs: Vec<Instr<W>>,
+}
+
+impl<W> Program<W>
+where
+    W: Word + FromStr,
+    W::Err: Display,
+{
+    /// Parse instructions like `addi 0 1 2`, checking that register operands
+    /// name one of the given number of registers.
+    pub fn parse(lines: &[&str], registers: usize) -> Result<Program<W>> {
+        let bad_register = || match registers {
+            0 => "expected no registers, as there are none".to_owned(),
+            registers => {
+                format!("expected a register from 0 to {}", registers - 1)
+            }
+        };
+
+        let mut ip_register = None;
+        let mut instrs = Vec::new();
+        for line in parse::lines(lines) {
+            if line.idx == 0 && line.text.starts_with("#ip") {
+                let register = line.after("#ip ")?;
+                let value = register.parse::<usize>()?;
+                if value >= registers {
+                    return Err(register.error(bad_register()));
+                }
+                ip_register = Some(value);
+                continue;
+            }
+
+            let split = line.split(" ");
+            if split.len() != 4 {
+                return Err(
+                    line.error("expected an instruction like `addi 0 1 2`")
+                );
+            }
+
+            let op = OpCode::from_mnemonic(split[0].text).ok_or_else(|| {
+                line.error(format!("unknown opcode `{}`", split[0].text))
+            })?;
+            let args = split[1..]
+                .iter()
+                .map(|arg| arg.parse())
+                .collect::<Result<Vec<W>>>()?;
+
+            let (a, b) = op.operands();
+            for (n, operand) in
+                IntoIterator::into_iter([a, b, Operand::Register]).enumerate()
+            {
+                let in_range =
+                    args[n].to_index().is_some_and(|r| r < registers);
+                if operand == Operand::Register && !in_range {
+                    return Err(split[n + 1].error(bad_register()));
+                }
+            }
+
+            instrs.push(Instr::new(op, args[0], args[1], args[2]));
+        }
+
+        Ok(Program {
+            ip_register,
+            instrs,
+        })
+    }
+}
+
+impl<W: Word> Program<W> {
+    /// The same program using register `renaming[r]` wherever it used
+    /// register `r`, including the one the instruction pointer is bound to.
+    /// Every register the program uses must be renamed.
+    pub fn renamed(&self, renaming: &[usize]) -> Program<W> {
+        let rename = |r: W| W::from_index(renaming[r.to_index().unwrap()]);
+        let instrs = self
+            .instrs
+            .iter()
+            .map(|instr| {
+                let (a, b) = instr.op.operands();
+                let operand = |operand, x| match operand {
+                    Operand::Register => rename(x),
+                    _ => x,
+                };
+                Instr::new(
+                    instr.op,
+                    operand(a, instr.a),
+                    operand(b, instr.b),
+                    rename(instr.c),
+                )
+            })
+            .collect();
+
+        Program {
+            ip_register: self.ip_register.map(|r| renaming[r]),
+            instrs,
+        }
+    }
+
+    /// The same program with its operands converted to a wider type, so that
+    /// it can run with values that would overflow this one.
+    pub fn widened<V: Word + From<W>>(&self) -> Program<V> {
+        Program {
+            ip_register: self.ip_register,
+            instrs: self
+                .instrs
+                .iter()
+                .map(|instr| {
+                    Instr::new(
+                        instr.op,
+                        instr.a.into(),
+                        instr.b.into(),
+                        instr.c.into(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The program in the format it is parsed from.
+impl<W: Display> Display for Program<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(register) = self.ip_register {
+            writeln!(f, "#ip {}", register)?;
+        }
+        for instr in &self.instrs {
+            writeln!(f, "{}", instr)?;
+        }
+        Ok(())
+    }
+}
+
+/// The state of a machine: its registers, and its instruction pointer, which
+/// is written to and read back from the bound register around every
+/// instruction if there is one.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Machine<W> {
+    pub registers: Vec<W>,
+    pub ip: W,
+    ip_register: Option<usize>,
+}
+
+impl<W: Word> Machine<W> {
+    /// A machine with the given number of registers, all zero.
+    pub fn new(registers: usize) -> Machine<W> {
+        Machine::with_registers(vec![W::default(); registers])
+    }
+
+    pub fn with_registers(registers: Vec<W>) -> Machine<W> {
+        Machine {
+            registers,
+            ip: W::default(),
+            ip_register: None,
+        }
+    }
+
+    /// Bind the instruction pointer to a register.
+    pub fn bind_ip(
+        mut self,
+        register: usize,
+    ) -> std::result::Result<Machine<W>, Fault<W>> {
+        if register >= self.registers.len() {
+            return Err(Fault::BadRegister {
+                ip: self.ip,
+                register: W::from_index(register),
+            });
+        }
+        self.ip_register = Some(register);
+        Ok(self)
+    }
+
+    /// A machine with the given number of registers, all zero, and the
+    /// instruction pointer bound as the program asks.
+    pub fn for_program(
+        program: &Program<W>,
+        registers: usize,
+    ) -> std::result::Result<Machine<W>, Fault<W>> {
+        let machine = Machine::new(registers);
+        match program.ip_register {
+            Some(register) => machine.bind_ip(register),
+            None => Ok(machine),
+        }
+    }
+
+    pub fn ip_register(&self) -> Option<usize> {
+        self.ip_register
+    }
+
+    fn index(&self, register: W) -> std::result::Result<usize, Fault<W>> {
+        register
+            .to_index()
+            .filter(|&r| r < self.registers.len())
+            .ok_or(Fault::BadRegister {
+                ip: self.ip,
+                register,
+            })
+    }
+
+    fn read(&self, register: W) -> std::result::Result<W, Fault<W>> {
+        Ok(self.registers[self.index(register)?])
+    }
+
+    /// Execute a single instruction on the registers, leaving the instruction
+    /// pointer alone.
+    pub fn execute(
+        &mut self,
+        instr: Instr<W>,
+    ) -> std::result::Result<(), Fault<W>> {
+        let Instr { op, a, b, c } = instr;
+        let (a_operand, b_operand) = op.operands();
+        let value = |operand, x| match operand {
+            Operand::Register => self.read(x),
+            _ => Ok(x),
+        };
+        let value = op.apply(value(a_operand, a)?, value(b_operand, b)?);
+
+        let c = self.index(c)?;
+        self.registers[c] = value;
+        Ok(())
+    }
+
+    /// Whether the instruction pointer has left the program.
+    pub fn halted(&self, program: &[Instr<W>]) -> bool {
+        self.ip.to_index().is_none_or(|ip| ip >= program.len())
+    }
+
+    /// Execute the instruction the instruction pointer points at, and move
+    /// on to the next one.
+    pub fn step(
+        &mut self,
+        program: &[Instr<W>],
+    ) -> std::result::Result<(), Fault<W>> {
+        let instr = self
+            .ip
+            .to_index()
+            .and_then(|ip| program.get(ip))
+            .ok_or(Fault::IpOutOfRange(self.ip))?;
+
+        if let Some(register) = self.ip_register {
+            self.registers[register] = self.ip;
+        }
+        self.execute(*instr)?;
+        if let Some(register) = self.ip_register {
+            self.ip = self.registers[register];
+        }
+        self.ip = self.ip.wrapping_add(W::from_index(1));
+
+        Ok(())
+    }
+
+    /// Run until the program halts, returning the number of instructions
+    /// executed.
+    pub fn run(
+        &mut self,
+        program: &[Instr<W>],
+    ) -> std::result::Result<u64, Fault<W>> {
+        self.run_until(program, |_| false)
+    }
+
+    /// Run until the program halts or `stop` returns true before executing an
+    /// instruction, returning the number of instructions executed.
+    pub fn run_until<F>(
+        &mut self,
+        program: &[Instr<W>],
+        mut stop: F,
+    ) -> std::result::Result<u64, Fault<W>>
+    where
+        F: FnMut(&Machine<W>) -> bool,
+    {
+        let mut steps = 0;
+        while !self.halted(program) && !stop(self) {
+            self.step(program)?;
+            steps += 1;
+            // Programs can run for a long time, or forever.
+            if steps % 0x10000 == 0 {
+                solution::checkpoint();
+            }
+        }
+        Ok(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example program of day 19.
+    fn example() -> Program<i32> {
+        let lines = [
+            "#ip 0",
+            "seti 5 0 1",
+            "seti 6 0 2",
+            "addi 0 1 0",
+            "addr 1 2 3",
+            "setr 1 0 0",
+            "seti 8 0 4",
+            "seti 9 0 5",
+        ];
+        Program::parse(&lines, 6).unwrap()
+    }
+
+    #[test]
+    fn run() {
+        let program = example();
+        let mut machine = Machine::for_program(&program, 6).unwrap();
+        assert_eq!(machine.run(&program.instrs), Ok(5));
+        assert_eq!(machine.registers, vec![6, 5, 6, 0, 0, 9]);
+        assert_eq!(machine.step(&program.instrs), Err(Fault::IpOutOfRange(7)));
+    }
+
+    #[test]
+    fn renamed() {
+        let program = example().renamed(&[5, 4, 3, 2, 1, 0]);
+        let text = program.to_string();
+        assert!(text.starts_with("#ip 5\nseti 5 0 4\nseti 6 0 3\naddi 5 1 5\n"));
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(Program::parse(&lines, 6).unwrap(), program);
+
+        let mut machine = Machine::for_program(&program, 6).unwrap();
+        assert_eq!(machine.run(&program.instrs), Ok(5));
+        assert_eq!(machine.registers, vec![9, 0, 0, 6, 5, 6]);
+    }
+
+    #[test]
+    fn faults() {
+        let mut machine = Machine::<i32>::new(4);
+        assert_eq!(
+            machine.execute(Instr::new(Addr, 0, 4, 1)),
+            Err(Fault::BadRegister { ip: 0, register: 4 })
+        );
+        assert!(Machine::<i32>::new(4).bind_ip(4).is_err());
+
+        let err =
+            Program::<i32>::parse(&["#ip 1", "addr 0 6 1"], 6).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "<input>:2:8: expected a register from 0 to 5"
+        );
+        let err = Program::<i32>::parse(&["seti 1 0 0"], 0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "<input>:1:10: expected no registers, as there are none"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! The error type shared by all solutions.
+use std::fmt::{self, Display};
+use std::io;
+use std::str::FromStr;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The puzzle input is malformed. Lines and columns count from 1.
+    Input {
+        file: Option<String>,
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    /// The puzzle input could not be read.
+    Io(io::Error),
+    /// A puzzle parameter is unknown or has an invalid value.
+    Param(String),
+    /// The input is well-formed, but the puzzle cannot be solved for it.
+    Unsolvable(String),
+}
+
+impl Error {
+    /// A malformed input error at the given byte offset of the line with the
+    /// given index.
+    pub fn input<R: Into<String>>(
+        idx: usize,
+        offset: usize,
+        reason: R,
+    ) -> Error {
+        Error::Input {
+            file: None,
+            line: idx + 1,
+            column: offset + 1,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn unsolvable<R: Into<String>>(reason: R) -> Error {
+        Error::Unsolvable(reason.into())
+    }
+
+    /// Record the name of the file the input was read from.
+    pub fn in_file(self, name: &str) -> Error {
+        match self {
+            Error::Input {
+                line,
+                column,
+                reason,
+                ..
+            } => Error::Input {
+                file: Some(name.to_owned()),
+                line,
+                column,
+                reason,
+            },
+            err => err,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Input {
+                file,
+                line,
+                column,
+                reason,
+            } => write!(
+                f,
+                "{}:{}:{}: {}",
+                file.as_deref().unwrap_or("<input>"),
+                line,
+                column,
+                reason
+            ),
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Param(reason) | Error::Unsolvable(reason) => {
+                write!(f, "{}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+/// The byte offset of `part` in `line`. `part` must be a slice of `line`.
+pub fn offset(line: &str, part: &str) -> usize {
+    part.as_ptr() as usize - line.as_ptr() as usize
+}
+
+/// Parse `part`, a slice of the line with the given index, reporting the
+/// position of `part` if it is malformed.
+pub fn parse_at<T>(idx: usize, line: &str, part: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    part.parse().map_err(|err| {
+        Error::input(
+            idx,
+            offset(line, part),
+            format!("invalid number `{}`: {}", part, err),
+        )
+    })
+}
+
+/// Parse the value of the puzzle parameter with the given name.
+pub fn parse_param<T>(name: &str, value: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.parse().map_err(|err| {
+        Error::Param(format!("Invalid value `{}` for {}: {}", value, name, err))
+    })
+}
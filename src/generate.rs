@@ -0,0 +1,140 @@
+//! Seeded random puzzle inputs, for fuzzing the solutions and for testing how
+//! they scale. Every day generates inputs in the format of its puzzle with
+//! `Solution::generate`, built so that they can be solved: the same seed and
+//! size always give the same input.
+use days;
+use input::Input;
+
+/// A small, fast pseudo-random number generator (SplitMix64). It is not
+/// cryptographically secure, but its output does not depend on the platform.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number from 0 up to but not including `n`, which must not be 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "cannot pick a number below 0");
+        // Reject the top of the range that would make low numbers likelier.
+        let limit = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < limit {
+                return x % n;
+            }
+        }
+    }
+
+    /// A number from `from` to `to`, inclusive.
+    pub fn range(&mut self, from: i64, to: i64) -> i64 {
+        assert!(from <= to, "empty range {}..={}", from, to);
+        let span = to.wrapping_sub(from) as u64;
+        let offset = if span == u64::MAX {
+            self.next_u64()
+        } else {
+            self.below(span + 1)
+        };
+        from.wrapping_add(offset as i64)
+    }
+
+    /// An index into a slice of length `len`, which must not be 0.
+    pub fn index(&mut self, len: usize) -> usize {
+        self.below(len as u64) as usize
+    }
+
+    /// True with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        // The top 53 bits give every double in [0, 1) with a 2^-53 step.
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.index(items.len())]
+    }
+
+    /// Shuffle the items in place (Fisher-Yates).
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.index(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+/// How to generate an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub seed: u64,
+    /// How large the input is; what that means depends on the day, such as
+    /// the number of lines or the width of a map.
+    pub size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options { seed: 0, size: 50 }
+    }
+}
+
+/// A random input for the given day, if there is a puzzle on that day.
+pub fn input(day: u32, options: Options) -> Option<Input> {
+    let generator = days::generator(day)?;
+    let text = generator(&mut Rng::new(options.seed), options.size);
+    Some(Input::new(
+        format!(
+            "generated day {} (seed {}, size {})",
+            day, options.seed, options.size
+        ),
+        text,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solution::Part;
+
+    #[test]
+    fn rng() {
+        let mut rng = Rng::new(1);
+        let first = (0..100).map(|_| rng.range(-3, 3)).collect::<Vec<_>>();
+        assert!(first.iter().all(|x| (-3..=3).contains(x)));
+        assert!((-3..=3).all(|x| first.contains(&x)));
+
+        let mut again = Rng::new(1);
+        assert!(first.iter().all(|&x| again.range(-3, 3) == x));
+
+        let mut items = (0..10).collect::<Vec<_>>();
+        rng.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+    }
+
+    /// Small generated inputs of every day can be parsed and solved.
+    #[test]
+    fn solvable() {
+        for day in 1..=days::DAYS {
+            for seed in 0..3 {
+                let options = Options { seed, size: 10 };
+                let input = input(day, options).unwrap();
+                let solver = days::solver(day).unwrap();
+                if let Err(err) = solver(&input, Part::Both, &[]) {
+                    panic!("{}: {}\n{}", input.name(), err, input.as_str());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,455 @@
+//! Two-dimensional grids of tiles, as drawn with characters in the puzzles.
+//!
+//! A grid stores its tiles either densely, for maps given as rows of
+//! characters, or sparsely, for maps of which only a few positions are known.
+use error::{Error, Result};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+
+/// A position in a grid, with `y` increasing downwards.
+///
+/// Positions are ordered in reading order: top to bottom, then left to right.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Pos {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Pos {
+    pub fn new(x: i32, y: i32) -> Pos {
+        Pos { x, y }
+    }
+
+    /// The four orthogonal neighbours, in reading order.
+    pub fn neighbours4(self) -> [Pos; 4] {
+        let Pos { x, y } = self;
+        [
+            Pos::new(x, y - 1),
+            Pos::new(x - 1, y),
+            Pos::new(x + 1, y),
+            Pos::new(x, y + 1),
+        ]
+    }
+
+    /// The eight orthogonal and diagonal neighbours, in reading order.
+    pub fn neighbours8(self) -> [Pos; 8] {
+        let Pos { x, y } = self;
+        [
+            Pos::new(x - 1, y - 1),
+            Pos::new(x, y - 1),
+            Pos::new(x + 1, y - 1),
+            Pos::new(x - 1, y),
+            Pos::new(x + 1, y),
+            Pos::new(x - 1, y + 1),
+            Pos::new(x, y + 1),
+            Pos::new(x + 1, y + 1),
+        ]
+    }
+}
+
+impl PartialOrd for Pos {
+    fn partial_cmp(&self, other: &Pos) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pos {
+    fn cmp(&self, other: &Pos) -> Ordering {
+        (self.y, self.x).cmp(&(other.y, other.x))
+    }
+}
+
+/// The smallest rectangle containing a set of positions, with inclusive
+/// corners.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Bounds {
+    pub min: Pos,
+    pub max: Pos,
+}
+
+impl Bounds {
+    pub fn contains(&self, pos: Pos) -> bool {
+        pos.x >= self.min.x
+            && pos.x <= self.max.x
+            && pos.y >= self.min.y
+            && pos.y <= self.max.y
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max.x - self.min.x + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max.y - self.min.y + 1) as usize
+    }
+
+    /// Grow the bounds to contain the position.
+    fn extend(self, pos: Pos) -> Bounds {
+        Bounds {
+            min: Pos::new(self.min.x.min(pos.x), self.min.y.min(pos.y)),
+            max: Pos::new(self.max.x.max(pos.x), self.max.y.max(pos.y)),
+        }
+    }
+}
+
+/// How a grid stores its tiles.
+pub trait Storage<T> {
+    fn get(&self, pos: Pos) -> Option<&T>;
+    fn get_mut(&mut self, pos: Pos) -> Option<&mut T>;
+    /// Set the tile at a position. Dense storage panics if the position is
+    /// out of bounds.
+    fn insert(&mut self, pos: Pos, tile: T);
+    /// The bounds of the tiles, or `None` if there are none.
+    fn bounds(&self) -> Option<Bounds>;
+    /// The tiles with their positions, in reading order.
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = (Pos, &'a T)> + 'a>;
+}
+
+/// Tiles for every position of a rectangle with its top-left corner at the
+/// origin, stored row by row.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Dense<T> {
+    width: usize,
+    height: usize,
+    tiles: Vec<T>,
+}
+
+impl<T> Dense<T> {
+    fn index(&self, pos: Pos) -> Option<usize> {
+        if pos.x < 0
+            || pos.y < 0
+            || pos.x as usize >= self.width
+            || pos.y as usize >= self.height
+        {
+            None
+        } else {
+            Some(pos.y as usize * self.width + pos.x as usize)
+        }
+    }
+}
+
+impl<T> Storage<T> for Dense<T> {
+    fn get(&self, pos: Pos) -> Option<&T> {
+        self.index(pos).map(|idx| &self.tiles[idx])
+    }
+
+    fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        self.index(pos).map(move |idx| &mut self.tiles[idx])
+    }
+
+    fn insert(&mut self, pos: Pos, tile: T) {
+        let idx = self
+            .index(pos)
+            .unwrap_or_else(|| panic!("{:?} is outside the grid", pos));
+        self.tiles[idx] = tile;
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        if self.tiles.is_empty() {
+            None
+        } else {
+            Some(Bounds {
+                min: Pos::new(0, 0),
+                max: Pos::new(self.width as i32 - 1, self.height as i32 - 1),
+            })
+        }
+    }
+
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = (Pos, &'a T)> + 'a> {
+        let width = self.width;
+        Box::new(self.tiles.iter().enumerate().map(move |(idx, tile)| {
+            (Pos::new((idx % width) as i32, (idx / width) as i32), tile)
+        }))
+    }
+}
+
+/// Tiles for some positions only, which can lie anywhere.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sparse<T> {
+    tiles: HashMap<Pos, T>,
+    bounds: Option<Bounds>,
+}
+
+impl<T> Storage<T> for Sparse<T> {
+    fn get(&self, pos: Pos) -> Option<&T> {
+        self.tiles.get(&pos)
+    }
+
+    fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        self.tiles.get_mut(&pos)
+    }
+
+    fn insert(&mut self, pos: Pos, tile: T) {
+        self.bounds = Some(match self.bounds {
+            Some(bounds) => bounds.extend(pos),
+            None => Bounds { min: pos, max: pos },
+        });
+        self.tiles.insert(pos, tile);
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = (Pos, &'a T)> + 'a> {
+        let mut tiles = self
+            .tiles
+            .iter()
+            .map(|(&pos, tile)| (pos, tile))
+            .collect::<Vec<_>>();
+        tiles.sort_by_key(|&(pos, _)| pos);
+        Box::new(tiles.into_iter())
+    }
+}
+
+/// A grid of tiles, dense unless sparse storage is asked for.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Grid<T, S = Dense<T>> {
+    storage: S,
+    tile: std::marker::PhantomData<T>,
+}
+
+pub type SparseGrid<T> = Grid<T, Sparse<T>>;
+
+impl<T: Clone> Grid<T> {
+    /// A dense grid with every tile set to `tile`.
+    pub fn filled(width: usize, height: usize, tile: T) -> Grid<T> {
+        Grid::from_storage(Dense {
+            width,
+            height,
+            tiles: vec![tile; width * height],
+        })
+    }
+}
+
+impl<T> Grid<T> {
+    /// Parse a dense grid from rows of characters, mapping every character to
+    /// a tile, or an error for characters that are not expected. Rows shorter
+    /// than the longest one are padded with spaces.
+    pub fn parse_with<F>(lines: &[&str], mut tile: F) -> Result<Grid<T>>
+    where
+        F: FnMut(Pos, char) -> Result<T>,
+    {
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut tiles = Vec::with_capacity(width * lines.len());
+
+        for (y, line) in lines.iter().enumerate() {
+            let padding =
+                std::iter::repeat_n(' ', width - line.chars().count());
+            for (x, c) in line.chars().chain(padding).enumerate() {
+                tiles.push(tile(Pos::new(x as i32, y as i32), c)?);
+            }
+        }
+
+        Ok(Grid::from_storage(Dense {
+            width,
+            height: lines.len(),
+            tiles,
+        }))
+    }
+
+    /// Parse a dense grid from rows of characters, with a mapping from
+    /// characters to tiles that returns `None` for unexpected characters.
+    pub fn parse<F>(lines: &[&str], mut tile: F) -> Result<Grid<T>>
+    where
+        F: FnMut(char) -> Option<T>,
+    {
+        Grid::parse_with(lines, |pos, c| {
+            tile(c).ok_or_else(|| unexpected(pos, c))
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.storage.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.storage.height
+    }
+}
+
+impl<T> SparseGrid<T> {
+    /// An empty sparse grid.
+    pub fn sparse() -> SparseGrid<T> {
+        Grid::from_storage(Sparse {
+            tiles: HashMap::new(),
+            bounds: None,
+        })
+    }
+
+    /// The number of positions with a tile.
+    pub fn len(&self) -> usize {
+        self.storage.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.storage.tiles.is_empty()
+    }
+}
+
+/// The error for an unexpected character at a position of a grid.
+pub fn unexpected(pos: Pos, c: char) -> Error {
+    Error::input(
+        pos.y as usize,
+        pos.x as usize,
+        format!("unexpected `{}`", c),
+    )
+}
+
+impl<T, S: Storage<T>> Grid<T, S> {
+    fn from_storage(storage: S) -> Grid<T, S> {
+        Grid {
+            storage,
+            tile: std::marker::PhantomData,
+        }
+    }
+
+    pub fn get(&self, pos: Pos) -> Option<&T> {
+        self.storage.get(pos)
+    }
+
+    pub fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        self.storage.get_mut(pos)
+    }
+
+    /// Set the tile at a position. Dense grids panic if the position is out
+    /// of bounds.
+    pub fn insert(&mut self, pos: Pos, tile: T) {
+        self.storage.insert(pos, tile)
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.storage.bounds()
+    }
+
+    /// Whether the position lies within the bounds of the grid.
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.bounds().is_some_and(|bounds| bounds.contains(pos))
+    }
+
+    /// The tiles with their positions, in reading order.
+    pub fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = (Pos, &'a T)> + 'a> {
+        self.storage.iter()
+    }
+
+    /// The orthogonal neighbours of a position that lie within the bounds of
+    /// the grid, in reading order.
+    pub fn neighbours4(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        let bounds = self.bounds();
+        IntoIterator::into_iter(pos.neighbours4())
+            .filter(move |&p| bounds.is_some_and(|bounds| bounds.contains(p)))
+    }
+
+    /// The orthogonal and diagonal neighbours of a position that lie within
+    /// the bounds of the grid, in reading order.
+    pub fn neighbours8(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        let bounds = self.bounds();
+        IntoIterator::into_iter(pos.neighbours8())
+            .filter(move |&p| bounds.is_some_and(|bounds| bounds.contains(p)))
+    }
+
+    /// Draw the grid within its bounds, a line per row. Positions without a
+    /// tile are passed `None`.
+    pub fn render<F>(&self, mut draw: F) -> String
+    where
+        F: FnMut(Pos, Option<&T>) -> char,
+    {
+        let mut s = String::new();
+        if let Some(Bounds { min, max }) = self.bounds() {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let pos = Pos::new(x, y);
+                    s.push(draw(pos, self.get(pos)));
+                }
+                s.push('\n');
+            }
+        }
+        s
+    }
+}
+
+impl<T, S: Storage<T>> Index<Pos> for Grid<T, S> {
+    type Output = T;
+
+    fn index(&self, pos: Pos) -> &T {
+        self.get(pos)
+            .unwrap_or_else(|| panic!("no tile at {:?}", pos))
+    }
+}
+
+impl<T, S: Storage<T>> IndexMut<Pos> for Grid<T, S> {
+    fn index_mut(&mut self, pos: Pos) -> &mut T {
+        self.get_mut(pos)
+            .unwrap_or_else(|| panic!("no tile at {:?}", pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &str) -> Vec<&str> {
+        s.lines().collect()
+    }
+
+    #[test]
+    fn parse_and_render() {
+        let grid = Grid::parse(&lines("#.#\n.#"), |c| match c {
+            '#' => Some(true),
+            '.' | ' ' => Some(false),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 2);
+        assert!(grid[Pos::new(1, 1)]);
+        assert_eq!(grid.get(Pos::new(3, 0)), None);
+        assert_eq!(
+            grid.render(|_, t| if t == Some(&true) { '#' } else { '.' }),
+            "#.#\n.#.\n"
+        );
+
+        let err = Grid::parse(&lines("##\n#x"), |c| {
+            if c == '#' {
+                Some(())
+            } else {
+                None
+            }
+        });
+        assert_eq!(err.unwrap_err().to_string(), "<input>:2:2: unexpected `x`");
+    }
+
+    #[test]
+    fn neighbours() {
+        let grid = Grid::filled(3, 3, 0);
+        assert_eq!(
+            grid.neighbours4(Pos::new(0, 0)).collect::<Vec<_>>(),
+            vec![Pos::new(1, 0), Pos::new(0, 1)]
+        );
+        assert_eq!(grid.neighbours4(Pos::new(1, 1)).count(), 4);
+        assert_eq!(grid.neighbours8(Pos::new(1, 1)).count(), 8);
+        assert_eq!(grid.neighbours8(Pos::new(2, 2)).count(), 3);
+    }
+
+    #[test]
+    fn sparse() {
+        let mut grid = SparseGrid::sparse();
+        grid.insert(Pos::new(5, -1), 'a');
+        grid.insert(Pos::new(3, 1), 'b');
+        grid.insert(Pos::new(4, -1), 'c');
+        assert_eq!(
+            grid.bounds(),
+            Some(Bounds {
+                min: Pos::new(3, -1),
+                max: Pos::new(5, 1)
+            })
+        );
+        assert_eq!(grid.iter().map(|(_, &t)| t).collect::<String>(), "cab");
+        assert_eq!(grid.render(|_, t| *t.unwrap_or(&' ')), " ca\n   \nb  \n");
+    }
+}
@@ -0,0 +1,235 @@
+//! Puzzle inputs: read from a file or stdin, or embedded in the binary at
+//! compile time, with line endings and trailing blank lines normalised. What
+//! an input is parsed into is cached with it, so that it is parsed once.
+use days;
+use error::Error;
+use solution::{Result, Solution};
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// The inputs in the input directory, so the binary can run anywhere.
+static EMBEDDED: [&str; 25] = [
+    include_str!("../input/december01.txt"),
+    include_str!("../input/december02.txt"),
+    include_str!("../input/december03.txt"),
+    include_str!("../input/december04.txt"),
+    include_str!("../input/december05.txt"),
+    include_str!("../input/december06.txt"),
+    include_str!("../input/december07.txt"),
+    include_str!("../input/december08.txt"),
+    include_str!("../input/december09.txt"),
+    include_str!("../input/december10.txt"),
+    include_str!("../input/december11.txt"),
+    include_str!("../input/december12.txt"),
+    include_str!("../input/december13.txt"),
+    include_str!("../input/december14.txt"),
+    include_str!("../input/december15.txt"),
+    include_str!("../input/december16.txt"),
+    include_str!("../input/december17.txt"),
+    include_str!("../input/december18.txt"),
+    include_str!("../input/december19.txt"),
+    include_str!("../input/december20.txt"),
+    include_str!("../input/december21.txt"),
+    include_str!("../input/december22.txt"),
+    include_str!("../input/december23.txt"),
+    include_str!("../input/december24.txt"),
+    include_str!("../input/december25.txt"),
+];
+
+/// The text of an input, with `\n` line endings and without trailing blank
+/// lines, and the name to report errors in it under.
+#[derive(Clone)]
+pub struct Input {
+    name: String,
+    text: Cow<'static, str>,
+    /// The text parsed by the solutions it was parsed for, each with how
+    /// long parsing took, by the type of the solution. Clones share them.
+    parsed: RefCell<HashMap<TypeId, Rc<dyn Any>>>,
+}
+
+/// Normalise line endings and strip trailing blank lines, only copying the
+/// text if it changes.
+fn normalize<T>(text: T) -> Cow<'static, str>
+where
+    T: Into<Cow<'static, str>>,
+{
+    let mut text = text.into();
+    if text.contains('\r') {
+        text = Cow::Owned(text.replace("\r\n", "\n"));
+    }
+
+    let len = text.trim_end_matches('\n').len();
+    if len < text.len() {
+        match text {
+            Cow::Borrowed(s) => text = Cow::Borrowed(&s[..len]),
+            Cow::Owned(ref mut s) => s.truncate(len),
+        }
+    }
+    text
+}
+
+impl Input {
+    pub fn new<N, T>(name: N, text: T) -> Input
+    where
+        N: Into<String>,
+        T: Into<Cow<'static, str>>,
+    {
+        Input {
+            name: name.into(),
+            text: normalize(text),
+            parsed: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Input> {
+        let path = path.as_ref();
+        Ok(Input::new(
+            path.display().to_string(),
+            fs::read_to_string(path)?,
+        ))
+    }
+
+    pub fn from_stdin() -> Result<Input> {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(Input::new("<stdin>", text))
+    }
+
+    /// The input of a day as it was when the binary was compiled.
+    pub fn embedded(day: u32) -> Option<Input> {
+        let text = EMBEDDED.get((day as usize).checked_sub(1)?)?;
+        Some(Input::new(
+            format!("{} (embedded)", days::input_path(day)),
+            *text,
+        ))
+    }
+
+    /// Read the input of a day from the given path, stdin (`-`), or the
+    /// day's input file, falling back to the embedded input if there is no
+    /// such file.
+    pub fn for_day(day: u32, source: Option<&str>) -> Result<Input> {
+        match source {
+            Some("-") => Input::from_stdin(),
+            Some(path) => Input::from_path(path),
+            None => match Input::from_path(days::input_path(day)) {
+                Err(Error::Io(ref err))
+                    if err.kind() == io::ErrorKind::NotFound =>
+                {
+                    Input::embedded(day).ok_or_else(|| {
+                        Error::Param(format!(
+                            "There is no puzzle on day {}",
+                            day
+                        ))
+                    })
+                }
+                input => input,
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The whole text.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The lines of the text, borrowed from it.
+    pub fn lines(&self) -> Vec<&str> {
+        self.text.lines().collect()
+    }
+
+    /// The text parsed as the input of solution `S`, and how long parsing it
+    /// took. Only the first call parses it; later ones share what it was
+    /// parsed into, so that solving, rendering and cross-checking a day on
+    /// the same input parse it once. Errors are not cached.
+    pub fn parsed<S>(&self) -> Result<(Rc<S::Input>, Duration)>
+    where
+        S: Solution + 'static,
+    {
+        let key = TypeId::of::<S>();
+        if let Some(parsed) = self.parsed.borrow().get(&key) {
+            let parsed = parsed
+                .downcast_ref::<(Rc<S::Input>, Duration)>()
+                .expect("inputs are cached by the type of their solution");
+            return Ok(parsed.clone());
+        }
+
+        let start = Instant::now();
+        let parsed = (Rc::new(S::parse(&self.lines())?), start.elapsed());
+        self.parsed
+            .borrow_mut()
+            .insert(key, Rc::new(parsed.clone()));
+        Ok(parsed)
+    }
+}
+
+impl fmt::Debug for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Input")
+            .field("name", &self.name)
+            .field("text", &self.text)
+            .finish()
+    }
+}
+
+/// Inputs are equal if their names and texts are, whatever they were parsed
+/// into so far.
+impl PartialEq for Input {
+    fn eq(&self, other: &Input) -> bool {
+        self.name == other.name && self.text == other.text
+    }
+}
+
+impl Eq for Input {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use december03::December03;
+
+    #[test]
+    fn normalization() {
+        let input = Input::new("test", "a\r\n\r\nb\r\n\n\n");
+        assert_eq!(input.as_str(), "a\n\nb");
+        assert_eq!(input.lines(), vec!["a", "", "b"]);
+        assert!(Input::new("empty", "\n").lines().is_empty());
+
+        match Input::new("test", "a\nb\n").text {
+            Cow::Borrowed(text) => assert_eq!(text, "a\nb"),
+            Cow::Owned(_) => panic!("the text was copied"),
+        }
+    }
+
+    /// What an input is parsed into is cached, and shared by its clones.
+    #[test]
+    fn parsed() {
+        let input = Input::new("claims", "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n");
+        let (claims, _) = input.parsed::<December03>().unwrap();
+        assert_eq!(claims.len(), 2);
+        let (again, _) = input.clone().parsed::<December03>().unwrap();
+        assert!(Rc::ptr_eq(&claims, &again));
+
+        assert!(Input::new("bad", "#1").parsed::<December03>().is_err());
+    }
+
+    #[test]
+    fn embedded() {
+        assert_eq!(
+            Input::embedded(1).unwrap().name(),
+            "input/december01.txt (embedded)"
+        );
+        assert!(Input::embedded(0).is_none());
+        assert!(Input::embedded(26).is_none());
+    }
+}
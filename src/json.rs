@@ -0,0 +1,177 @@
+//! A minimal JSON writer for machine-readable output.
+use solution::{Answers, Timings};
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// A JSON value. Objects keep their keys in the order they were given.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// An object from key/value pairs.
+    pub fn object<K: Into<String>>(pairs: Vec<(K, Json)>) -> Json {
+        Json::Object(pairs.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    pub fn string<S: Into<String>>(s: S) -> Json {
+        Json::String(s.into())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        value.map(Into::into).unwrap_or(Json::Null)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Json {
+        Json::Bool(value)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Json {
+        Json::Number(value)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Json {
+        Json::Number(f64::from(value))
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Json {
+        Json::String(value)
+    }
+}
+
+impl<'a> From<&'a str> for Json {
+    fn from(value: &'a str) -> Json {
+        Json::String(value.to_owned())
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Json {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Writes compact JSON on a single line.
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            // JSON has no infinities or NaN.
+            Json::Number(n) if !n.is_finite() => f.write_str("null"),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                f.write_str("[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(pairs) => {
+                f.write_str("{")?;
+                for (idx, (key, value)) in pairs.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn millis(duration: Duration) -> Json {
+    Json::Number(duration.as_secs_f64() * 1000.0)
+}
+
+/// The document describing the answers of a day: the time taken to parse the
+/// input, the answer and time taken of every part that was solved, and the
+/// diagnostics reported while solving.
+pub fn answers(day: u32, answers: &Answers, timings: &Timings) -> Json {
+    let mut parts = Vec::new();
+    for (part, answer, elapsed) in &[
+        (1, &answers.part1, timings.part1),
+        (2, &answers.part2, timings.part2),
+    ] {
+        if let Some(answer) = answer {
+            parts.push(Json::object(vec![
+                ("part", Json::from(*part as u32)),
+                ("answer", Json::from(answer.as_str())),
+                ("elapsed_ms", elapsed.map(millis).unwrap_or(Json::Null)),
+            ]));
+        }
+    }
+
+    Json::object(vec![
+        ("day", Json::from(day)),
+        ("parse_elapsed_ms", millis(timings.parse)),
+        ("parts", Json::Array(parts)),
+        ("diagnostics", Json::from(answers.diagnostics.clone())),
+    ])
+}
+
+/// The document describing a day that could not be solved.
+pub fn error<E: Display>(day: u32, error: &E) -> Json {
+    Json::object(vec![
+        ("day", Json::from(day)),
+        ("error", Json::from(error.to_string())),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let json = Json::object(vec![
+            ("day", Json::from(10u32)),
+            ("answer", Json::from("#  #\n\"HI\"")),
+            ("elapsed", Json::from(1.5)),
+            ("skipped", Json::from(None::<String>)),
+            ("diagnostics", Json::from(vec!["a\tb"])),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            r##"{"day":10,"answer":"#  #\n\"HI\"","elapsed":1.5,"skipped":null,"diagnostics":["a\tb"]}"##
+        );
+    }
+}
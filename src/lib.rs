@@ -0,0 +1,61 @@
+//! Solutions to the Advent of Code 2018 programming puzzles.
+//!
+//! Every day is a module implementing the shared [`Solution`] trait, so the
+//! solvers can be called from other code as well as from the `aoc` binary.
+//!
+//! [`Solution`]: solution/trait.Solution.html
+extern crate euclid;
+#[macro_use]
+extern crate maplit;
+
+pub mod bench;
+pub mod days;
+pub mod debugger;
+pub mod decompile;
+pub mod disasm;
+pub mod elfcode;
+pub mod error;
+pub mod generate;
+pub mod grid;
+pub mod input;
+pub mod json;
+pub mod optimise;
+pub mod parse;
+pub mod pool;
+pub mod profile;
+pub mod record;
+pub mod render;
+pub mod server;
+pub mod solution;
+pub mod store;
+pub mod trace;
+pub mod verify;
+pub mod view;
+
+pub mod december01;
+pub mod december02;
+pub mod december03;
+pub mod december04;
+pub mod december05;
+pub mod december06;
+pub mod december07;
+pub mod december08;
+pub mod december09;
+pub mod december10;
+pub mod december11;
+pub mod december12;
+pub mod december13;
+pub mod december14;
+pub mod december15;
+pub mod december16;
+pub mod december17;
+pub mod december18;
+pub mod december19;
+pub mod december20;
+pub mod december21;
+pub mod december22;
+pub mod december23;
+pub mod december24;
+pub mod december25;
+
+pub use solution::Solution;
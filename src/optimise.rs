@@ -0,0 +1,726 @@
+//! An optimising interpreter for ElfCode programs. Programs are compiled
+//! before they run: operands are resolved to the registers or values they
+//! stand for, reads of the instruction pointer become the index of the
+//! instruction, and writes of a constant to it become jumps to a target known
+//! in advance. Loops of well-known shapes, such as the divisor sum of day 19
+//! and the division by 256 of day 21, are recognised, and run natively in a
+//! few steps rather than millions of instructions.
+use elfcode::{Fault, Machine, OpCode, Operand, Program, Word};
+use solution;
+use std::collections::BTreeMap;
+
+/// The sum of divisors of day 19, which tries every product of two numbers
+/// up to `n`. Lowercase letters stand for distinct registers other than the
+/// instruction pointer, `ip` for the instruction pointer, and `_` for any
+/// operand. `goto N` is a jump to the Nth instruction of the loop.
+static DIVISOR_SUM: &[&str] = &[
+    "seti 1 _ j",
+    "mulr i j t",
+    "eqrr t n t",
+    "addr t ip ip",
+    "goto 6",
+    "addr i s s",
+    "addi j 1 j",
+    "gtrr j n t",
+    "addr t ip ip",
+    "goto 1",
+    "addi i 1 i",
+    "gtrr i n t",
+    "addr t ip ip",
+    "goto 0",
+];
+
+/// The division of day 21, which counts up until one more times the divisor
+/// is larger than the dividend. Uppercase letters stand for immediate values,
+/// and `goto _` for a jump anywhere: out of the loop.
+static DIVIDE: &[&str] = &[
+    "seti 0 _ q",
+    "addi q 1 t",
+    "muli t K t",
+    "gtrr t x t",
+    "addr t ip ip",
+    "goto 7",
+    "goto _",
+    "addi q 1 q",
+    "goto 1",
+];
+
+/// The instruction of `DIVIDE` that leaves the loop.
+const DIVIDE_EXIT: usize = 6;
+
+/// A loop the interpreter recognises, by the registers it works on.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Idiom<W> {
+    /// Add every divisor of `number` from `divisor` on to `sum`, by trying
+    /// every product of `divisor` and `multiple` up to `number`: day 19.
+    DivisorSum {
+        divisor: usize,
+        multiple: usize,
+        product: usize,
+        number: usize,
+        sum: usize,
+    },
+    /// Set `quotient` to `dividend` divided by `divisor`, by counting it up
+    /// until `product`, one more than it times the divisor, is larger than
+    /// the dividend: day 21.
+    Divide {
+        quotient: usize,
+        product: usize,
+        dividend: usize,
+        divisor: W,
+    },
+}
+
+impl<W: Word> Idiom<W> {
+    /// Run the loop on the registers, unless its registers are such that the
+    /// loop would not end as usual, or its products would overflow. Then it
+    /// has to be run instruction by instruction instead.
+    fn run(&self, r: &mut [W]) -> bool {
+        let zero = W::default();
+        let one = W::from_index(1);
+        match *self {
+            Idiom::DivisorSum {
+                divisor,
+                multiple,
+                product,
+                number,
+                sum,
+            } => {
+                let (first, n) = (r[divisor], r[number]);
+                if first < one || first > n || n.checked_mul(n).is_none() {
+                    return false;
+                }
+                let mut total = r[sum];
+                let mut d = one;
+                while d.wrapping_mul(d) <= n {
+                    if n % d == zero {
+                        let other = n / d;
+                        if d >= first {
+                            total = total.wrapping_add(d);
+                        }
+                        if other != d && other >= first {
+                            total = total.wrapping_add(other);
+                        }
+                    }
+                    d = d.wrapping_add(one);
+                }
+                let last = n.wrapping_add(one);
+                r[sum] = total;
+                r[divisor] = last;
+                r[multiple] = last;
+                r[product] = one;
+                true
+            }
+            Idiom::Divide {
+                quotient,
+                product,
+                dividend,
+                divisor,
+            } => {
+                let x = r[dividend];
+                if divisor < one || x.checked_add(divisor).is_none() {
+                    return false;
+                }
+                r[quotient] = if x < zero { zero } else { x / divisor };
+                r[product] = one;
+                true
+            }
+        }
+    }
+}
+
+/// An operand of a compiled instruction.
+#[derive(Clone, Copy, Debug)]
+enum Source<W> {
+    Register(usize),
+    Value(W),
+}
+
+impl<W: Word> Source<W> {
+    fn read(self, r: &[W]) -> W {
+        match self {
+            Source::Register(register) => r[register],
+            Source::Value(value) => value,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Op<W> {
+    /// An instruction that leaves the instruction pointer alone.
+    Set {
+        op: OpCode,
+        a: Source<W>,
+        b: Source<W>,
+        c: usize,
+    },
+    /// A write of a constant to the instruction pointer, which then points
+    /// at `next`: the instruction `target`, or past the end of the program.
+    Goto { value: W, next: W, target: usize },
+    /// Any other write to the instruction pointer.
+    Jump {
+        op: OpCode,
+        a: Source<W>,
+        b: Source<W>,
+    },
+    /// An instruction that names a register the machine does not have.
+    Fault(Fault<W>),
+    /// The first instruction of a loop that is run natively, and left for
+    /// the instruction `exit`. The instruction itself is run instead if the
+    /// loop cannot be.
+    Idiom {
+        idiom: Idiom<W>,
+        exit: usize,
+        fallback: Box<Op<W>>,
+    },
+}
+
+/// The registers and immediate values that the letters of a template stand
+/// for.
+#[derive(Clone)]
+struct Bindings<W> {
+    registers: BTreeMap<char, usize>,
+    values: BTreeMap<char, W>,
+}
+
+impl<W: Word> Bindings<W> {
+    fn register(&mut self, name: char, register: usize) -> bool {
+        match self.registers.get(&name) {
+            Some(&bound) => bound == register,
+            None if self.registers.values().any(|&r| r == register) => false,
+            None => {
+                self.registers.insert(name, register);
+                true
+            }
+        }
+    }
+
+    fn value(&mut self, name: char, value: W) -> bool {
+        *self.values.entry(name).or_insert(value) == value
+    }
+
+    /// Whether an operand is what the word of a template stands for.
+    fn operand(
+        &mut self,
+        word: &str,
+        operand: Operand,
+        value: W,
+        ip: usize,
+    ) -> bool {
+        let name = word.chars().next().filter(|_| word.len() == 1);
+        match (word, operand, name) {
+            ("_", _, _) => true,
+            ("ip", Operand::Register, _) => value.to_index() == Some(ip),
+            (_, Operand::Register, Some(name)) if name.is_lowercase() => {
+                match value.to_index() {
+                    Some(register) if register != ip => {
+                        self.register(name, register)
+                    }
+                    _ => false,
+                }
+            }
+            (_, Operand::Immediate, Some(name)) if name.is_uppercase() => {
+                self.value(name, value)
+            }
+            (_, Operand::Immediate, _) => {
+                word.parse::<usize>().ok() == value.to_index()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The instruction an instruction pointer points at, or the number of
+/// instructions if it is outside the program.
+fn target<W: Word>(ip: W, len: usize) -> usize {
+    ip.to_index().map_or(len, |target| target.min(len))
+}
+
+fn commutative(op: OpCode) -> bool {
+    matches!(
+        op,
+        OpCode::Addr
+            | OpCode::Mulr
+            | OpCode::Banr
+            | OpCode::Borr
+            | OpCode::Eqrr
+    )
+}
+
+/// A program compiled for a machine with a number of registers.
+#[derive(Clone, Debug)]
+pub struct Compiled<W> {
+    ops: Vec<Op<W>>,
+    ip_register: Option<usize>,
+    registers: usize,
+}
+
+impl<W: Word> Compiled<W> {
+    pub fn new(
+        program: &Program<W>,
+        registers: usize,
+    ) -> Result<Compiled<W>, Fault<W>> {
+        if let Some(register) = program.ip_register {
+            if register >= registers {
+                return Err(Fault::BadRegister {
+                    ip: W::default(),
+                    register: W::from_index(register),
+                });
+            }
+        }
+        let mut compiled = Compiled {
+            ops: Vec::new(),
+            ip_register: program.ip_register,
+            registers,
+        };
+        compiled.ops = (0..program.instrs.len())
+            .map(|idx| compiled.compile(program, idx))
+            .collect();
+        for start in 0..program.instrs.len() {
+            if let Some((idiom, exit)) = compiled.idiom(program, start) {
+                let fallback = Box::new(compiled.ops[start].clone());
+                compiled.ops[start] = Op::Idiom {
+                    idiom,
+                    exit,
+                    fallback,
+                };
+            }
+        }
+        Ok(compiled)
+    }
+
+    fn compile(&self, program: &Program<W>, idx: usize) -> Op<W> {
+        let instr = program.instrs[idx];
+        let at = W::from_index(idx);
+        let register = |r: W| {
+            r.to_index().filter(|&r| r < self.registers).ok_or(
+                Fault::BadRegister {
+                    ip: at,
+                    register: r,
+                },
+            )
+        };
+        let source = |operand, x: W| match operand {
+            Operand::Register if self.ip_register == register(x).ok() => {
+                Ok(Source::Value(at))
+            }
+            Operand::Register => register(x).map(Source::Register),
+            _ => Ok(Source::Value(x)),
+        };
+
+        let (a, b) = instr.op.operands();
+        let operands = source(a, instr.a).and_then(|a| {
+            let b = source(b, instr.b)?;
+            Ok((a, b, register(instr.c)?))
+        });
+        let (a, b, c) = match operands {
+            Ok(operands) => operands,
+            Err(fault) => return Op::Fault(fault),
+        };
+        if self.ip_register != Some(c) {
+            return Op::Set {
+                op: instr.op,
+                a,
+                b,
+                c,
+            };
+        }
+        match (a, b) {
+            (Source::Value(a), Source::Value(b)) => {
+                let value = instr.op.apply(a, b);
+                let next = value.wrapping_add(W::from_index(1));
+                Op::Goto {
+                    value,
+                    next,
+                    target: target(next, program.instrs.len()),
+                }
+            }
+            _ => Op::Jump { op: instr.op, a, b },
+        }
+    }
+
+    /// Whether the instructions from `start` on are a loop of the template.
+    fn matches(
+        &self,
+        program: &Program<W>,
+        start: usize,
+        template: &[&str],
+    ) -> Option<Bindings<W>> {
+        let ip = self.ip_register?;
+        if start + template.len() > self.ops.len() {
+            return None;
+        }
+        let mut bindings = Bindings {
+            registers: BTreeMap::new(),
+            values: BTreeMap::new(),
+        };
+        for (offset, line) in template.iter().enumerate() {
+            let idx = start + offset;
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            if words[0] == "goto" {
+                match self.ops[idx] {
+                    Op::Goto { target, .. }
+                        if words[1] == "_"
+                            || words[1].parse::<usize>().ok()
+                                == Some(target.wrapping_sub(start)) => {}
+                    _ => return None,
+                }
+                continue;
+            }
+            if let Op::Fault(_) = self.ops[idx] {
+                return None;
+            }
+
+            let instr = program.instrs[idx];
+            if instr.op.mnemonic() != words[0] {
+                return None;
+            }
+            let (a, b) = instr.op.operands();
+            let mut orders = vec![(instr.a, instr.b)];
+            if commutative(instr.op) {
+                orders.push((instr.b, instr.a));
+            }
+            bindings = orders.into_iter().find_map(|(x, y)| {
+                let mut bound = bindings.clone();
+                let matched = bound.operand(words[1], a, x, ip)
+                    && bound.operand(words[2], b, y, ip)
+                    && bound.operand(words[3], Operand::Register, instr.c, ip);
+                if matched {
+                    Some(bound)
+                } else {
+                    None
+                }
+            })?;
+        }
+        Some(bindings)
+    }
+
+    /// The loop that starts at `start`, if it is one the interpreter
+    /// recognises, and the instruction it is left for.
+    fn idiom(
+        &self,
+        program: &Program<W>,
+        start: usize,
+    ) -> Option<(Idiom<W>, usize)> {
+        if let Some(bindings) = self.matches(program, start, DIVISOR_SUM) {
+            let r = &bindings.registers;
+            let idiom = Idiom::DivisorSum {
+                divisor: r[&'i'],
+                multiple: r[&'j'],
+                product: r[&'t'],
+                number: r[&'n'],
+                sum: r[&'s'],
+            };
+            return Some((idiom, start + DIVISOR_SUM.len()));
+        }
+        if let Some(bindings) = self.matches(program, start, DIVIDE) {
+            let r = &bindings.registers;
+            let idiom = Idiom::Divide {
+                quotient: r[&'q'],
+                product: r[&'t'],
+                dividend: r[&'x'],
+                divisor: bindings.values[&'K'],
+            };
+            return Some((idiom, start + DIVIDE_EXIT));
+        }
+        None
+    }
+
+    /// The loops that are run natively, by the instruction they start at.
+    pub fn idioms(&self) -> Vec<(usize, Idiom<W>)> {
+        self.ops
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, op)| match *op {
+                Op::Idiom { idiom, .. } => Some((idx, idiom)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Run an instruction on the registers, returning the instruction
+    /// pointer after it and the instruction that points at.
+    fn execute(
+        &self,
+        op: &Op<W>,
+        idx: usize,
+        r: &mut [W],
+    ) -> Result<(W, usize), Fault<W>> {
+        let at = W::from_index(idx);
+        match *op {
+            Op::Set { op, a, b, c } => {
+                if let Some(ip) = self.ip_register {
+                    r[ip] = at;
+                }
+                r[c] = op.apply(a.read(r), b.read(r));
+                Ok((W::from_index(idx + 1), idx + 1))
+            }
+            Op::Goto {
+                value,
+                next,
+                target,
+            } => {
+                r[self.ip_register.expect("jumps have an ip register")] = value;
+                Ok((next, target))
+            }
+            Op::Jump { op, a, b } => {
+                let value = op.apply(a.read(r), b.read(r));
+                r[self.ip_register.expect("jumps have an ip register")] = value;
+                let next = value.wrapping_add(W::from_index(1));
+                Ok((next, target(next, self.ops.len())))
+            }
+            Op::Fault(fault) => {
+                if let Some(ip) = self.ip_register {
+                    r[ip] = at;
+                }
+                Err(fault)
+            }
+            Op::Idiom {
+                ref idiom,
+                exit,
+                ref fallback,
+            } => {
+                if !idiom.run(r) {
+                    return self.execute(fallback, idx, r);
+                }
+                // Every loop is left by adding 1 to the instruction pointer
+                // before the exit.
+                r[self.ip_register.expect("loops have an ip register")] =
+                    W::from_index(exit - 1);
+                Ok((W::from_index(exit), exit.min(self.ops.len())))
+            }
+        }
+    }
+
+    /// Run the program on a machine, which must have the number of
+    /// registers it was compiled for and its instruction pointer bound as
+    /// the program asks, until it halts. Returns the number of steps taken,
+    /// where a loop run natively is a single step.
+    pub fn run(&self, machine: &mut Machine<W>) -> Result<u64, Fault<W>> {
+        self.run_until(machine, |_| false)
+    }
+
+    /// Run the program until it halts or `stop` returns true before a step,
+    /// like `Machine::run_until`. `stop` is not asked within loops that run
+    /// natively.
+    pub fn run_until<F>(
+        &self,
+        machine: &mut Machine<W>,
+        mut stop: F,
+    ) -> Result<u64, Fault<W>>
+    where
+        F: FnMut(&Machine<W>) -> bool,
+    {
+        assert_eq!(machine.registers.len(), self.registers);
+        assert_eq!(machine.ip_register(), self.ip_register);
+        let len = self.ops.len();
+        let mut idx = target(machine.ip, len);
+        let mut steps = 0;
+        while idx < len && !stop(machine) {
+            let (ip, next) =
+                self.execute(&self.ops[idx], idx, &mut machine.registers)?;
+            machine.ip = ip;
+            idx = next;
+            steps += 1;
+            // Programs can run for a long time, or forever.
+            if steps % 0x10000 == 0 {
+                solution::checkpoint();
+            }
+        }
+        Ok(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use days;
+    use generate::{self, Options};
+    use input::Input;
+
+    fn program<W>(lines: &[&str]) -> Program<W>
+    where
+        W: Word + ::std::str::FromStr,
+        W::Err: ::std::fmt::Display,
+    {
+        Program::parse(lines, 6).unwrap()
+    }
+
+    fn machine<W: Word>(program: &Program<W>, registers: &[W]) -> Machine<W> {
+        let machine = Machine::with_registers(registers.to_vec());
+        machine.bind_ip(program.ip_register.unwrap()).unwrap()
+    }
+
+    /// Run the program both on the machine and compiled, and check that they
+    /// end up the same. Returns the number of steps the compiled program
+    /// took.
+    fn agree<W: Word>(program: &Program<W>, registers: &[W]) -> u64 {
+        let mut expected = machine(program, registers);
+        let expected_result = expected.run(&program.instrs).map(|_| ());
+        let mut actual = machine(program, registers);
+        let result = Compiled::new(program, 6).unwrap().run(&mut actual);
+        assert_eq!(result.map(|_| ()), expected_result);
+        assert_eq!(actual, expected);
+        result.unwrap_or(0)
+    }
+
+    /// The loop of day 19 from instruction 0, with the jumps written
+    /// differently.
+    static DIVISOR_SUM_PROGRAM: &[&str] = &[
+        "#ip 3",
+        "seti 1 0 2",
+        "mulr 5 2 1",
+        "eqrr 1 4 1",
+        "addr 1 3 3",
+        "addi 3 1 3",
+        "addr 5 0 0",
+        "addi 2 1 2",
+        "gtrr 2 4 1",
+        "addr 3 1 3",
+        "seti 0 0 3",
+        "addi 5 1 5",
+        "gtrr 5 4 1",
+        "addr 1 3 3",
+        "seti -1 0 3",
+    ];
+
+    /// The loop of day 21 from instruction 0, which halts once it is done.
+    static DIVIDE_PROGRAM: &[&str] = &[
+        "#ip 2",
+        "seti 0 0 3",
+        "addi 3 1 1",
+        "muli 1 256 1",
+        "gtrr 1 4 1",
+        "addr 1 2 2",
+        "addi 2 1 2",
+        "seti 8 0 2",
+        "addi 3 1 3",
+        "seti 0 0 2",
+    ];
+
+    #[test]
+    fn idioms() {
+        let divisor_sum = Idiom::DivisorSum {
+            divisor: 5,
+            multiple: 2,
+            product: 1,
+            number: 4,
+            sum: 0,
+        };
+        let compiled =
+            Compiled::new(&program::<i64>(DIVISOR_SUM_PROGRAM), 6).unwrap();
+        assert_eq!(compiled.idioms(), [(0, divisor_sum)]);
+        let compiled =
+            Compiled::new(&program::<i64>(DIVIDE_PROGRAM), 6).unwrap();
+        let divide = Idiom::Divide {
+            quotient: 3,
+            product: 1,
+            dividend: 4,
+            divisor: 256,
+        };
+        assert_eq!(compiled.idioms(), [(0, divide)]);
+
+        let parse = |day| {
+            let input = Input::for_day(day, None).unwrap();
+            let program = days::program(day).unwrap()(&input.lines());
+            Compiled::new(&program.unwrap(), 6).unwrap()
+        };
+        assert_eq!(parse(19).idioms(), [(2, divisor_sum)]);
+        assert_eq!(parse(21).idioms(), [(17, divide)]);
+
+        // A loop whose registers are not distinct is not the same loop.
+        let mut lines = DIVIDE_PROGRAM.to_vec();
+        lines[4] = "gtrr 1 3 1";
+        assert!(Compiled::new(&program::<i64>(&lines), 6)
+            .unwrap()
+            .idioms()
+            .is_empty());
+    }
+
+    /// Part 2 of day 19 is only run on programs with the loop it sums the
+    /// divisors with.
+    #[test]
+    fn optimised() {
+        let divisor_sum = program::<i32>(DIVISOR_SUM_PROGRAM);
+        assert!(::december19::optimised(&divisor_sum, 0).is_ok());
+        let divide = program::<i32>(DIVIDE_PROGRAM);
+        assert!(::december19::optimised(&divide, 0).is_err());
+    }
+
+    #[test]
+    fn divisor_sum() {
+        let program = program::<i32>(DIVISOR_SUM_PROGRAM);
+        for &first in &[1, 3, 7, 12] {
+            let steps = agree(&program, &[100, 0, 0, 0, 12, first]);
+            assert_eq!(steps, 1);
+        }
+        // Loops that do not end as usual, or whose products overflow, are
+        // run instruction by instruction.
+        for &(n, first) in &[(12, 0), (12, 13), (0, 1), (50_000, 49_995)] {
+            let steps = agree(&program, &[0, 0, 0, 0, n, first]);
+            assert!(steps > 1);
+        }
+    }
+
+    #[test]
+    fn divide() {
+        let program = program::<i64>(DIVIDE_PROGRAM);
+        for &x in &[-5, 0, 255, 256, 1000, 3 * 65536 + 7] {
+            let steps = agree(&program, &[0, 0, 0, 0, x, 0]);
+            assert_eq!(steps, 2);
+        }
+    }
+
+    /// Registers that do not exist are only a fault once they are used.
+    #[test]
+    fn faults() {
+        let parse = |lines: &[&str]| Program::parse(lines, 10).unwrap();
+        let halts = parse(&["#ip 1", "seti 1 0 1", "seti 7 0 9"]);
+        agree::<i64>(&halts, &[0; 6]);
+        let faults = parse(&["#ip 1", "seti 0 0 1", "seti 7 0 9"]);
+        agree::<i64>(&faults, &[0; 6]);
+        assert!(Compiled::new(&faults, 1).is_err());
+    }
+
+    /// The puzzle inputs and generated inputs of day 19 run as on the
+    /// machine, and those of day 21 stop at instruction 28 with the same
+    /// registers.
+    #[test]
+    fn puzzles() {
+        let mut inputs = vec![Input::for_day(19, None).unwrap()];
+        inputs.extend((0..5).filter_map(|seed| {
+            generate::input(19, Options { seed, size: 10 })
+        }));
+        for input in &inputs {
+            let program = days::program(19).unwrap()(&input.lines()).unwrap();
+            agree(&program, &[0; 6]);
+        }
+
+        let mut inputs = vec![Input::for_day(21, None).unwrap()];
+        inputs.extend(
+            (0..5).filter_map(|seed| {
+                generate::input(21, Options { seed, size: 1 })
+            }),
+        );
+        for input in &inputs {
+            let program = days::program(21).unwrap()(&input.lines()).unwrap();
+            let mut expected = machine(&program, &[0; 6]);
+            let mut actual = expected.clone();
+            let compiled = Compiled::new(&program, 6).unwrap();
+            for _ in 0..3 {
+                expected.step(&program.instrs).unwrap();
+                expected
+                    .run_until(&program.instrs, |machine| machine.ip == 28)
+                    .unwrap();
+                let mut first = true;
+                compiled
+                    .run_until(&mut actual, |machine| {
+                        !std::mem::replace(&mut first, false)
+                            && machine.ip == 28
+                    })
+                    .unwrap();
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+}
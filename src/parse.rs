@@ -0,0 +1,421 @@
+//! Parsing puzzle inputs. A `Line` is a line of the input, or a part of one,
+//! that knows where it is: it can be split, have numbers and `key=<x,y>`
+//! vectors scanned out of it, and report errors at the line and column where
+//! the input went wrong. Inputs made of several parts are split into
+//! `Section`s at blank lines. Maps are parsed with `Grid::parse_with`.
+use error::{self, Error};
+use solution::Result;
+use std::convert::TryInto;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A part of a line of an input, by default all of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line<'a> {
+    /// The index of the line in the input.
+    pub idx: usize,
+    /// The whole line, which errors count columns in.
+    line: &'a str,
+    pub text: &'a str,
+}
+
+/// The lines of an input.
+pub fn lines<'a>(lines: &[&'a str]) -> Vec<Line<'a>> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(idx, &line)| Line::new(idx, line))
+        .collect()
+}
+
+/// The line with the given index, or an empty line past the end of the input
+/// if there is none.
+pub fn line<'a>(lines: &[&'a str], idx: usize) -> Line<'a> {
+    Line::new(idx, lines.get(idx).cloned().unwrap_or(""))
+}
+
+/// The only line of an input, such as a single number. `expected` describes
+/// it, for the error if it is missing.
+pub fn single<'a>(lines: &[&'a str], expected: &str) -> Result<Line<'a>> {
+    match lines.len() {
+        0 => Err(Error::input(0, 0, format!("expected {}", expected))),
+        1 => Ok(Line::new(0, lines[0])),
+        _ => Err(Error::input(1, 0, "expected only one line of input")),
+    }
+}
+
+/// The runs of digits in `text`, optionally with a minus sign right before
+/// them.
+fn numbers(text: &str, signed: bool) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let mut numbers = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let minus = signed && i > 0 && bytes[i - 1] == b'-';
+        let start = if minus { i - 1 } else { i };
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        numbers.push((start, i));
+    }
+    numbers
+}
+
+impl<'a> Line<'a> {
+    pub fn new(idx: usize, line: &'a str) -> Line<'a> {
+        Line {
+            idx,
+            line,
+            text: line,
+        }
+    }
+
+    /// A part of this one, which must be a slice of its text.
+    fn part(&self, text: &'a str) -> Line<'a> {
+        Line { text, ..*self }
+    }
+
+    /// The byte offset of the text in the whole line.
+    pub fn offset(&self) -> usize {
+        error::offset(self.line, self.text)
+    }
+
+    /// A malformed input error at the start of the text.
+    pub fn error<R: Into<String>>(&self, reason: R) -> Error {
+        self.error_at(0, reason)
+    }
+
+    /// A malformed input error at the given byte offset of the text.
+    pub fn error_at<R: Into<String>>(&self, offset: usize, reason: R) -> Error {
+        Error::input(self.idx, self.offset() + offset, reason)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn trim(&self) -> Line<'a> {
+        self.part(self.text.trim())
+    }
+
+    /// Parse all of the text, such as a number.
+    pub fn parse<T>(&self) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        error::parse_at(self.idx, self.line, self.text)
+    }
+
+    /// The text after the given literal prefix.
+    pub fn after(&self, prefix: &str) -> Result<Line<'a>> {
+        match self.text.strip_prefix(prefix) {
+            Some(rest) => Ok(self.part(rest)),
+            None => Err(self.error(format!("expected `{}`", prefix))),
+        }
+    }
+
+    /// The text before and after the first occurrence of `separator`.
+    pub fn split_once(&self, separator: &str) -> Result<(Line<'a>, Line<'a>)> {
+        match self.text.find(separator) {
+            Some(at) => Ok((
+                self.part(&self.text[..at]),
+                self.part(&self.text[at + separator.len()..]),
+            )),
+            None => Err(self.error(format!("expected `{}`", separator))),
+        }
+    }
+
+    /// The parts of the text between occurrences of `separator`.
+    pub fn split(&self, separator: &str) -> Vec<Line<'a>> {
+        self.text
+            .split(separator)
+            .map(|text| self.part(text))
+            .collect()
+    }
+
+    fn scan<T>(&self, signed: bool) -> Result<Vec<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        numbers(self.text, signed)
+            .into_iter()
+            .map(|(start, end)| self.part(&self.text[start..end]).parse())
+            .collect()
+    }
+
+    /// Every number in the text, ignoring what is around them: `#1 @ 1,3:
+    /// 4x4` holds 1, 1, 3, 4 and 4. Minus signs are ignored as well, so that
+    /// dashes in dates are not taken for them.
+    pub fn unsigned<T>(&self) -> Result<Vec<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.scan(false)
+    }
+
+    /// Every number in the text, with a minus sign if one comes right before
+    /// it.
+    pub fn signed<T>(&self) -> Result<Vec<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.scan(true)
+    }
+
+    fn exactly<T, const N: usize>(
+        &self,
+        numbers: Vec<T>,
+        expected: &str,
+    ) -> Result<[T; N]> {
+        numbers
+            .try_into()
+            .map_err(|_| self.error(format!("expected {}", expected)))
+    }
+
+    /// The `N` unsigned numbers in the text. `expected` describes the text,
+    /// for the error if there are more or fewer.
+    pub fn unsigned_n<T, const N: usize>(
+        &self,
+        expected: &str,
+    ) -> Result<[T; N]>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.exactly(self.unsigned()?, expected)
+    }
+
+    /// The `N` signed numbers in the text. `expected` describes the text, for
+    /// the error if there are more or fewer.
+    pub fn signed_n<T, const N: usize>(&self, expected: &str) -> Result<[T; N]>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.exactly(self.signed()?, expected)
+    }
+
+    /// The text after `key=`, where the key is a whole word.
+    fn after_key(&self, key: &str) -> Result<Line<'a>> {
+        let pattern = format!("{}=", key);
+        let at = self
+            .text
+            .match_indices(&pattern)
+            .map(|(at, _)| at)
+            .find(|&at| {
+                let before = self.text[..at].chars().next_back();
+                !before.is_some_and(char::is_alphanumeric)
+            })
+            .ok_or_else(|| self.error(format!("expected `{}=`", key)))?;
+        Ok(self.part(&self.text[at + pattern.len()..]))
+    }
+
+    /// The value of `key=<value>`, up to the next comma or space: `2..7` in
+    /// `x=495, y=2..7`.
+    pub fn field(&self, key: &str) -> Result<Line<'a>> {
+        let rest = self.after_key(key)?;
+        let end = rest
+            .text
+            .find(|c: char| c == ',' || c.is_whitespace())
+            .unwrap_or(rest.text.len());
+        Ok(rest.part(&rest.text[..end]))
+    }
+
+    /// The number in `key=<number>`.
+    pub fn value<T>(&self, key: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.field(key)?.parse()
+    }
+
+    /// The `N` coordinates of the vector `key=<x,y>`, which may have spaces
+    /// around them.
+    pub fn vector<T, const N: usize>(&self, key: &str) -> Result<[T; N]>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let (coordinates, _) =
+            self.after_key(key)?.after("<")?.split_once(">")?;
+        let numbers = coordinates
+            .split(",")
+            .iter()
+            .map(|coordinate| coordinate.trim().parse())
+            .collect::<Result<Vec<T>>>()?;
+        coordinates.exactly(numbers, &format!("{} coordinates", N))
+    }
+}
+
+/// Lines of an input between blank lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section<'a> {
+    /// The index of the first line in the input.
+    pub start: usize,
+    pub lines: &'a [&'a str],
+}
+
+/// The sections of an input, separated by `blanks` or more blank lines in a
+/// row.
+pub fn sections<'a>(lines: &'a [&'a str], blanks: usize) -> Vec<Section<'a>> {
+    Section { start: 0, lines }.sections(blanks)
+}
+
+impl<'a> Section<'a> {
+    pub fn lines(&self) -> Vec<Line<'a>> {
+        self.lines
+            .iter()
+            .enumerate()
+            .map(|(idx, &line)| Line::new(self.start + idx, line))
+            .collect()
+    }
+
+    /// The line with the given index in the section, or an empty line past
+    /// its end.
+    pub fn line(&self, idx: usize) -> Line<'a> {
+        Line::new(self.start + idx, self.lines.get(idx).cloned().unwrap_or(""))
+    }
+
+    /// The index of the line after the section.
+    pub fn end(&self) -> usize {
+        self.start + self.lines.len()
+    }
+
+    /// The sections of this one, separated by `blanks` or more blank lines
+    /// in a row. Fewer blank lines in a row are kept in the sections, which
+    /// start and end with lines that are not blank.
+    pub fn sections(&self, blanks: usize) -> Vec<Section<'a>> {
+        let blank = |idx: usize| self.lines[idx].trim().is_empty();
+        let mut sections = Vec::new();
+        let mut start = None;
+        let mut idx = 0;
+        while idx < self.lines.len() {
+            if !blank(idx) {
+                start = start.or(Some(idx));
+                idx += 1;
+                continue;
+            }
+            let run = (idx..self.lines.len()).take_while(|&i| blank(i)).count();
+            if let (Some(from), true) = (start, run >= blanks) {
+                sections.push(Section {
+                    start: self.start + from,
+                    lines: &self.lines[from..idx],
+                });
+                start = None;
+            }
+            idx += run;
+        }
+        if let Some(from) = start {
+            let mut end = self.lines.len();
+            while blank(end - 1) {
+                end -= 1;
+            }
+            sections.push(Section {
+                start: self.start + from,
+                lines: &self.lines[from..end],
+            });
+        }
+        sections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(err: Error) -> (usize, usize) {
+        match err {
+            Error::Input { line, column, .. } => (line, column),
+            err => panic!("expected an input error, got {}", err),
+        }
+    }
+
+    #[test]
+    fn numbers() {
+        let line = Line::new(0, "[1518-11-01 00:05] Guard #10 begins shift");
+        assert_eq!(line.unsigned::<u32>().unwrap(), [1518, 11, 1, 0, 5, 10]);
+        let line = Line::new(0, "-1,2,-30, 4");
+        assert_eq!(line.signed::<i32>().unwrap(), [-1, 2, -30, 4]);
+        let [x, y]: [i32; 2] = Line::new(0, "1, 6").unsigned_n("").unwrap();
+        assert_eq!((x, y), (1, 6));
+
+        let line = Line::new(3, "#1 @ 1,3: 4x4");
+        let err = line.unsigned_n::<u32, 4>("a claim").unwrap_err();
+        assert_eq!(err.to_string(), "<input>:4:1: expected a claim");
+        let err = Line::new(0, "1 999").unsigned::<u8>().unwrap_err();
+        assert_eq!(column(err), (1, 3));
+    }
+
+    #[test]
+    fn vectors() {
+        let line = Line::new(0, "position=< 9, -1> velocity=<-2,  3>");
+        assert_eq!(line.vector::<i64, 2>("position").unwrap(), [9, -1]);
+        assert_eq!(line.vector::<i64, 2>("velocity").unwrap(), [-2, 3]);
+
+        let line = Line::new(0, "pos=<0,1,2>, r=4");
+        assert_eq!(line.vector::<i64, 3>("pos").unwrap(), [0, 1, 2]);
+        assert_eq!(line.value::<i64>("r").unwrap(), 4);
+        let err = line.vector::<i64, 2>("pos").unwrap_err();
+        assert_eq!(err.to_string(), "<input>:1:6: expected 2 coordinates");
+        let err = line.vector::<i64, 3>("os").unwrap_err();
+        assert_eq!(column(err), (1, 1));
+
+        let line = Line::new(0, "x=495, y=2..7");
+        assert_eq!(line.field("y").unwrap().text, "2..7");
+        let err = line.value::<i32>("y").unwrap_err();
+        assert_eq!(column(err), (1, 10));
+    }
+
+    #[test]
+    fn parts() {
+        let line = Line::new(2, "Step C must be finished before step A.");
+        let (first, rest) = line
+            .after("Step ")
+            .unwrap()
+            .split_once(" must be finished before step ")
+            .unwrap();
+        assert_eq!((first.text, rest.text), ("C", "A."));
+        assert_eq!(rest.offset(), 36);
+        assert_eq!(column(rest.error_at(1, "")), (3, 38));
+        let err = rest.split_once(" can begin").unwrap_err();
+        assert_eq!(err.to_string(), "<input>:3:37: expected ` can begin`");
+
+        let parts = Line::new(0, "9 0 0 1").split(" ");
+        assert_eq!(parts[3].offset(), 6);
+        assert_eq!(parts[3].parse::<i32>().unwrap(), 1);
+    }
+
+    #[test]
+    fn sectioned() {
+        let lines = ["", "a", "b", "", "c", "", "", "d", "", ""];
+        let sections = sections(&lines, 1);
+        let starts = sections.iter().map(|s| s.start).collect::<Vec<_>>();
+        assert_eq!(starts, [1, 4, 7]);
+        assert_eq!(sections[0].lines, ["a", "b"]);
+        assert_eq!(sections[2].lines, ["d"]);
+        assert_eq!(sections[2].line(1).idx, 8);
+
+        let halves = super::sections(&lines, 2);
+        assert_eq!(halves.len(), 2);
+        assert_eq!(halves[0].lines, ["a", "b", "", "c"]);
+        let inner = halves[0].sections(1);
+        assert_eq!(inner[1].start, 4);
+        assert_eq!(inner[1].lines()[0].idx, 4);
+
+        assert!(super::sections(&[], 1).is_empty());
+        assert!(super::single(&[], "a number").is_err());
+        assert_eq!(
+            super::single(&["3"], "").unwrap().parse::<u8>().unwrap(),
+            3
+        );
+    }
+}
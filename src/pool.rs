@@ -0,0 +1,246 @@
+//! Solving days concurrently on a pool of threads, each with a time limit. A
+//! day that runs out of time is cancelled: it stops at its next
+//! `solution::checkpoint`, and another thread takes its place meanwhile, so
+//! that a day without checkpoints in its slow loops cannot hold up the rest.
+use days;
+use error::Error;
+use input::Input;
+use solution::{self, Answers, Cancelled, Part, Timings};
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How to run the days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// The number of days solved at once.
+    pub jobs: usize,
+    /// How long a day may take before it is cancelled.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            jobs: 1,
+            timeout: None,
+        }
+    }
+}
+
+/// How solving a day ended.
+#[derive(Debug)]
+pub enum Outcome {
+    Solved(Answers, Timings),
+    Failed(Error),
+    /// The solution panicked, with the given message.
+    Panicked(String),
+    /// The solution was cancelled for taking longer than the time limit.
+    TimedOut,
+}
+
+/// A day that was solved, or not.
+#[derive(Debug)]
+pub struct Run {
+    pub day: u32,
+    pub outcome: Outcome,
+    /// The time from starting the day to its outcome, including reading the
+    /// input.
+    pub elapsed: Duration,
+}
+
+enum Message {
+    Started(u32, Instant, Arc<AtomicBool>),
+    Finished(u32, Outcome, Instant),
+}
+
+/// The message of a panic payload, which is usually a string.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => (*message).to_owned(),
+        (_, Some(message)) => message.clone(),
+        _ => "a panic without a message".to_owned(),
+    }
+}
+
+/// Solve a day on its puzzle input, catching panics and cancellation.
+fn solve(day: u32, part: Part) -> Outcome {
+    let result = panic::catch_unwind(|| {
+        let input = Input::for_day(day, None)?;
+        let solver = days::solver(day).expect("a puzzle day");
+        solver(&input, part, &[]).map_err(|err| err.in_file(input.name()))
+    });
+    match result {
+        Ok(Ok((answers, timings))) => Outcome::Solved(answers, timings),
+        Ok(Err(err)) => Outcome::Failed(err),
+        Err(ref payload) if payload.is::<Cancelled>() => Outcome::TimedOut,
+        Err(payload) => Outcome::Panicked(panic_message(&*payload)),
+    }
+}
+
+/// Take days from the queue and solve them until it is empty, or until a
+/// day is cancelled, after which the thread is replaced.
+fn worker(queue: Arc<Mutex<VecDeque<u32>>>, part: Part, tx: Sender<Message>) {
+    thread::spawn(move || loop {
+        let day = match queue.lock().expect("no worker panics").pop_front() {
+            Some(day) => day,
+            None => return,
+        };
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        solution::cancel_with(Some(cancel.clone()));
+        let started = Message::Started(day, Instant::now(), cancel.clone());
+        if tx.send(started).is_err() {
+            return;
+        }
+        let outcome = solve(day, part);
+        let finished = Message::Finished(day, outcome, Instant::now());
+        if tx.send(finished).is_err() || cancel.load(Ordering::Relaxed) {
+            return;
+        }
+    });
+}
+
+/// Solve the given days on their puzzle inputs, returning how that went in
+/// the order of the days. A day given more than once is solved once. Days
+/// that are cancelled but do not stop are left running in the background.
+pub fn solve_days(days: &[u32], part: Part, options: Options) -> Vec<Run> {
+    let days = days.iter().cloned().collect::<BTreeSet<_>>();
+    let queue = Arc::new(Mutex::new(days.iter().cloned().collect()));
+    let (tx, rx) = mpsc::channel();
+    for _ in 0..options.jobs.clamp(1, days.len().max(1)) {
+        worker(queue.clone(), part, tx.clone());
+    }
+
+    // The days being solved, with when they started and their cancel flag.
+    let mut running: BTreeMap<u32, (Instant, Arc<AtomicBool>)> =
+        BTreeMap::new();
+    let mut runs = BTreeMap::new();
+    while runs.len() < days.len() {
+        let now = Instant::now();
+        let deadline = options.timeout.and_then(|timeout| {
+            running
+                .values()
+                .map(|&(started, _)| started + timeout)
+                .min()
+        });
+        let wait = deadline
+            .map(|deadline| deadline.saturating_duration_since(now))
+            .unwrap_or(Duration::from_secs(3600));
+
+        match rx.recv_timeout(wait) {
+            Ok(Message::Started(day, started, cancel)) => {
+                running.insert(day, (started, cancel));
+            }
+            Ok(Message::Finished(day, outcome, finished)) => {
+                // Days that timed out were reported already.
+                if let Some((started, _)) = running.remove(&day) {
+                    let elapsed = finished - started;
+                    runs.insert(
+                        day,
+                        Run {
+                            day,
+                            outcome,
+                            elapsed,
+                        },
+                    );
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => unreachable!(),
+        }
+
+        let timeout = match options.timeout {
+            Some(timeout) => timeout,
+            None => continue,
+        };
+        let now = Instant::now();
+        let late = running
+            .iter()
+            .filter(|&(_, &(started, _))| now - started >= timeout)
+            .map(|(&day, _)| day)
+            .collect::<Vec<_>>();
+        for day in late {
+            let (_, cancel) = running.remove(&day).expect("a running day");
+            cancel.store(true, Ordering::Relaxed);
+            let outcome = Outcome::TimedOut;
+            runs.insert(
+                day,
+                Run {
+                    day,
+                    outcome,
+                    elapsed: timeout,
+                },
+            );
+            worker(queue.clone(), part, tx.clone());
+        }
+    }
+
+    runs.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_order() {
+        let options = Options {
+            jobs: 4,
+            timeout: None,
+        };
+        let runs = solve_days(&[8, 2, 5, 1, 7], Part::One, options);
+        let days = runs.iter().map(|run| run.day).collect::<Vec<_>>();
+        assert_eq!(days, [1, 2, 5, 7, 8]);
+        assert!(runs.iter().all(|run| match run.outcome {
+            Outcome::Solved(ref answers, _) => answers.part1.is_some(),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn duplicates() {
+        let runs = solve_days(&[7, 7], Part::One, Options::default());
+        let days = runs.iter().map(|run| run.day).collect::<Vec<_>>();
+        assert_eq!(days, [7]);
+    }
+
+    /// Day 22 takes seconds to find its way to the target, and stops at a
+    /// checkpoint when it is cancelled. The other days go on meanwhile.
+    #[test]
+    fn timeout() {
+        let options = Options {
+            jobs: 1,
+            timeout: Some(Duration::from_millis(200)),
+        };
+        let started = Instant::now();
+        let runs = solve_days(&[22, 1], Part::Two, options);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        match runs[1].outcome {
+            Outcome::TimedOut => {}
+            ref outcome => panic!("expected a time out, got {:?}", outcome),
+        }
+        match runs[0].outcome {
+            Outcome::Solved(ref answers, _) => {
+                assert_eq!(answers.part2, Some("245".to_owned()))
+            }
+            ref outcome => panic!("expected day 1 to be solved: {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn panics() {
+        let payload = panic::catch_unwind(|| panic!("at {}", 3)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "at 3");
+        let payload = panic::catch_unwind(|| panic!("plain")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "plain");
+    }
+}
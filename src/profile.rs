@@ -0,0 +1,374 @@
+//! Profiling where solutions spend their time and memory. While profiling is
+//! running on a thread, parsing and each part are timed as spans, and so are
+//! the spans solutions mark with `span`, such as every combat on day 15.
+//! Peak memory is only measured in programs that install `Counting` as their
+//! global allocator, like `aoc`; allocations are counted across all threads.
+//! Profiles can be printed as a report, or saved as a Chrome trace that
+//! trace viewers such as `chrome://tracing` and Perfetto load.
+use json::Json;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting the bytes allocated and the most that were
+/// allocated at once.
+pub struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            allocated(new_size);
+        }
+        new
+    }
+}
+
+fn allocated(size: usize) {
+    let now = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(now, Ordering::Relaxed);
+}
+
+/// A timed span of a solution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// The names of the spans it is part of, outermost first, and its own.
+    pub path: Vec<&'static str>,
+    /// When it started, since profiling started.
+    pub start: Duration,
+    pub elapsed: Duration,
+    /// The most bytes allocated at once during the span, beyond those
+    /// allocated when it started.
+    pub peak: usize,
+}
+
+impl Span {
+    pub fn name(&self) -> &'static str {
+        self.path.last().expect("spans have a name")
+    }
+}
+
+/// The spans of a profiled solution, in the order they ended.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub started: Instant,
+    pub spans: Vec<Span>,
+}
+
+struct Profiler {
+    started: Instant,
+    path: Vec<&'static str>,
+    spans: Vec<Span>,
+}
+
+thread_local! {
+    static PROFILER: RefCell<Option<Profiler>> = const { RefCell::new(None) };
+}
+
+/// Start profiling on this thread, dropping the spans of an earlier profile
+/// that was not stopped.
+pub fn start() {
+    PROFILER.with(|profiler| {
+        *profiler.borrow_mut() = Some(Profiler {
+            started: Instant::now(),
+            path: Vec::new(),
+            spans: Vec::new(),
+        })
+    });
+}
+
+/// Stop profiling, returning the spans.
+pub fn stop() -> Profile {
+    let profiler = PROFILER.with(|profiler| profiler.borrow_mut().take());
+    match profiler {
+        Some(profiler) => Profile {
+            started: profiler.started,
+            spans: profiler.spans,
+        },
+        None => Profile {
+            started: Instant::now(),
+            spans: Vec::new(),
+        },
+    }
+}
+
+/// A span that has started, which ends when it is dropped: also when the code
+/// in it unwinds, as cancelled solutions do, so that the spans around it and
+/// after it get the right path and peak.
+struct Started {
+    start: Duration,
+    base: usize,
+    outer: usize,
+}
+
+impl Drop for Started {
+    fn drop(&mut self) {
+        let peak = PEAK.fetch_max(self.outer, Ordering::Relaxed);
+        PROFILER.with(|profiler| {
+            if let Some(profiler) = profiler.borrow_mut().as_mut() {
+                let path = profiler.path.clone();
+                profiler.path.pop();
+                profiler.spans.push(Span {
+                    path,
+                    start: self.start,
+                    elapsed: Instant::now() - profiler.started - self.start,
+                    peak: peak.saturating_sub(self.base),
+                });
+            }
+        });
+    }
+}
+
+/// Run `f` as a span with the given name, if profiling is running. This is
+/// cheap when it is not.
+pub fn span<T, F: FnOnce() -> T>(name: &'static str, f: F) -> T {
+    let started = PROFILER.with(|profiler| {
+        profiler.borrow_mut().as_mut().map(|profiler| {
+            profiler.path.push(name);
+            Instant::now() - profiler.started
+        })
+    });
+    let start = match started {
+        Some(start) => start,
+        None => return f(),
+    };
+
+    // Measure the peak of this span from what is allocated now, and put back
+    // the peak of the spans around it afterwards.
+    let base = ALLOCATED.load(Ordering::Relaxed);
+    let outer = PEAK.swap(base, Ordering::Relaxed);
+    let _started = Started { start, base, outer };
+    f()
+}
+
+/// A number of bytes, in the largest binary unit that keeps it at least 1.
+pub fn bytes(n: usize) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut value = n as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", n)
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+/// A table of the spans of the profiled days, with those that took the most
+/// time together first. Spans with the same path are summed.
+pub fn report(profiles: &[(u32, Profile)]) -> String {
+    // (calls, total time, largest peak) per day and path.
+    let mut rows = BTreeMap::new();
+    for (day, profile) in profiles {
+        for span in &profile.spans {
+            let row = rows.entry((*day, span.path.join(" / "))).or_insert((
+                0,
+                Duration::default(),
+                0,
+            ));
+            row.0 += 1;
+            row.1 += span.elapsed;
+            row.2 = row.2.max(span.peak);
+        }
+    }
+    let mut rows = rows.into_iter().collect::<Vec<_>>();
+    rows.sort_by_key(|&(_, (_, elapsed, _))| std::cmp::Reverse(elapsed));
+
+    let mut report = format!(
+        "{:>3}  {:<24} {:>6} {:>12} {:>12}\n",
+        "Day", "Span", "Calls", "Time", "Peak memory"
+    );
+    for ((day, path), (calls, elapsed, peak)) in rows {
+        writeln!(
+            report,
+            "{:>3}  {:<24} {:>6} {:>10.3}ms {:>12}",
+            day,
+            path,
+            calls,
+            elapsed.as_secs_f64() * 1000.0,
+            bytes(peak)
+        )
+        .expect("writing to a string cannot fail");
+    }
+    report
+}
+
+/// The spans of the profiled days as a Chrome trace, with a row per day.
+pub fn trace(profiles: &[(u32, Profile)]) -> Json {
+    let micros = |duration: Duration| Json::from(duration.as_secs_f64() * 1e6);
+    let epoch = profiles.iter().map(|(_, profile)| profile.started).min();
+
+    let mut events = Vec::new();
+    for (day, profile) in profiles {
+        let offset = profile.started - epoch.expect("there are profiles");
+        events.push(Json::object(vec![
+            ("name", Json::from("thread_name")),
+            ("ph", Json::from("M")),
+            ("pid", Json::from(1u32)),
+            ("tid", Json::from(*day)),
+            (
+                "args",
+                Json::object(vec![(
+                    "name",
+                    Json::from(format!("Day {}", day)),
+                )]),
+            ),
+        ]));
+        for span in &profile.spans {
+            events.push(Json::object(vec![
+                ("name", Json::from(span.name())),
+                ("cat", Json::from(format!("day {}", day))),
+                ("ph", Json::from("X")),
+                ("ts", micros(offset + span.start)),
+                ("dur", micros(span.elapsed)),
+                ("pid", Json::from(1u32)),
+                ("tid", Json::from(*day)),
+                (
+                    "args",
+                    Json::object(vec![(
+                        "peak_bytes",
+                        Json::from(span.peak as f64),
+                    )]),
+                ),
+            ]));
+        }
+    }
+
+    Json::object(vec![
+        ("traceEvents", Json::Array(events)),
+        ("displayTimeUnit", Json::from("ms")),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    #[test]
+    fn spans() {
+        assert_eq!(span("unprofiled", || 1), 1);
+
+        start();
+        let total = span("part 1", || {
+            let small = span("small", || vec![0u8; 1000].len());
+            let large = span("large", || vec![0u8; 100_000].len());
+            small + large
+        });
+        let profile = stop();
+        assert_eq!(total, 101_000);
+
+        let paths = profile
+            .spans
+            .iter()
+            .map(|span| span.path.join(" / "))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["part 1 / small", "part 1 / large", "part 1"]);
+
+        // Other tests may allocate at the same time, so peaks are at least
+        // what the spans allocated.
+        let peaks = profile
+            .spans
+            .iter()
+            .map(|span| span.peak)
+            .collect::<Vec<_>>();
+        assert!(peaks[0] >= 1000);
+        assert!(peaks[1] >= 100_000);
+        assert!(peaks[2] >= peaks[1]);
+        assert!(profile.spans[2].elapsed >= profile.spans[1].elapsed);
+        assert!(profile.spans[1].start >= profile.spans[0].start);
+    }
+
+    /// A span that unwinds still ends, and the spans after it are not taken
+    /// to be inside it.
+    #[test]
+    fn unwinding() {
+        start();
+        span("part 1", || {
+            let unwound = std::panic::catch_unwind(|| {
+                span("combat", || std::panic::resume_unwind(Box::new(())))
+            });
+            assert!(unwound.is_err());
+            span("after", || ());
+        });
+        let profile = stop();
+        let paths = profile
+            .spans
+            .iter()
+            .map(|span| span.path.join(" / "))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["part 1 / combat", "part 1 / after", "part 1"]);
+    }
+
+    #[test]
+    fn reports() {
+        let span = |path: Vec<&'static str>, millis, peak| Span {
+            path,
+            start: Duration::default(),
+            elapsed: Duration::from_millis(millis),
+            peak,
+        };
+        let profile = Profile {
+            started: Instant::now(),
+            spans: vec![
+                span(vec!["parse"], 1, 10),
+                span(vec!["part 2", "combat"], 20, 2048),
+                span(vec!["part 2", "combat"], 30, 4096),
+                span(vec!["part 2"], 52, 4096),
+            ],
+        };
+        let profiles = vec![(15, profile)];
+
+        let report = report(&profiles);
+        let lines = report.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with(" 15  part 2  "));
+        assert!(lines[2].starts_with(" 15  part 2 / combat "));
+        assert!(lines[2].contains("      2     50.000ms      4.0 KiB"));
+        assert!(lines[3].ends_with("10 B"));
+
+        let trace = trace(&profiles).to_string();
+        assert!(trace.starts_with(
+            r#"{"traceEvents":[{"name":"thread_name","ph":"M","pid":1,"tid":15,"args":{"name":"Day 15"}},{"name":"parse","cat":"day 15","ph":"X","ts":0,"dur":1000,"pid":1,"tid":15,"args":{"peak_bytes":10}}"#
+        ));
+        assert!(trace.ends_with(r#"],"displayTimeUnit":"ms"}"#));
+    }
+}
@@ -0,0 +1,294 @@
+//! Recording simulations as animations. Simulation loops call `frame` after
+//! every tick; while a recording is running, every tick that is not skipped
+//! is drawn and kept. Recordings are written as animated GIFs or as numbered
+//! PPM images.
+use error::Error;
+use render::{Image, Rgb};
+use solution::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// How to record a simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// The number of ticks to skip after every recorded one.
+    pub skip: usize,
+    /// How long every frame is shown.
+    pub delay: Duration,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            skip: 0,
+            delay: Duration::from_millis(100),
+        }
+    }
+}
+
+struct Recorder {
+    options: Options,
+    ticks: usize,
+    frames: Vec<Image>,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+/// Start recording the frames of simulations on this thread, dropping those
+/// of an earlier recording that was not stopped.
+pub fn start(options: Options) {
+    RECORDER.with(|recorder| {
+        *recorder.borrow_mut() = Some(Recorder {
+            options,
+            ticks: 0,
+            frames: Vec::new(),
+        })
+    });
+}
+
+/// Stop recording, returning the recorded frames.
+pub fn stop() -> Animation {
+    let recorder = RECORDER.with(|recorder| recorder.borrow_mut().take());
+    match recorder {
+        Some(recorder) => Animation {
+            frames: recorder.frames,
+            delay: recorder.options.delay,
+        },
+        None => Animation::default(),
+    }
+}
+
+/// Record the state of a simulation after a tick. `draw` is only called
+/// for ticks that are recorded, so this is cheap when nothing is.
+pub fn frame<F: FnOnce() -> Image>(draw: F) {
+    RECORDER.with(|recorder| {
+        if let Some(recorder) = recorder.borrow_mut().as_mut() {
+            if recorder.ticks % (recorder.options.skip + 1) == 0 {
+                recorder.frames.push(draw());
+            }
+            recorder.ticks += 1;
+        }
+    });
+}
+
+/// Recorded frames, each shown for `delay`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Animation {
+    pub frames: Vec<Image>,
+    pub delay: Duration,
+}
+
+impl Animation {
+    /// Every frame drawn with squares of `scale` pixels.
+    pub fn scaled(&self, scale: usize) -> Animation {
+        Animation {
+            frames: self.frames.iter().map(|f| f.scaled(scale)).collect(),
+            delay: self.delay,
+        }
+    }
+
+    /// Write an animated GIF that loops forever. Frames are drawn from the
+    /// top left corner of a canvas as large as the largest frame.
+    pub fn write_gif<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let width = self.frames.iter().map(Image::width).max().unwrap_or(0);
+        let height = self.frames.iter().map(Image::height).max().unwrap_or(0);
+
+        w.write_all(b"GIF89a")?;
+        w.write_all(&(width as u16).to_le_bytes())?;
+        w.write_all(&(height as u16).to_le_bytes())?;
+        // No global colour table, background colour 0, square pixels.
+        w.write_all(&[0, 0, 0])?;
+        // Loop forever.
+        w.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+
+        let delay = (self.delay.as_millis() / 10).min(u128::from(u16::MAX));
+        for frame in &self.frames {
+            // The delay of the frame, which replaces the previous one.
+            w.write_all(&[0x21, 0xf9, 0x04, 0x04])?;
+            w.write_all(&(delay as u16).to_le_bytes())?;
+            w.write_all(&[0, 0])?;
+
+            let bits = colour_table_bits(frame.colours().len());
+            w.write_all(&[0x2c, 0, 0, 0, 0])?;
+            w.write_all(&(frame.width() as u16).to_le_bytes())?;
+            w.write_all(&(frame.height() as u16).to_le_bytes())?;
+            // A local colour table of 2^bits entries.
+            w.write_all(&[0x80 | (bits - 1) as u8])?;
+            for n in 0..1 << bits {
+                let Rgb(r, g, b) =
+                    frame.colours().get(n).cloned().unwrap_or(Rgb(0, 0, 0));
+                w.write_all(&[r, g, b])?;
+            }
+
+            let min_code_size = bits.max(2);
+            w.write_all(&[min_code_size as u8])?;
+            for block in lzw(frame.pixels(), min_code_size).chunks(255) {
+                w.write_all(&[block.len() as u8])?;
+                w.write_all(block)?;
+            }
+            w.write_all(&[0])?;
+        }
+
+        w.write_all(&[0x3b])
+    }
+
+    /// Save the animation as a GIF, or as numbered PPM images if the path
+    /// ends in `.ppm`: `out.ppm` is saved as `out-00000.ppm`,
+    /// `out-00001.ppm` and so on.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        match extension.to_lowercase().as_str() {
+            "gif" => {
+                let mut w = BufWriter::new(File::create(path)?);
+                self.write_gif(&mut w)?;
+                w.flush()?;
+                Ok(())
+            }
+            "ppm" => {
+                let stem = path.with_extension("");
+                for (n, frame) in self.frames.iter().enumerate() {
+                    let name = format!("{}-{:05}.ppm", stem.display(), n);
+                    let mut w = BufWriter::new(File::create(name)?);
+                    frame.write_ppm(&mut w)?;
+                    w.flush()?;
+                }
+                Ok(())
+            }
+            _ => Err(Error::Param(format!(
+                "Cannot save `{}`: expected a .gif or .ppm file",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// The number of bits of colour table indices, at least 1.
+fn colour_table_bits(colours: usize) -> u32 {
+    let mut bits = 1;
+    while 1 << bits < colours {
+        bits += 1;
+    }
+    bits
+}
+
+/// Writes variable-length codes least significant bit first.
+#[derive(Default)]
+struct CodeWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    len: u32,
+}
+
+impl CodeWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.bits |= u32::from(code) << self.len;
+        self.len += size;
+        while self.len >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+const MAX_CODES: u16 = 4096;
+
+/// Compress palette indices with GIF's variant of LZW.
+fn lzw(indices: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut w = CodeWriter::default();
+    let mut codes: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut size = min_code_size + 1;
+    w.write(clear, size);
+
+    let mut indices = indices.iter();
+    let mut current = match indices.next() {
+        Some(&index) => u16::from(index),
+        None => {
+            w.write(end, size);
+            return w.finish();
+        }
+    };
+
+    for &index in indices {
+        if let Some(&code) = codes.get(&(current, index)) {
+            current = code;
+            continue;
+        }
+
+        w.write(current, size);
+        codes.insert((current, index), next);
+        next += 1;
+        // The decoder adds codes one step later, so sizes grow once the
+        // code after the first that does not fit has been added.
+        if next > 1 << size && size < 12 {
+            size += 1;
+        }
+        if next == MAX_CODES {
+            w.write(clear, size);
+            codes.clear();
+            next = end + 1;
+            size = min_code_size + 1;
+        }
+        current = u16::from(index);
+    }
+
+    w.write(current, size);
+    w.write(end, size);
+    w.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use render::Palette;
+
+    static PALETTE: Palette =
+        Palette(&[(' ', Rgb(0, 0, 0)), ('#', Rgb(255, 255, 255))]);
+
+    #[test]
+    fn record() {
+        // Ticks outside a recording are not drawn.
+        frame(|| panic!("not recording"));
+
+        start(Options {
+            skip: 1,
+            delay: Duration::from_millis(50),
+        });
+        for text in &["#", " ", "##", "  "] {
+            frame(|| Image::from_text(text, &PALETTE));
+        }
+        let animation = stop();
+        assert_eq!(animation.frames.len(), 2);
+        assert_eq!(animation.frames[1].width(), 2);
+        assert_eq!(animation.delay, Duration::from_millis(50));
+        assert!(stop().frames.is_empty());
+    }
+
+    #[test]
+    fn lzw() {
+        // Worked out by hand: clear, 1, 6 (1 1), 6, then 2 and 9 (2 2) once
+        // the codes have grown to four bits, and end.
+        assert_eq!(
+            super::lzw(&[1, 1, 1, 1, 1, 2, 2, 2], 2),
+            vec![0x8c, 0x2d, 0x59]
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Raster images of puzzle states, drawn from the ASCII art the days already
+//! produce by giving every tile character a colour. Images are written as
+//! PPM or as PNG; the PNG encoder is minimal, but compresses the long runs
+//! and repeated rows of scaled-up tiles well.
+use error::Error;
+use solution::Result;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+/// The colours of tile characters. The first entry is the background, used
+/// for characters that are not in the palette and to pad short rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette(pub &'static [(char, Rgb)]);
+
+impl Palette {
+    fn index(&self, c: char) -> u8 {
+        self.0.iter().position(|&(p, _)| p == c).unwrap_or(0) as u8
+    }
+}
+
+/// An image with a palette of at most 256 colours.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    colours: Vec<Rgb>,
+    /// The tile character of every colour.
+    symbols: Vec<char>,
+    /// Indices into `colours`, row by row.
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    /// An image with a pixel for every character of the text.
+    pub fn from_text(text: &str, palette: &Palette) -> Image {
+        let width = text
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let height = text.lines().count();
+
+        let mut pixels = vec![0; width * height];
+        for (y, line) in text.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                pixels[y * width + x] = palette.index(c);
+            }
+        }
+
+        Image {
+            width,
+            height,
+            colours: palette.0.iter().map(|&(_, colour)| colour).collect(),
+            symbols: palette.0.iter().map(|&(c, _)| c).collect(),
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn colours(&self) -> &[Rgb] {
+        &self.colours
+    }
+
+    /// The tile character of every colour, so `symbols()[index]` is drawn
+    /// in `colours()[index]`.
+    pub fn symbols(&self) -> &[char] {
+        &self.symbols
+    }
+
+    /// The index into `colours` of every pixel, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Rgb> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.colours[self.pixels[y * self.width + x] as usize])
+    }
+
+    /// The ASCII art the image was drawn from, with characters that are not
+    /// in the palette replaced by the background character.
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            text.extend(row.iter().map(|&p| self.symbols[p as usize]));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The image with every pixel drawn as a square of `scale` pixels.
+    pub fn scaled(&self, scale: usize) -> Image {
+        let width = self.width * scale;
+        let mut pixels = Vec::with_capacity(width * self.height * scale);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            let start = pixels.len();
+            for &pixel in row {
+                pixels.extend(std::iter::repeat_n(pixel, scale));
+            }
+            for _ in 1..scale {
+                pixels.extend_from_within(start..start + width);
+            }
+        }
+
+        Image {
+            width,
+            height: self.height * scale,
+            colours: self.colours.clone(),
+            symbols: self.symbols.clone(),
+            pixels,
+        }
+    }
+
+    /// Write a binary PPM (P6) image.
+    pub fn write_ppm<W: Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 3);
+        for &pixel in &self.pixels {
+            let Rgb(r, g, b) = self.colours[pixel as usize];
+            bytes.extend_from_slice(&[r, g, b]);
+        }
+        w.write_all(&bytes)
+    }
+
+    /// Write a PNG image with 8-bit palette indices.
+    pub fn write_png<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // Bit depth 8, palette colours, deflate, no filters, no interlacing.
+        header.extend_from_slice(&[8, 3, 0, 0, 0]);
+        write_chunk(&mut w, b"IHDR", &header)?;
+
+        let palette = self
+            .colours
+            .iter()
+            .flat_map(|&Rgb(r, g, b)| vec![r, g, b])
+            .collect::<Vec<_>>();
+        write_chunk(&mut w, b"PLTE", &palette)?;
+
+        // Every row starts with its filter type, which is always none.
+        let mut scanlines = Vec::with_capacity((self.width + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+        write_chunk(&mut w, b"IDAT", &zlib(&scanlines, self.width + 1))?;
+        write_chunk(&mut w, b"IEND", &[])
+    }
+
+    /// Save the image as PNG or PPM, depending on the extension of the path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let write = match extension.to_lowercase().as_str() {
+            "png" => Image::write_png,
+            "ppm" => Image::write_ppm,
+            _ => {
+                return Err(Error::Param(format!(
+                    "Cannot save `{}`: expected a .png or .ppm file",
+                    path.display()
+                )))
+            }
+        };
+
+        let mut w = BufWriter::new(File::create(path)?);
+        write(self, &mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+fn write_chunk<W: Write>(
+    w: &mut W,
+    kind: &[u8; 4],
+    data: &[u8],
+) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    w.write_all(&crc.to_be_bytes())
+}
+
+fn crc32<'a, I: IntoIterator<Item = &'a u8>>(bytes: I) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// Writes bits least significant first, as deflate expects.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u64,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, len: u32) {
+        self.bits |= u64::from(value) << self.len;
+        self.len += len;
+        while self.len >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    /// Huffman codes are written most significant bit first.
+    fn write_code(&mut self, code: u32, len: u32) {
+        let reversed = (0..len).fold(0, |r, i| (r << 1) | ((code >> i) & 1));
+        self.write(reversed, len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+const LENGTH_BASE: [u32; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59,
+    67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5,
+    5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u32; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513,
+    769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10,
+    11, 11, 12, 12, 13, 13,
+];
+const MAX_MATCH: usize = 258;
+const MAX_DISTANCE: usize = 32768;
+
+/// Write a literal or length symbol with the fixed Huffman codes.
+fn write_symbol(w: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => w.write_code(0x30 + symbol, 8),
+        144..=255 => w.write_code(0x190 + symbol - 144, 9),
+        256..=279 => w.write_code(symbol - 256, 7),
+        _ => w.write_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(w: &mut BitWriter, len: usize, distance: usize) {
+    let (len, distance) = (len as u32, distance as u32);
+    let code = LENGTH_BASE.iter().rposition(|&base| base <= len).unwrap();
+    write_symbol(w, 257 + code as u32);
+    w.write(len - LENGTH_BASE[code], LENGTH_EXTRA[code]);
+
+    let code = DISTANCE_BASE
+        .iter()
+        .rposition(|&base| base <= distance)
+        .unwrap();
+    w.write_code(code as u32, 5);
+    w.write(distance - DISTANCE_BASE[code], DISTANCE_EXTRA[code]);
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    ((usize::from(bytes[0]) << 10)
+        ^ (usize::from(bytes[1]) << 5)
+        ^ usize::from(bytes[2]))
+        & 0x7fff
+}
+
+/// Compress data into a zlib stream of a single deflate block with fixed
+/// Huffman codes. Matches are looked for at the last position with the same
+/// three bytes, and one byte and one `stride` (a row of pixels) back.
+fn zlib(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut w = BitWriter::default();
+    w.bytes.extend_from_slice(&[0x78, 0x01]);
+    // The final block, with fixed Huffman codes.
+    w.write(1, 1);
+    w.write(1, 2);
+
+    let mut last = vec![usize::MAX; 1 << 15];
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best_len, mut best_distance) = (0, 0);
+        if i + 3 <= data.len() {
+            let h = hash(&data[i..]);
+            for &candidate in
+                &[last[h], i.wrapping_sub(1), i.wrapping_sub(stride)]
+            {
+                if candidate >= i || i - candidate > MAX_DISTANCE {
+                    continue;
+                }
+                let len = data[i..]
+                    .iter()
+                    .zip(&data[candidate..])
+                    .take(MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_distance = i - candidate;
+                }
+            }
+        }
+
+        let len = if best_len >= 3 {
+            write_match(&mut w, best_len, best_distance);
+            best_len
+        } else {
+            write_symbol(&mut w, u32::from(data[i]));
+            1
+        };
+        for p in i..(i + len).min(data.len().saturating_sub(2)) {
+            last[hash(&data[p..])] = p;
+        }
+        i += len;
+    }
+    write_symbol(&mut w, 256);
+
+    let mut bytes = w.finish();
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static PALETTE: Palette =
+        Palette(&[(' ', Rgb(0, 0, 0)), ('#', Rgb(255, 255, 255))]);
+
+    #[test]
+    fn from_text() {
+        let image = Image::from_text("# #\n#", &PALETTE).scaled(2);
+        assert_eq!((image.width(), image.height()), (6, 4));
+        assert_eq!(image.get(1, 3), Some(Rgb(255, 255, 255)));
+        assert_eq!(image.get(2, 0), Some(Rgb(0, 0, 0)));
+        assert_eq!(image.get(5, 3), Some(Rgb(0, 0, 0)));
+        assert_eq!(image.get(6, 0), None);
+        assert_eq!(Image::from_text("#?\n#", &PALETTE).text(), "# \n# \n");
+
+        let mut ppm = Vec::new();
+        Image::from_text("# ", &PALETTE)
+            .write_ppm(&mut ppm)
+            .unwrap();
+        assert_eq!(ppm, b"P6\n2 1\n255\n\xff\xff\xff\x00\x00\x00");
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn deflate() {
+        // A single literal and a run, from hand-encoding the fixed codes.
+        assert_eq!(
+            zlib(b"a", 1),
+            vec![0x78, 0x01, 0x4b, 0x04, 0x00, 0x00, 0x62, 0x00, 0x62]
+        );
+        let run = zlib(&[7; 1000], 10);
+        assert!(run.len() < 20);
+    }
+}
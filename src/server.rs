@@ -0,0 +1,487 @@
+//! A small HTTP/1.1 server exposing the solvers as a JSON API, for tools that
+//! would rather not link the crate. It only uses the standard library, serves
+//! every connection on a thread of its own, and closes it after one request.
+//!
+//! - `POST /day/{n}/part/{1|2|both}` solves the input in the request body,
+//!   and answers with the JSON document of `aoc run --json`. Puzzle
+//!   parameters can be given in the query string, as in `?workers=2`.
+//! - `POST /day/{n}/render` draws the puzzle state of the input in the body
+//!   as a PNG image, with every tile a square of `?scale=` pixels (default 4).
+//!
+//! Solutions that take longer than the time limit are cancelled at their next
+//! `solution::checkpoint`, and answered with status 503.
+use days;
+use error::Error;
+use input::Input;
+use json::{self, Json};
+use pool;
+use solution::{self, Cancelled, Part};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// The most bytes of request line and headers that are read.
+const MAX_HEAD: u64 = 64 * 1024;
+/// The largest body accepted; puzzle inputs are much smaller.
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// A parsed HTTP request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// The path, without the query string.
+    pub path: String,
+    /// The decoded `name=value` pairs of the query string.
+    pub query: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// An HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, json: &Json) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            body: json.to_string().into_bytes(),
+        }
+    }
+
+    /// A JSON document with just the reason of an error.
+    fn error<R: Into<String>>(status: u16, reason: R) -> Response {
+        let json = Json::object(vec![("error", Json::from(reason.into()))]);
+        Response::json(status, &json)
+    }
+
+    /// The error of solving a day, with the status that fits its kind.
+    fn failure(day: u32, err: &Error) -> Response {
+        let status = match err {
+            Error::Input { .. } | Error::Param(_) => 400,
+            Error::Unsolvable(_) => 422,
+            Error::Io(_) => 500,
+        };
+        Response::json(status, &json::error(day, err))
+    }
+
+    /// The response to a solution that was cancelled after `timeout`.
+    fn timed_out(day: u32, timeout: Duration) -> Response {
+        let reason = format!("timed out after {:.3}s", timeout.as_secs_f64());
+        Response::json(503, &json::error(day, &reason))
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Decode `%XX` escapes and `+` for spaces.
+fn decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = std::str::from_utf8(rest.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &rest[2..];
+            }
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Read a request, or the response to send if it is malformed.
+pub fn read_request<R: BufRead>(
+    reader: &mut R,
+) -> std::result::Result<Request, Response> {
+    let bad = |reason: &str| Response::error(400, reason);
+    let mut head = reader.take(MAX_HEAD);
+
+    let mut line = String::new();
+    head.read_line(&mut line)
+        .map_err(|_| bad("Expected a request line"))?;
+    let parts = line.split_whitespace().collect::<Vec<_>>();
+    let (method, target) = match parts[..] {
+        [method, target, version] if version.starts_with("HTTP/1.") => {
+            (method.to_owned(), target.to_owned())
+        }
+        _ => return Err(bad("Expected a request line")),
+    };
+
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        match head.read_line(&mut header) {
+            Ok(0) | Err(_) => {
+                return Err(bad("Expected the end of the headers"))
+            }
+            Ok(_) => {}
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| bad("Expected a header like `Name: value`"))?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| bad("Invalid Content-Length"))?,
+            );
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(Response::error(
+                501,
+                "Only Content-Length is supported",
+            ));
+        }
+    }
+
+    let body = match length {
+        Some(length) if length > MAX_BODY => {
+            return Err(Response::error(413, "The body is too large"))
+        }
+        Some(length) => {
+            let mut body = vec![0; length];
+            reader
+                .read_exact(&mut body)
+                .map_err(|_| bad("The body ended early"))?;
+            body
+        }
+        None if method == "POST" => {
+            return Err(Response::error(411, "Expected a Content-Length"))
+        }
+        None => Vec::new(),
+    };
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (target.as_str(), ""),
+    };
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match (decode(name), decode(value)) {
+                (Some(name), Some(value)) => Ok((name, value)),
+                _ => Err(bad("Invalid query string")),
+            }
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(Request {
+        method,
+        path: path.to_owned(),
+        query,
+        body,
+    })
+}
+
+/// The puzzle input in the body of a request.
+fn input(request: &Request) -> std::result::Result<Input, Response> {
+    let text = String::from_utf8(request.body.clone())
+        .map_err(|_| Response::error(400, "The input is not UTF-8"))?;
+    Ok(Input::new("<body>", text))
+}
+
+/// Run `f`, catching panics, and cancelling it once `timeout` has passed.
+fn cancel_after<T, F: FnOnce() -> T>(
+    timeout: Duration,
+    f: F,
+) -> thread::Result<T> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    // The timer stops early when `done` is dropped, as `f` has returned.
+    let (done, finished) = mpsc::channel::<()>();
+    let flag = cancel.clone();
+    thread::spawn(move || {
+        if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+
+    solution::cancel_with(Some(cancel));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    solution::cancel_with(None);
+    drop(done);
+    result
+}
+
+fn solve(
+    day: u32,
+    part: Part,
+    request: &Request,
+    timeout: Duration,
+) -> Response {
+    let solver = match days::solver(day) {
+        Some(solver) => solver,
+        None => {
+            return Response::error(404, format!("No puzzle on day {}", day))
+        }
+    };
+    let input = match input(request) {
+        Ok(input) => input,
+        Err(response) => return response,
+    };
+
+    // The input is dropped after a panic, so whatever it was parsed into
+    // by then is never used.
+    let result = cancel_after(timeout, || {
+        solver(&input, part, &request.query)
+            .map_err(|err| err.in_file(input.name()))
+    });
+    match result {
+        Ok(Ok((answers, timings))) => {
+            Response::json(200, &json::answers(day, &answers, &timings))
+        }
+        Ok(Err(err)) => Response::failure(day, &err),
+        Err(ref payload) if payload.is::<Cancelled>() => {
+            Response::timed_out(day, timeout)
+        }
+        Err(payload) => Response::error(500, pool::panic_message(&*payload)),
+    }
+}
+
+fn render(day: u32, request: &Request, timeout: Duration) -> Response {
+    let renderer = match days::renderer(day) {
+        Some(renderer) => renderer,
+        None => {
+            let reason = format!("There is nothing to render on day {}", day);
+            return Response::error(404, reason);
+        }
+    };
+    let input = match input(request) {
+        Ok(input) => input,
+        Err(response) => return response,
+    };
+
+    let mut scale = 4;
+    let mut params = Vec::new();
+    for (name, value) in &request.query {
+        match (name.as_str(), value.parse()) {
+            ("scale", Ok(n)) if n > 0 && n <= 64 => scale = n,
+            ("scale", _) => {
+                return Response::error(400, "Expected a scale from 1 to 64")
+            }
+            _ => params.push((name.clone(), value.clone())),
+        }
+    }
+
+    let result = cancel_after(timeout, || {
+        renderer(&input, &params).map_err(|err| err.in_file(input.name()))
+    });
+    match result {
+        Ok(Ok(image)) => {
+            let mut png = Vec::new();
+            match image.scaled(scale).write_png(&mut png) {
+                Ok(()) => Response {
+                    status: 200,
+                    content_type: "image/png",
+                    body: png,
+                },
+                Err(err) => Response::failure(day, &Error::Io(err)),
+            }
+        }
+        Ok(Err(err)) => Response::failure(day, &err),
+        Err(ref payload) if payload.is::<Cancelled>() => {
+            Response::timed_out(day, timeout)
+        }
+        Err(payload) => Response::error(500, pool::panic_message(&*payload)),
+    }
+}
+
+/// Answer a request, giving up on solutions that take longer than `timeout`.
+pub fn handle(request: &Request, timeout: Duration) -> Response {
+    let segments = request.path.split('/').skip(1).collect::<Vec<_>>();
+    let route = match segments[..] {
+        ["day", day, "part", part] => match (day.parse(), part.parse()) {
+            (Ok(day), Ok(part)) => Some((day, Some(part))),
+            _ => None,
+        },
+        ["day", day, "render"] => day.parse().ok().map(|day| (day, None)),
+        _ => None,
+    };
+
+    match (route, request.method.as_str()) {
+        (None, _) => Response::error(404, "Not found"),
+        (Some(_), method) if method != "POST" => {
+            Response::error(405, "Expected a POST with the input as body")
+        }
+        (Some((day, Some(part))), _) => solve(day, part, request, timeout),
+        (Some((day, None)), _) => render(day, request, timeout),
+    }
+}
+
+/// Write a response, closing the connection after it.
+pub fn write_response<W: Write>(
+    w: &mut W,
+    response: &Response,
+) -> io::Result<()> {
+    write!(
+        w,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    )?;
+    if response.status == 405 {
+        write!(w, "Allow: POST\r\n")?;
+    }
+    write!(w, "\r\n")?;
+    w.write_all(&response.body)?;
+    w.flush()
+}
+
+fn connection(stream: TcpStream, timeout: Duration) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match read_request(&mut reader) {
+        Ok(request) => handle(&request, timeout),
+        Err(response) => response,
+    };
+    write_response(&mut &stream, &response)
+}
+
+/// Serve requests on the listener, cancelling solutions that take longer than
+/// `timeout`. Connections that cannot be accepted are reported and skipped.
+pub fn serve(listener: TcpListener, timeout: Duration) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Error accepting a connection: {}", err);
+                continue;
+            }
+        };
+        thread::spawn(move || {
+            // The client may have gone; there is no one to tell.
+            let _ = connection(stream, timeout);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(text: &str) -> std::result::Result<Request, Response> {
+        read_request(&mut text.as_bytes())
+    }
+
+    fn post(path: &str, body: &str) -> Response {
+        let text = format!(
+            "POST {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            path,
+            body.len(),
+            body
+        );
+        handle(&request(&text).unwrap(), Duration::from_secs(60))
+    }
+
+    #[test]
+    fn requests() {
+        let parsed = request(
+            "POST /day/7/part/2?workers=2&base_duration=0&x=a%20b+c \
+             HTTP/1.1\r\nHost: localhost\r\ncontent-length: 3\r\n\r\nabcdef",
+        )
+        .unwrap();
+        assert_eq!(parsed.method, "POST");
+        assert_eq!(parsed.path, "/day/7/part/2");
+        assert_eq!(parsed.query[1], ("base_duration".into(), "0".into()));
+        assert_eq!(parsed.query[2], ("x".into(), "a b c".into()));
+        assert_eq!(parsed.body, b"abc");
+
+        assert_eq!(request("POST / HTTP/1.1\r\n\r\n").unwrap_err().status, 411);
+        assert_eq!(request("GET /\r\n\r\n").unwrap_err().status, 400);
+        let chunked = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n";
+        assert_eq!(request(chunked).unwrap_err().status, 501);
+    }
+
+    #[test]
+    fn routes() {
+        let response = post("/day/1/part/1", "+1\n-2\n+3");
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.starts_with(r#"{"day":1,"parse_elapsed_ms":"#));
+        assert!(body.contains(r#""parts":[{"part":1,"answer":"2","#));
+
+        let response = post("/day/7/part/2?workers=0", "");
+        assert_eq!(response.status, 400);
+        let response = post("/day/1/part/1", "+1\nx");
+        assert_eq!(response.status, 400);
+        assert_eq!(
+            String::from_utf8(response.body).unwrap(),
+            r#"{"day":1,"error":"<body>:2:1: invalid number `x`: invalid digit found in string"}"#
+        );
+
+        assert_eq!(post("/day/26/part/1", "").status, 404);
+        assert_eq!(post("/day/1/part/3", "").status, 404);
+        assert_eq!(post("/day/1/render", "").status, 404);
+        let get = request("GET /day/1/part/1 HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(handle(&get, Duration::from_secs(60)).status, 405);
+
+        let response = post("/day/18/render?scale=2", ".#\n|.");
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, "image/png");
+        assert!(response.body.starts_with(b"\x89PNG"));
+        assert_eq!(post("/day/18/render?scale=0", ".#").status, 400);
+    }
+
+    /// Day 1 never finds a frequency twice when the changes add up to more
+    /// than zero, and is cancelled.
+    #[test]
+    fn timeout() {
+        let parsed = request(
+            "POST /day/1/part/2 HTTP/1.1\r\nContent-Length: 2\r\n\r\n+1",
+        )
+        .unwrap();
+        let response = handle(&parsed, Duration::from_millis(100));
+        assert_eq!(response.status, 503);
+        assert_eq!(
+            String::from_utf8(response.body).unwrap(),
+            r#"{"day":1,"error":"timed out after 0.100s"}"#
+        );
+    }
+
+    #[test]
+    fn responses() {
+        let mut out = Vec::new();
+        write_response(&mut out, &Response::error(404, "Not found")).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\n\
+             Content-Length: 21\r\nConnection: close\r\n\r\n\
+             {\"error\":\"Not found\"}"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use error::Error;
+pub use error::Result;
+use generate::Rng;
+use input::Input;
+use profile;
+use render::Image;
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::panic;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A day's puzzle: parse the input once, then solve both parts from it.
+pub trait Solution {
+    /// The parsed puzzle input, which is cached with the text it was parsed
+    /// from.
+    type Input: 'static;
+    /// Parameters of the puzzle that are not part of the input.
+    type Params: Params;
+    /// The answer to part 1.
+    type Part1: Display;
+    /// The answer to part 2.
+    type Part2: Display;
+
+    fn parse(lines: &[&str]) -> Result<Self::Input>;
+    fn part1(input: &Self::Input, params: &Self::Params)
+        -> Result<Self::Part1>;
+    fn part2(input: &Self::Input, params: &Self::Params)
+        -> Result<Self::Part2>;
+
+    /// Draw the puzzle state that leads to the answer, for days where that
+    /// is a picture, such as the message in the sky on day 10.
+    fn render(_input: &Self::Input, _params: &Self::Params) -> Result<Image> {
+        Err(Error::Param(
+            "There is nothing to render on this day".to_owned(),
+        ))
+    }
+
+    /// A random input in the format of the puzzle that can be solved.
+    /// `size` scales the input, such as the number of lines or the width of
+    /// a map; what it means is documented per day.
+    fn generate(rng: &mut Rng, size: usize) -> String;
+}
+
+/// Puzzle parameters that are given in the puzzle text rather than the input,
+/// such as the number of workers on day 7. They default to the values of the
+/// puzzle, and can be overridden by name, e.g. to solve the examples.
+pub trait Params: Default {
+    /// Override the parameter with the given name.
+    fn set(&mut self, name: &str, value: &str) -> Result<()>;
+}
+
+impl Params for () {
+    fn set(&mut self, name: &str, _value: &str) -> Result<()> {
+        Err(Error::Param(format!("Unknown parameter `{}`", name)))
+    }
+}
+
+/// Which parts of a puzzle to solve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Part {
+    One,
+    Two,
+    Both,
+}
+
+impl Part {
+    fn includes_one(self) -> bool {
+        self != Part::Two
+    }
+
+    fn includes_two(self) -> bool {
+        self != Part::One
+    }
+}
+
+impl FromStr for Part {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Part, String> {
+        match s {
+            "1" => Ok(Part::One),
+            "2" => Ok(Part::Two),
+            "both" => Ok(Part::Both),
+            _ => Err(format!("Unknown part `{}`, expected 1, 2 or both", s)),
+        }
+    }
+}
+
+/// The answers to the parts that were solved, formatted for display.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Answers {
+    pub part1: Option<String>,
+    pub part2: Option<String>,
+    /// Messages reported with `diagnostic` while solving.
+    pub diagnostics: Vec<String>,
+}
+
+thread_local! {
+    static DIAGNOSTICS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Report progress or intermediate results of a solution, such as the attack
+/// powers tried on day 15. They are returned with the answers rather than
+/// printed, so that callers decide whether and where to show them.
+pub fn diagnostic<M: Into<String>>(message: M) {
+    DIAGNOSTICS
+        .with(|diagnostics| diagnostics.borrow_mut().push(message.into()));
+}
+
+fn take_diagnostics() -> Vec<String> {
+    DIAGNOSTICS.with(|diagnostics| diagnostics.replace(Vec::new()))
+}
+
+/// The payload a cancelled solution unwinds with from `checkpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+thread_local! {
+    static CANCEL: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Cancel the solutions run on this thread once `flag` is set, or never with
+/// `None`.
+pub fn cancel_with(flag: Option<Arc<AtomicBool>>) {
+    CANCEL.with(|cancel| *cancel.borrow_mut() = flag);
+}
+
+/// Stop the solution running on this thread if it was cancelled, by
+/// unwinding with `Cancelled` (without a panic message). Long loops, such as
+/// the rounds of a simulation, call this so that solutions taking too long
+/// can be stopped.
+pub fn checkpoint() {
+    let cancelled = CANCEL.with(|cancel| {
+        cancel
+            .borrow()
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    });
+    if cancelled {
+        panic::resume_unwind(Box::new(Cancelled));
+    }
+}
+
+/// How long parsing and solving each of the parts took.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Timings {
+    pub parse: Duration,
+    pub part1: Option<Duration>,
+    pub part2: Option<Duration>,
+}
+
+impl Timings {
+    /// The time taken by all phases together.
+    pub fn total(&self) -> Duration {
+        self.parse
+            + self.part1.unwrap_or_default()
+            + self.part2.unwrap_or_default()
+    }
+}
+
+/// The default parameters, overridden with the given name/value pairs.
+fn params<S: Solution>(overrides: &[(String, String)]) -> Result<S::Params> {
+    let mut params = S::Params::default();
+    for (name, value) in overrides {
+        params.set(name, value)?;
+    }
+    Ok(params)
+}
+
+/// Parse the input, unless it was parsed before, and solve the requested
+/// parts, overriding the default parameters with the given name/value pairs.
+pub fn solve<S: Solution + 'static>(
+    input: &Input,
+    part: Part,
+    overrides: &[(String, String)],
+) -> Result<(Answers, Timings)> {
+    let params = params::<S>(overrides)?;
+
+    // Drop the diagnostics of an earlier solution that failed.
+    take_diagnostics();
+
+    let (input, parse) = profile::span("parse", || input.parsed::<S>())?;
+    let mut timings = Timings {
+        parse,
+        ..Timings::default()
+    };
+    let mut answers = Answers::default();
+
+    if part.includes_one() {
+        let start = Instant::now();
+        let answer = profile::span("part 1", || S::part1(&input, &params))?;
+        answers.part1 = Some(answer.to_string());
+        timings.part1 = Some(start.elapsed());
+    }
+    if part.includes_two() {
+        let start = Instant::now();
+        let answer = profile::span("part 2", || S::part2(&input, &params))?;
+        answers.part2 = Some(answer.to_string());
+        timings.part2 = Some(start.elapsed());
+    }
+
+    answers.diagnostics = take_diagnostics();
+    Ok((answers, timings))
+}
+
+/// Parse the input, unless it was parsed before, and draw the puzzle state,
+/// overriding the default parameters with the given name/value pairs.
+pub fn render<S: Solution + 'static>(
+    input: &Input,
+    overrides: &[(String, String)],
+) -> Result<Image> {
+    let params = params::<S>(overrides)?;
+    let (input, _) = input.parsed::<S>()?;
+    S::render(&input, &params)
+}
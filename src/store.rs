@@ -0,0 +1,321 @@
+//! The answers confirmed on the Advent of Code website, kept in a small TOML
+//! file so that `aoc check` can tell when a day's answer changes. Besides the
+//! confirmed answers, the hints the website gives for wrong answers are kept:
+//! the lowest answer that was too high and the highest that was too low.
+//!
+//! ```toml
+//! [day15]
+//! part1 = "207542"
+//! part2_too_high = 65000
+//! part2_too_low = 60000
+//! ```
+use parse::{self, Line};
+use solution::Result;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+/// Where `aoc` keeps the answers, next to the puzzle inputs.
+pub static DEFAULT_PATH: &str = "input/answers.toml";
+
+/// What is known about the answer to a part.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record {
+    /// The answer the website accepted.
+    pub answer: Option<String>,
+    /// The lowest answer the website said was too high.
+    pub too_high: Option<i64>,
+    /// The highest answer the website said was too low.
+    pub too_low: Option<i64>,
+}
+
+/// The records of the parts of every day, by day and part.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Store {
+    records: BTreeMap<(u32, u32), Record>,
+}
+
+/// How an answer compares to what is known about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The answer is the confirmed one.
+    Correct,
+    /// The answer is not the confirmed one, given here.
+    Wrong(String),
+    /// The answer is at least this one, which was too high.
+    TooHigh(i64),
+    /// The answer is at most this one, which was too low.
+    TooLow(i64),
+    /// Nothing rules the answer out, but it has not been confirmed.
+    Unconfirmed,
+}
+
+impl Verdict {
+    /// Whether the answer is known to be wrong.
+    pub fn is_wrong(&self) -> bool {
+        match self {
+            Verdict::Correct | Verdict::Unconfirmed => false,
+            Verdict::Wrong(_) | Verdict::TooHigh(_) | Verdict::TooLow(_) => {
+                true
+            }
+        }
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Correct => write!(f, "correct"),
+            Verdict::Wrong(answer) if answer.contains('\n') => {
+                write!(f, "wrong, expected:\n{}", answer)
+            }
+            Verdict::Wrong(answer) => write!(f, "wrong, expected {}", answer),
+            Verdict::TooHigh(answer) => {
+                write!(f, "too high, {} was already too high", answer)
+            }
+            Verdict::TooLow(answer) => {
+                write!(f, "too low, {} was already too low", answer)
+            }
+            Verdict::Unconfirmed => write!(f, "unconfirmed"),
+        }
+    }
+}
+
+/// Parse a TOML string like `"a\nb"`, or a bare integer.
+fn parse_value(value: Line) -> Result<String> {
+    let text = match value.after("\"") {
+        Ok(text) => text,
+        Err(_) => return value.parse::<i64>().map(|n| n.to_string()),
+    };
+
+    let mut string = String::new();
+    let mut chars = text.text.char_indices();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '"' if offset + 1 == text.text.len() => return Ok(string),
+            '"' => return Err(text.error_at(offset + 1, "expected the end")),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => string.push('\n'),
+                Some((_, 't')) => string.push('\t'),
+                Some((_, c)) if c == '"' || c == '\\' => string.push(c),
+                _ => return Err(text.error_at(offset, "invalid escape")),
+            },
+            c => string.push(c),
+        }
+    }
+    Err(text.error_at(text.text.len(), "expected `\"`"))
+}
+
+/// Write a TOML string.
+fn write_value(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl Store {
+    /// Parse the `[dayNN]` tables of a store, with `partN`, `partN_too_high`
+    /// and `partN_too_low` keys.
+    pub fn parse(lines: &[&str]) -> Result<Store> {
+        let mut store = Store::default();
+        let mut day = None;
+
+        for line in parse::lines(lines) {
+            let line = line.trim();
+            if line.is_empty() || line.text.starts_with('#') {
+                continue;
+            }
+            if let Ok(table) = line.after("[day") {
+                let (number, rest) = table.split_once("]")?;
+                match number.parse() {
+                    Ok(n) if (1..=25).contains(&n) && rest.is_empty() => {
+                        day = Some(n)
+                    }
+                    _ => {
+                        return Err(
+                            line.error("expected a table like `[day15]`")
+                        )
+                    }
+                }
+                continue;
+            }
+
+            let day = day
+                .ok_or_else(|| line.error("expected a table like `[day15]`"))?;
+            let (key, value) = line.split_once("=")?;
+            let (key, value) = (key.trim(), value.trim());
+            let (part, field) = match key.after("part1") {
+                Ok(field) => (1, field),
+                Err(_) => (
+                    2,
+                    key.after("part2").map_err(|_| {
+                        key.error(format!("unknown key `{}`", key.text))
+                    })?,
+                ),
+            };
+
+            let record = store.records.entry((day, part)).or_default();
+            match field.text {
+                "" => record.answer = Some(parse_value(value)?),
+                "_too_high" => record.too_high = Some(value.parse()?),
+                "_too_low" => record.too_low = Some(value.parse()?),
+                _ => {
+                    return Err(key.error(format!("unknown key `{}`", key.text)))
+                }
+            }
+        }
+
+        Ok(store)
+    }
+
+    pub fn record(&self, day: u32, part: u32) -> Option<&Record> {
+        self.records.get(&(day, part))
+    }
+
+    /// Record the answer the website accepted, returning the one confirmed
+    /// before if it was different.
+    pub fn confirm(
+        &mut self,
+        day: u32,
+        part: u32,
+        answer: String,
+    ) -> Option<String> {
+        let record = self.records.entry((day, part)).or_default();
+        let earlier = record.answer.replace(answer);
+        earlier.filter(|earlier| Some(earlier) != record.answer.as_ref())
+    }
+
+    /// Record that the website said an answer was too high.
+    pub fn too_high(&mut self, day: u32, part: u32, answer: i64) {
+        let record = self.records.entry((day, part)).or_default();
+        record.too_high =
+            Some(record.too_high.map_or(answer, |n| n.min(answer)));
+    }
+
+    /// Record that the website said an answer was too low.
+    pub fn too_low(&mut self, day: u32, part: u32, answer: i64) {
+        let record = self.records.entry((day, part)).or_default();
+        record.too_low = Some(record.too_low.map_or(answer, |n| n.max(answer)));
+    }
+
+    /// Compare an answer with what is known about it. Only answers that are
+    /// numbers can be too high or too low.
+    pub fn check(&self, day: u32, part: u32, answer: &str) -> Verdict {
+        let record = match self.record(day, part) {
+            Some(record) => record,
+            None => return Verdict::Unconfirmed,
+        };
+        if record.answer.as_deref() == Some(answer) {
+            return Verdict::Correct;
+        }
+
+        let number = answer.parse::<i64>().ok();
+        match (number, record.too_high, record.too_low) {
+            (Some(n), Some(high), _) if n >= high => Verdict::TooHigh(high),
+            (Some(n), _, Some(low)) if n <= low => Verdict::TooLow(low),
+            _ => match record.answer {
+                Some(ref confirmed) => Verdict::Wrong(confirmed.clone()),
+                None => Verdict::Unconfirmed,
+            },
+        }
+    }
+}
+
+/// Writes the store as TOML, a table per day.
+impl Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "# Answers confirmed on adventofcode.com, checked by `aoc check`."
+        )?;
+        let mut day = None;
+        for (&(d, part), record) in &self.records {
+            if day != Some(d) {
+                write!(f, "\n[day{:02}]\n", d)?;
+                day = Some(d);
+            }
+            if let Some(ref answer) = record.answer {
+                write!(f, "part{} = ", part)?;
+                write_value(f, answer)?;
+                writeln!(f)?;
+            }
+            if let Some(high) = record.too_high {
+                writeln!(f, "part{}_too_high = {}", part, high)?;
+            }
+            if let Some(low) = record.too_low {
+                writeln!(f, "part{}_too_low = {}", part, low)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let text = "# Answers\n\n[day10]\npart1 = \"#  #\\n####\"\n\n\
+                    [day15]\npart1 = 207542\npart2_too_high = 65000\n\
+                    part2_too_low = 60000\n";
+        let input = text.lines().collect::<Vec<_>>();
+        let store = Store::parse(&input).unwrap();
+        assert_eq!(
+            store.record(10, 1).unwrap().answer,
+            Some("#  #\n####".to_owned())
+        );
+        assert_eq!(store.record(15, 2).unwrap().too_low, Some(60000));
+
+        let written = store.to_string();
+        let again = Store::parse(&written.lines().collect::<Vec<_>>()).unwrap();
+        assert_eq!(again, store);
+        assert!(written.contains("\n[day15]\npart1 = \"207542\"\n"));
+    }
+
+    #[test]
+    fn errors() {
+        let err = Store::parse(&["part1 = 3"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "<input>:1:1: expected a table like `[day15]`"
+        );
+        let err = Store::parse(&["[day3]", "part3 = 1"]).unwrap_err();
+        assert_eq!(err.to_string(), "<input>:2:1: unknown key `part3`");
+        let err = Store::parse(&["[day3]", "part1 = \"a\"b\""]).unwrap_err();
+        assert_eq!(err.to_string(), "<input>:2:12: expected the end");
+        let err = Store::parse(&["[day3]", "part1_too_low = x"]).unwrap_err();
+        assert!(err.to_string().starts_with("<input>:2:17: invalid number"));
+        assert!(Store::parse(&["[day26]"]).is_err());
+    }
+
+    #[test]
+    fn verdicts() {
+        let mut store = Store::default();
+        assert_eq!(store.check(1, 1, "3"), Verdict::Unconfirmed);
+
+        store.too_high(1, 1, 100);
+        store.too_high(1, 1, 90);
+        store.too_low(1, 1, 10);
+        assert_eq!(store.check(1, 1, "95"), Verdict::TooHigh(90));
+        assert_eq!(store.check(1, 1, "10"), Verdict::TooLow(10));
+        assert_eq!(store.check(1, 1, "50"), Verdict::Unconfirmed);
+
+        assert_eq!(store.confirm(1, 1, "42".to_owned()), None);
+        assert_eq!(store.confirm(1, 1, "42".to_owned()), None);
+        assert_eq!(store.check(1, 1, "42"), Verdict::Correct);
+        assert_eq!(store.check(1, 1, "43"), Verdict::Wrong("42".to_owned()));
+        assert_eq!(store.check(1, 1, "abc"), Verdict::Wrong("42".to_owned()));
+        assert_eq!(store.check(1, 1, "95"), Verdict::TooHigh(90));
+        assert!(Verdict::TooLow(10).is_wrong());
+        assert!(!Verdict::Unconfirmed.is_wrong());
+        assert_eq!(store.confirm(1, 1, "41".to_owned()), Some("42".to_owned()));
+    }
+}
@@ -0,0 +1,396 @@
+//! Tracing ElfCode programs as they run on the machine: how often every
+//! instruction runs, which jumps back go round the loops, and the registers at
+//! chosen instructions. A program that comes back to an instruction with the
+//! same registers is caught repeating itself, and stopped, since it would
+//! never halt.
+use disasm;
+use elfcode::{Machine, Program, Word};
+use json::Json;
+use solution::Result;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// How many of the hottest instructions the table shows.
+const HOTTEST: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// The instructions to take snapshots of the registers at, before they
+    /// run, and to watch for repeated registers.
+    pub at: Vec<usize>,
+    /// How many instructions to run at most.
+    pub steps: u64,
+    /// How many snapshots to keep; later ones are still checked for
+    /// repeats.
+    pub snapshots: usize,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            at: Vec::new(),
+            steps: 10_000_000,
+            snapshots: 100,
+        }
+    }
+}
+
+/// The registers before the instruction at `ip` ran, after `step`
+/// instructions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Snapshot<W> {
+    pub step: u64,
+    pub ip: usize,
+    pub registers: Vec<W>,
+}
+
+/// The program came back to the instruction at `ip` after `step`
+/// instructions with the registers it had there after `first`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Repeat {
+    pub ip: usize,
+    pub first: u64,
+    pub step: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Trace<W> {
+    /// The number of instructions run.
+    pub steps: u64,
+    pub halted: bool,
+    /// How often every instruction ran.
+    pub hits: Vec<u64>,
+    /// How often every jump from an instruction back to an earlier one, or
+    /// to itself, was taken.
+    pub back_edges: BTreeMap<(usize, usize), u64>,
+    pub snapshots: Vec<Snapshot<W>>,
+    pub repeat: Option<Repeat>,
+}
+
+/// Run the program on a machine with the given registers, until it halts,
+/// repeats itself or has run `options.steps` instructions.
+pub fn run<W: Word>(
+    program: &Program<W>,
+    registers: Vec<W>,
+    options: &Options,
+) -> Result<Trace<W>> {
+    let mut machine = Machine::with_registers(registers);
+    if let Some(register) = program.ip_register {
+        machine = machine.bind_ip(register)?;
+    }
+    let mut trace = Trace {
+        steps: 0,
+        halted: false,
+        hits: vec![0; program.instrs.len()],
+        back_edges: BTreeMap::new(),
+        snapshots: Vec::new(),
+        repeat: None,
+    };
+    let len = program.instrs.len();
+    let mut watched = vec![false; len];
+    for &ip in options.at.iter().filter(|&&ip| ip < len) {
+        watched[ip] = true;
+    }
+    let mut seen = BTreeMap::new();
+    let mut previous = None;
+
+    machine.run_until(&program.instrs, |machine| {
+        if trace.steps == options.steps {
+            return true;
+        }
+        let ip = machine.ip.to_index().expect("the machine has not halted");
+        if watched[ip] {
+            // The instruction reads its own index from the bound register,
+            // whatever was written to it before.
+            let mut registers = machine.registers.clone();
+            if let Some(register) = program.ip_register {
+                registers[register] = W::from_index(ip);
+            }
+            if let Some(&first) = seen.get(&(ip, registers.clone())) {
+                trace.repeat = Some(Repeat {
+                    ip,
+                    first,
+                    step: trace.steps,
+                });
+                return true;
+            }
+            if trace.snapshots.len() < options.snapshots {
+                trace.snapshots.push(Snapshot {
+                    step: trace.steps,
+                    ip,
+                    registers: registers.clone(),
+                });
+            }
+            seen.insert((ip, registers), trace.steps);
+        }
+
+        match previous {
+            Some(from) if ip <= from => {
+                *trace.back_edges.entry((from, ip)).or_insert(0) += 1
+            }
+            _ => {}
+        }
+        previous = Some(ip);
+        trace.hits[ip] += 1;
+        trace.steps += 1;
+        false
+    })?;
+
+    trace.halted = machine.halted(&program.instrs);
+    Ok(trace)
+}
+
+impl<W: Word> Trace<W> {
+    /// The instructions that ran, the most often first.
+    fn hottest(&self) -> Vec<usize> {
+        let mut hottest = (0..self.hits.len())
+            .filter(|&idx| self.hits[idx] > 0)
+            .collect::<Vec<_>>();
+        hottest.sort_by_key(|&idx| std::cmp::Reverse(self.hits[idx]));
+        hottest
+    }
+
+    /// The profile as tables: the hottest instructions, the jumps back, the
+    /// snapshots and the repeated registers, if any.
+    pub fn table(&self, program: &Program<W>) -> String {
+        let instrs = (0..program.instrs.len())
+            .map(|idx| format!("{:02} {}", idx, program.instrs[idx]))
+            .collect::<Vec<_>>();
+        let width = instrs.iter().map(String::len).max().unwrap_or(0);
+        let jumps = disasm::jumps(program);
+        let instr = |idx: usize| {
+            let line = format!(
+                "{:w$}  # {}",
+                instrs[idx],
+                disasm::pseudocode(program, &jumps, idx),
+                w = width
+            );
+            line.trim_end().to_owned()
+        };
+        let percent = |hits: u64| 100.0 * hits as f64 / self.steps as f64;
+
+        let mut table = String::new();
+        let outcome = if self.halted {
+            "halted"
+        } else if self.repeat.is_some() {
+            "stopped at repeated registers"
+        } else {
+            "stopped before halting"
+        };
+        writeln!(table, "Ran {} instructions, and {}.", self.steps, outcome)
+            .expect("writing to a string cannot fail");
+
+        table += "\nHottest instructions\n";
+        writeln!(table, "{:>12} {:>7}  Instruction", "Hits", "%")
+            .expect("writing to a string cannot fail");
+        for idx in self.hottest().into_iter().take(HOTTEST) {
+            writeln!(
+                table,
+                "{:>12} {:>6.2}%  {}",
+                self.hits[idx],
+                percent(self.hits[idx]),
+                instr(idx)
+            )
+            .expect("writing to a string cannot fail");
+        }
+
+        if !self.back_edges.is_empty() {
+            table += "\nLoop back-edges\n";
+            writeln!(table, "{:>12}  Jump", "Taken")
+                .expect("writing to a string cannot fail");
+            let mut edges = self.back_edges.iter().collect::<Vec<_>>();
+            edges.sort_by_key(|&(_, &taken)| std::cmp::Reverse(taken));
+            for (&(from, to), taken) in edges {
+                writeln!(table, "{:>12}  {:02} -> {:02}", taken, from, to)
+                    .expect("writing to a string cannot fail");
+            }
+        }
+
+        if !self.snapshots.is_empty() {
+            table += "\nSnapshots\n";
+            writeln!(table, "{:>12}  {:>2}  Registers", "Step", "ip")
+                .expect("writing to a string cannot fail");
+            for snapshot in &self.snapshots {
+                let registers = snapshot
+                    .registers
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>();
+                writeln!(
+                    table,
+                    "{:>12}  {:02}  {}",
+                    snapshot.step,
+                    snapshot.ip,
+                    registers.join(" ")
+                )
+                .expect("writing to a string cannot fail");
+            }
+        }
+
+        if let Some(repeat) = self.repeat {
+            writeln!(
+                table,
+                "\nAfter {} instructions, the registers at {:02} were those \
+                 after {}: the program never halts.",
+                repeat.step, repeat.ip, repeat.first
+            )
+            .expect("writing to a string cannot fail");
+        }
+        table
+    }
+}
+
+impl Trace<i64> {
+    pub fn json(&self) -> Json {
+        let count = |n: u64| Json::from(n as f64);
+        let index = |n: usize| Json::from(n as f64);
+        Json::object(vec![
+            ("instructions", count(self.steps)),
+            ("halted", Json::from(self.halted)),
+            (
+                "hits",
+                Json::Array(self.hits.iter().map(|&n| count(n)).collect()),
+            ),
+            (
+                "hottest",
+                Json::Array(self.hottest().into_iter().map(index).collect()),
+            ),
+            (
+                "back_edges",
+                Json::Array(
+                    self.back_edges
+                        .iter()
+                        .map(|(&(from, to), &taken)| {
+                            Json::object(vec![
+                                ("from", index(from)),
+                                ("to", index(to)),
+                                ("taken", count(taken)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "snapshots",
+                Json::Array(
+                    self.snapshots
+                        .iter()
+                        .map(|snapshot| {
+                            Json::object(vec![
+                                ("step", count(snapshot.step)),
+                                ("ip", index(snapshot.ip)),
+                                (
+                                    "registers",
+                                    Json::Array(
+                                        snapshot
+                                            .registers
+                                            .iter()
+                                            .map(|&value| {
+                                                Json::from(value as f64)
+                                            })
+                                            .collect(),
+                                    ),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "repeat",
+                match self.repeat {
+                    Some(repeat) => Json::object(vec![
+                        ("ip", index(repeat.ip)),
+                        ("first", count(repeat.first)),
+                        ("step", count(repeat.step)),
+                    ]),
+                    None => Json::Null,
+                },
+            ),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(lines: &[&str]) -> Program<i64> {
+        Program::parse(lines, 6).unwrap()
+    }
+
+    #[test]
+    fn profile() {
+        let program = program(&[
+            "#ip 5",
+            "seti 3 0 1",
+            "addi 1 -1 1",
+            "eqri 1 0 2",
+            "addr 2 5 5",
+            "seti 0 0 5",
+        ]);
+        let trace = run(&program, vec![0; 6], &Options::default()).unwrap();
+        assert!(trace.halted);
+        assert_eq!(trace.steps, 12);
+        assert_eq!(trace.hits, [1, 3, 3, 3, 2]);
+        assert_eq!(trace.back_edges, btreemap! { (4, 1) => 2 });
+        assert_eq!(trace.hottest(), [1, 2, 3, 4, 0]);
+        assert_eq!(trace.repeat, None);
+        assert_eq!(
+            trace.json().to_string(),
+            "{\"instructions\":12,\"halted\":true,\"hits\":[1,3,3,3,2],\
+             \"hottest\":[1,2,3,4,0],\
+             \"back_edges\":[{\"from\":4,\"to\":1,\"taken\":2}],\
+             \"snapshots\":[],\"repeat\":null}"
+        );
+
+        let options = Options {
+            steps: 5,
+            ..Options::default()
+        };
+        let trace = run(&program, vec![0; 6], &options).unwrap();
+        assert!(!trace.halted);
+        assert_eq!(trace.hits, [1, 1, 1, 1, 1]);
+    }
+
+    /// A counter that wraps around comes back to where it started.
+    #[test]
+    fn repeats() {
+        let program =
+            program(&["#ip 5", "addi 1 1 1", "bani 1 3 1", "seti -1 0 5"]);
+        let options = Options {
+            at: vec![1],
+            snapshots: 2,
+            ..Options::default()
+        };
+        let trace = run(&program, vec![0; 6], &options).unwrap();
+        assert!(!trace.halted);
+        assert_eq!(
+            trace.repeat,
+            Some(Repeat {
+                ip: 1,
+                first: 1,
+                step: 13
+            })
+        );
+        assert_eq!(
+            trace.snapshots,
+            [
+                Snapshot {
+                    step: 1,
+                    ip: 1,
+                    registers: vec![0, 1, 0, 0, 0, 1]
+                },
+                Snapshot {
+                    step: 4,
+                    ip: 1,
+                    registers: vec![0, 2, 0, 0, 0, 1]
+                },
+            ]
+        );
+        assert!(trace.table(&program).ends_with(
+            "After 13 instructions, the registers at 01 were those after 1: \
+             the program never halts.\n"
+        ));
+    }
+}
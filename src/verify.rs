@@ -0,0 +1,334 @@
+//! Cross-checks of days that solve (part of) their puzzle with two different
+//! algorithms, which should agree on every input. They are run on the puzzle
+//! input and on generated inputs by `aoc run <day> --verify`, and by the tests
+//! below. When the algorithms disagree, the input is minimised to the fewest
+//! lines on which they still do.
+use december03::{self, December03};
+use december10::{self, December10};
+use december19::{self, December19};
+use december21::{self, December21};
+use generate::{self, Options};
+use input::Input;
+use solution::Result;
+use std::fmt::{self, Display};
+
+/// Two algorithms for the same puzzle that should give the same answer.
+pub struct Pair {
+    pub day: u32,
+    pub names: (&'static str, &'static str),
+    /// Run both algorithms on an input. Inputs that cannot be parsed, or
+    /// that the algorithms cannot be run on, are an error. The input is
+    /// parsed as the day's, so that pairs of the same day share it.
+    compare: fn(&Input) -> Result<(String, String)>,
+    /// Whether counterexamples can be minimised by leaving out lines. Not so
+    /// for programs, which could then never halt.
+    shrink: bool,
+}
+
+pub static PAIRS: [Pair; 6] = [
+    Pair {
+        day: 3,
+        names: ("overlap_area_naive", "overlap_area"),
+        compare: compare_overlap_area,
+        shrink: true,
+    },
+    Pair {
+        day: 10,
+        names: ("conserve_momentum", "conserve_momentum_bsearch"),
+        compare: compare_conserve_momentum,
+        shrink: true,
+    },
+    Pair {
+        day: 19,
+        names: ("run", "decompiled"),
+        compare: compare_decompiled,
+        shrink: false,
+    },
+    Pair {
+        day: 19,
+        names: ("optimised", "factorize"),
+        compare: compare_factorize,
+        shrink: false,
+    },
+    Pair {
+        day: 21,
+        names: ("run", "simplified"),
+        compare: compare_simplified,
+        shrink: false,
+    },
+    Pair {
+        day: 21,
+        names: ("last_compared", "simplified"),
+        compare: compare_last_compared,
+        shrink: false,
+    },
+];
+
+fn compare_overlap_area(input: &Input) -> Result<(String, String)> {
+    let (claims, _) = input.parsed::<December03>()?;
+    Ok((
+        december03::overlap_area_naive(&claims).to_string(),
+        december03::overlap_area(&claims).to_string(),
+    ))
+}
+
+fn compare_conserve_momentum(input: &Input) -> Result<(String, String)> {
+    let (sky, _) = input.parsed::<December10>()?;
+    let (_, naive) = december10::conserve_momentum(&mut (*sky).clone());
+    let (_, binary) =
+        december10::conserve_momentum_bsearch(&mut (*sky).clone());
+    Ok((format!("{} seconds", naive), format!("{} seconds", binary)))
+}
+
+fn compare_decompiled(input: &Input) -> Result<(String, String)> {
+    let (program, _) = input.parsed::<December19>()?;
+    let n = december19::number(&program, 0)?;
+    Ok((
+        december19::run(&program)?.to_string(),
+        december19::decompiled(n).to_string(),
+    ))
+}
+
+/// Part 2 of the program, which the optimising interpreter runs, against
+/// summing the divisors of its number directly.
+fn compare_factorize(input: &Input) -> Result<(String, String)> {
+    let (program, _) = input.parsed::<December19>()?;
+    let n = december19::number(&program, 1)?;
+    Ok((
+        december19::optimised(&program, 1)?.to_string(),
+        december19::factorize(n).to_string(),
+    ))
+}
+
+/// Running the program until it halts is what `simplified` avoids, so only
+/// the first values it compares with register 0 are checked.
+fn compare_simplified(input: &Input) -> Result<(String, String)> {
+    const COUNT: usize = 10;
+    let (program, _) = input.parsed::<December21>()?;
+    let constants = december21::constants(&program)?;
+    let mut simplified = december21::simplified(constants, 0);
+    simplified.truncate(COUNT);
+    let run = december21::compared(&program, 0, simplified.len())?;
+    Ok((format!("{:?}", run), format!("{:?}", simplified)))
+}
+
+fn compare_last_compared(input: &Input) -> Result<(String, String)> {
+    let (program, _) = input.parsed::<December21>()?;
+    let simplified =
+        december21::simplified(december21::constants(&program)?, 0);
+    Ok((
+        december21::last_compared(&program)?.to_string(),
+        format!(
+            "{}",
+            simplified.last().expect("at least one value is compared")
+        ),
+    ))
+}
+
+/// The pairs of algorithms of a day.
+pub fn pairs(day: u32) -> Vec<&'static Pair> {
+    PAIRS.iter().filter(|pair| pair.day == day).collect()
+}
+
+/// Inputs to cross-check a day with: generated with seeds from 0 up to
+/// `count`, and sizes from 1 to 20.
+pub fn generated(day: u32, count: usize) -> Vec<Input> {
+    (0..count)
+        .filter_map(|seed| {
+            let options = Options {
+                seed: seed as u64,
+                size: 1 + seed % 20,
+            };
+            generate::input(day, options)
+        })
+        .collect()
+}
+
+/// An input the algorithms of a pair disagree on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    /// The name of the input the disagreement was found on.
+    pub name: String,
+    /// The fewest lines of that input found on which the algorithms still
+    /// disagree.
+    pub lines: Vec<String>,
+    pub answers: (String, String),
+}
+
+/// The outcome of cross-checking a pair.
+pub struct Report {
+    pub pair: &'static Pair,
+    /// The number of inputs the algorithms agreed on.
+    pub agreed: usize,
+    pub counterexample: Option<Counterexample>,
+}
+
+impl Pair {
+    /// The answers of both algorithms, if they differ.
+    pub fn check(&self, input: &Input) -> Result<Option<(String, String)>> {
+        let (a, b) = (self.compare)(input)?;
+        Ok(if a == b { None } else { Some((a, b)) })
+    }
+
+    /// Leave out as many lines as possible of an input the algorithms
+    /// disagree on, while they still do: first large chunks, then smaller
+    /// ones, down to single lines.
+    pub fn minimise(
+        &self,
+        lines: &[&str],
+        answers: (String, String),
+    ) -> (Vec<String>, (String, String)) {
+        let mut lines = lines.to_vec();
+        let mut answers = answers;
+        let mut chunk = if self.shrink { lines.len() / 2 } else { 0 };
+        while chunk > 0 {
+            let mut removed = false;
+            let mut start = 0;
+            while start < lines.len() {
+                let end = (start + chunk).min(lines.len());
+                let candidate = [&lines[..start], &lines[end..]].concat();
+                let input = Input::new("candidate", candidate.join("\n"));
+                match self.check(&input) {
+                    Ok(Some(found)) => {
+                        lines = candidate;
+                        answers = found;
+                        removed = true;
+                    }
+                    _ => start += chunk,
+                }
+            }
+            chunk = if removed {
+                chunk.min(lines.len())
+            } else {
+                chunk / 2
+            };
+        }
+        (lines.iter().map(|&line| line.to_owned()).collect(), answers)
+    }
+
+    /// Cross-check the algorithms on every input, up to the first one they
+    /// disagree on. An input they cannot be run on is an error.
+    pub fn verify(&'static self, inputs: &[Input]) -> Result<Report> {
+        let mut agreed = 0;
+        for input in inputs {
+            let answers =
+                self.check(input).map_err(|err| err.in_file(input.name()))?;
+            if let Some(answers) = answers {
+                let (lines, answers) = self.minimise(&input.lines(), answers);
+                return Ok(Report {
+                    pair: self,
+                    agreed,
+                    counterexample: Some(Counterexample {
+                        name: input.name().to_owned(),
+                        lines,
+                        answers,
+                    }),
+                });
+            }
+            agreed += 1;
+        }
+
+        Ok(Report {
+            pair: self,
+            agreed,
+            counterexample: None,
+        })
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (a, b) = self.pair.names;
+        match self.counterexample {
+            None => write!(
+                f,
+                "Day {:>2}: {} and {} agree on {} inputs",
+                self.pair.day, a, b, self.agreed
+            ),
+            Some(ref counterexample) => {
+                writeln!(
+                    f,
+                    "Day {:>2}: {} and {} disagree on {}",
+                    self.pair.day, a, b, counterexample.name
+                )?;
+                writeln!(f, "  {}: {}", a, counterexample.answers.0)?;
+                writeln!(f, "  {}: {}", b, counterexample.answers.1)?;
+                write!(
+                    f,
+                    "They still disagree on these {} lines of it:",
+                    counterexample.lines.len()
+                )?;
+                for line in &counterexample.lines {
+                    write!(f, "\n{}", line)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use days;
+
+    /// Every pair agrees on the puzzle input and on generated inputs.
+    #[test]
+    fn agree() {
+        for pair in PAIRS.iter() {
+            let mut inputs = vec![Input::for_day(pair.day, None).unwrap()];
+            inputs.extend(generated(pair.day, 20));
+            let report = pair.verify(&inputs).unwrap();
+            assert!(report.counterexample.is_none(), "{}", report);
+            assert_eq!(report.agreed, inputs.len());
+        }
+    }
+
+    #[test]
+    fn pairs_of_days() {
+        assert_eq!(pairs(3).len(), 1);
+        assert!(pairs(5).is_empty());
+        assert!(PAIRS.iter().all(|pair| days::solver(pair.day).is_some()));
+    }
+
+    /// Claims that overlap are counted once by one algorithm and twice by
+    /// the other; everything but those claims is minimised away.
+    #[test]
+    fn minimise() {
+        fn compare(input: &Input) -> Result<(String, String)> {
+            let claims = december03::parse_claims(&input.lines())?;
+            let sum = december03::claim_overlaps(&claims)
+                .iter()
+                .map(|overlap| overlap.size.area())
+                .sum::<u32>();
+            Ok((
+                december03::overlap_area_naive(&claims).to_string(),
+                sum.to_string(),
+            ))
+        }
+        static DOUBLE_COUNTING: Pair = Pair {
+            day: 3,
+            names: ("overlap_area_naive", "sum of overlaps"),
+            compare,
+            shrink: true,
+        };
+
+        let input = Input::new(
+            "claims",
+            "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 2,2: 2x2\n\
+             #5 @ 20,20: 1x1\n",
+        );
+        let report = DOUBLE_COUNTING.verify(&[input]).unwrap();
+        assert_eq!(
+            report.counterexample,
+            Some(Counterexample {
+                name: "claims".to_owned(),
+                lines: vec!["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4", "#4 @ 2,2: 2x2"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                answers: ("6".to_owned(), "8".to_owned()),
+            })
+        );
+    }
+}
@@ -0,0 +1,438 @@
+//! Playing recorded simulations in the terminal. Frames are shown with the
+//! tile characters they were drawn from on their palette colours, and can be
+//! played, paused, stepped through, sped up or slowed down, and scrolled when
+//! they are larger than the terminal. When stdout is not a terminal, the
+//! frames are printed one after another instead.
+//!
+//! The terminal is put in non-canonical mode with `stty`, so this works on
+//! Unix-like systems without further dependencies.
+use record::Animation;
+use render::{Image, Rgb};
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+const MIN_DELAY: Duration = Duration::from_millis(10);
+const MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Shown below the frame; cut off on narrow terminals.
+static HELP: &str = "space: play/pause  n/p: step  +/-: speed  \
+                     arrows/hjkl: scroll  g/G: first/last  q: quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// Play or pause.
+    Pause,
+    /// Pause and show the next frame.
+    Next,
+    /// Pause and show the previous frame.
+    Previous,
+    First,
+    Last,
+    Faster,
+    Slower,
+    /// Move the viewport by a number of columns and rows.
+    Scroll(isize, isize),
+    Quit,
+}
+
+/// The keys in bytes read from the terminal, skipping unknown ones.
+pub fn keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut bytes = bytes.iter();
+    while let Some(&byte) = bytes.next() {
+        let key = match byte {
+            b' ' => Key::Pause,
+            b'n' | b'.' => Key::Next,
+            b'p' | b',' => Key::Previous,
+            b'g' => Key::First,
+            b'G' => Key::Last,
+            b'+' | b'=' => Key::Faster,
+            b'-' | b'_' => Key::Slower,
+            b'h' => Key::Scroll(-1, 0),
+            b'l' => Key::Scroll(1, 0),
+            b'k' => Key::Scroll(0, -1),
+            b'j' => Key::Scroll(0, 1),
+            b'H' => Key::Scroll(-10, 0),
+            b'L' => Key::Scroll(10, 0),
+            b'K' => Key::Scroll(0, -10),
+            b'J' => Key::Scroll(0, 10),
+            b'q' | 3 => Key::Quit,
+            // Arrow keys: ESC [ A to ESC [ D.
+            0x1b => match (bytes.next(), bytes.next()) {
+                (Some(b'['), Some(b'A')) => Key::Scroll(0, -1),
+                (Some(b'['), Some(b'B')) => Key::Scroll(0, 1),
+                (Some(b'['), Some(b'C')) => Key::Scroll(1, 0),
+                (Some(b'['), Some(b'D')) => Key::Scroll(-1, 0),
+                (None, _) => Key::Quit,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// The state of playing an animation in a viewport of the terminal.
+#[derive(Debug, Clone)]
+pub struct Viewer<'a> {
+    animation: &'a Animation,
+    frame: usize,
+    playing: bool,
+    delay: Duration,
+    /// The top left tile in the viewport.
+    origin: (usize, usize),
+    /// The number of columns and rows of the viewport.
+    size: (usize, usize),
+}
+
+impl<'a> Viewer<'a> {
+    /// A viewer that starts playing the animation at its own speed.
+    pub fn new(animation: &'a Animation, size: (usize, usize)) -> Viewer<'a> {
+        Viewer {
+            animation,
+            frame: 0,
+            playing: true,
+            delay: animation.delay.clamp(MIN_DELAY, MAX_DELAY),
+            origin: (0, 0),
+            size,
+        }
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    pub fn origin(&self) -> (usize, usize) {
+        self.origin
+    }
+
+    fn current(&self) -> Option<&Image> {
+        self.animation.frames.get(self.frame)
+    }
+
+    /// Change the size of the viewport, keeping it on the frame.
+    pub fn resize(&mut self, size: (usize, usize)) {
+        self.size = size;
+        self.scroll(0, 0);
+    }
+
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = self
+            .current()
+            .map_or((0, 0), |frame| (frame.width(), frame.height()));
+        let max_x = width.saturating_sub(self.size.0);
+        let max_y = height.saturating_sub(self.size.1);
+        self.origin = (
+            self.origin.0.saturating_add_signed(dx).min(max_x),
+            self.origin.1.saturating_add_signed(dy).min(max_y),
+        );
+    }
+
+    /// Handle a key, returning false to quit.
+    pub fn press(&mut self, key: Key) -> bool {
+        let last = self.animation.frames.len().saturating_sub(1);
+        match key {
+            Key::Pause => {
+                // Playing from the end starts over.
+                if !self.playing && self.frame == last {
+                    self.frame = 0;
+                }
+                self.playing = !self.playing;
+            }
+            Key::Next => {
+                self.playing = false;
+                self.frame = (self.frame + 1).min(last);
+            }
+            Key::Previous => {
+                self.playing = false;
+                self.frame = self.frame.saturating_sub(1);
+            }
+            Key::First => self.frame = 0,
+            Key::Last => self.frame = last,
+            Key::Faster => self.delay = (self.delay / 2).max(MIN_DELAY),
+            Key::Slower => self.delay = (self.delay * 2).min(MAX_DELAY),
+            Key::Scroll(dx, dy) => self.scroll(dx, dy),
+            Key::Quit => return false,
+        }
+        // Frames can differ in size.
+        self.scroll(0, 0);
+        true
+    }
+
+    /// Move on to the next frame if playing, pausing at the last one.
+    pub fn tick(&mut self) {
+        if !self.playing {
+            return;
+        }
+        if self.frame + 1 < self.animation.frames.len() {
+            self.frame += 1;
+            self.scroll(0, 0);
+        }
+        if self.frame + 1 >= self.animation.frames.len() {
+            self.playing = false;
+        }
+    }
+
+    /// The part of the frame in the viewport, one line per row, with the
+    /// tiles on their colours if `colour` is set.
+    pub fn draw(&self, colour: bool) -> Vec<String> {
+        let frame = match self.current() {
+            Some(frame) => frame,
+            None => return Vec::new(),
+        };
+        let (x0, y0) = self.origin;
+        let x1 = (x0 + self.size.0).min(frame.width());
+        let y1 = (y0 + self.size.1).min(frame.height());
+        let pixels = frame.pixels();
+
+        (y0..y1)
+            .map(|y| {
+                let row =
+                    &pixels[y * frame.width() + x0..y * frame.width() + x1];
+                let mut line = String::new();
+                let mut previous = None;
+                for &pixel in row {
+                    let rgb = frame.colours()[pixel as usize];
+                    if colour && previous != Some(rgb) {
+                        line.push_str(&paint(rgb));
+                        previous = Some(rgb);
+                    }
+                    line.push(frame.symbols()[pixel as usize]);
+                }
+                if colour {
+                    line.push_str("\x1b[0m");
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// The line below the frame.
+    pub fn status(&self) -> String {
+        let status = format!(
+            "frame {}/{}  {}  {}ms/frame  {}",
+            self.frame + 1,
+            self.animation.frames.len(),
+            if self.playing { "playing" } else { "paused" },
+            self.delay.as_millis(),
+            HELP
+        );
+        status.chars().take(self.size.0).collect()
+    }
+}
+
+/// The escape sequence to draw on a colour, in black or white, whichever
+/// reads better.
+fn paint(Rgb(r, g, b): Rgb) -> String {
+    let luma = 299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b);
+    let ink = if luma > 128_000 { 0 } else { 255 };
+    format!("\x1b[38;2;{0};{0};{0};48;2;{1};{2};{3}m", ink, r, g, b)
+}
+
+/// Print every frame with its number, for when stdout is not a terminal.
+pub fn plain<W: Write>(animation: &Animation, mut w: W) -> io::Result<()> {
+    for (n, frame) in animation.frames.iter().enumerate() {
+        writeln!(w, "Frame {}/{}", n + 1, animation.frames.len())?;
+        w.write_all(frame.text().as_bytes())?;
+    }
+    w.flush()
+}
+
+/// Play the animation in the terminal, or print its frames if stdout is not
+/// a terminal.
+pub fn view(animation: &Animation) -> io::Result<()> {
+    if io::stdout().is_terminal() {
+        interactive(animation)
+    } else {
+        plain(animation, io::stdout().lock())
+    }
+}
+
+/// Run `stty` on the controlling terminal.
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::from(File::open("/dev/tty")?))
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("stty failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// The number of columns and rows of the terminal, if it reports them.
+fn terminal_size() -> (usize, usize) {
+    let size = stty(&["size"]).ok().and_then(|size| {
+        let mut split = size.split_whitespace().map(str::parse);
+        match (split.next(), split.next()) {
+            (Some(Ok(rows)), Some(Ok(cols))) if rows > 0 && cols > 0 => {
+                Some((cols, rows))
+            }
+            _ => None,
+        }
+    });
+    size.unwrap_or((80, 24))
+}
+
+/// The terminal in non-canonical mode on the alternate screen, restored when
+/// dropped.
+struct Terminal {
+    saved: String,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "min", "1", "time", "0"])?;
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush()?;
+        Ok(Terminal { saved })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[self.saved.as_str()]);
+    }
+}
+
+fn interactive(animation: &Animation) -> io::Result<()> {
+    let _terminal = Terminal::enter()?;
+
+    // Reads keys in the background, so frames keep playing while waiting.
+    // The thread ends at the first key pressed after the viewer is closed.
+    let mut tty = File::open("/dev/tty")?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+        while let Ok(n) = tty.read(&mut buffer) {
+            if n == 0 || sender.send(buffer[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let frame_size = |(cols, rows): (usize, usize)| (cols, rows.max(2) - 1);
+    let mut viewer = Viewer::new(animation, frame_size(terminal_size()));
+    loop {
+        let mut screen = String::from("\x1b[H");
+        for line in viewer.draw(true) {
+            screen.push_str(&line);
+            screen.push_str("\x1b[K\r\n");
+        }
+        screen.push_str("\x1b[J");
+        screen.push_str(&viewer.status());
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(screen.as_bytes())?;
+        stdout.flush()?;
+
+        let timeout = if viewer.playing() {
+            viewer.delay()
+        } else {
+            MAX_DELAY
+        };
+        match receiver.recv_timeout(timeout) {
+            Ok(bytes) => {
+                viewer.resize(frame_size(terminal_size()));
+                for key in keys(&bytes) {
+                    if !viewer.press(key) {
+                        return Ok(());
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => viewer.tick(),
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use render::Palette;
+
+    static PALETTE: Palette =
+        Palette(&[('.', Rgb(0, 0, 0)), ('#', Rgb(255, 255, 255))]);
+
+    fn animation() -> Animation {
+        Animation {
+            frames: vec![
+                Image::from_text("#..\n.#.\n..#", &PALETTE),
+                Image::from_text("...\n...\n..#", &PALETTE),
+            ],
+            delay: Duration::from_millis(100),
+        }
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            super::keys(b" n\x1b[A\x1b[Cx+q"),
+            vec![
+                Key::Pause,
+                Key::Next,
+                Key::Scroll(0, -1),
+                Key::Scroll(1, 0),
+                Key::Faster,
+                Key::Quit
+            ]
+        );
+        assert_eq!(super::keys(b"\x1b"), vec![Key::Quit]);
+    }
+
+    #[test]
+    fn viewer() {
+        let animation = animation();
+        let mut viewer = Viewer::new(&animation, (2, 2));
+        assert_eq!(viewer.draw(false), vec!["#.", ".#"]);
+
+        viewer.press(Key::Scroll(5, 1));
+        assert_eq!(viewer.origin(), (1, 1));
+        assert_eq!(viewer.draw(false), vec!["#.", ".#"]);
+
+        viewer.press(Key::Slower);
+        assert_eq!(viewer.delay(), Duration::from_millis(200));
+
+        viewer.tick();
+        assert_eq!(viewer.frame(), 1);
+        assert!(!viewer.playing());
+        viewer.tick();
+        assert_eq!(viewer.frame(), 1);
+
+        viewer.press(Key::Previous);
+        assert_eq!(viewer.frame(), 0);
+        viewer.press(Key::Last);
+        viewer.press(Key::Pause);
+        assert_eq!((viewer.frame(), viewer.playing()), (0, true));
+        assert!(!viewer.press(Key::Quit));
+
+        viewer.resize((3, 3));
+        assert_eq!(viewer.origin(), (0, 0));
+        assert!(viewer.status().starts_with("fra"));
+    }
+
+    #[test]
+    fn plain() {
+        let mut out = Vec::new();
+        super::plain(&animation(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Frame 1/2\n#..\n.#.\n..#\nFrame 2/2\n...\n...\n..#\n"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Runs every day against its inputs and compares the answers with those
+//! recorded in tests/answers.txt, reporting which days pass and which fail.
+//!
+//! Day numbers can be given to only check those days, e.g.
+//! `cargo test --test answers -- 15 22`.
+extern crate advent_of_code_2018;
+
+use advent_of_code_2018::days;
+use advent_of_code_2018::input::Input;
+use advent_of_code_2018::solution::{Answers, Part};
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+/// An input with its expected answers.
+struct Case {
+    day: u32,
+    input: String,
+    params: Vec<(String, String)>,
+    expected: Answers,
+}
+
+/// Parse an answer column: `-` for a skipped part, with `\n` escaping line
+/// breaks.
+fn parse_answer(answer: &str) -> Option<String> {
+    match answer {
+        "-" => None,
+        answer => Some(answer.replace("\\n", "\n")),
+    }
+}
+
+fn parse_case(idx: usize, line: &str) -> Result<Case, String> {
+    let columns = line.split('|').map(str::trim).collect::<Vec<_>>();
+    let (input, params, part1, part2) = match columns[..] {
+        [input, params, part1, part2] => (input, params, part1, part2),
+        _ => {
+            return Err(format!(
+                "answers.txt:{}: expected four columns",
+                idx + 1
+            ))
+        }
+    };
+
+    let day = input
+        .get(8..10)
+        .filter(|_| input.starts_with("december"))
+        .and_then(|day| day.parse::<u32>().ok())
+        .ok_or_else(|| {
+            format!("answers.txt:{}: expected decemberNN", idx + 1)
+        })?;
+
+    let params = params
+        .split_whitespace()
+        .map(|param| {
+            let mut split = param.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(name), Some(value)) => {
+                    Ok((name.to_owned(), value.to_owned()))
+                }
+                _ => {
+                    Err(format!("answers.txt:{}: expected name=value", idx + 1))
+                }
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(Case {
+        day,
+        input: input.to_owned(),
+        params,
+        expected: Answers {
+            part1: parse_answer(part1),
+            part2: parse_answer(part2),
+            ..Answers::default()
+        },
+    })
+}
+
+fn parse_cases(text: &str) -> Result<Vec<Case>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(idx, line)| parse_case(idx, line))
+        .collect()
+}
+
+/// Trailing whitespace on the lines of ASCII art answers is not significant.
+fn normalize(answer: &Option<String>) -> Option<String> {
+    answer.as_ref().map(|answer| {
+        answer
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim_end()
+            .to_owned()
+    })
+}
+
+/// Solve the parts that have an expected answer, returning a description of
+/// the failure if the answers differ.
+fn check(case: &Case) -> Result<(), String> {
+    let part = match (&case.expected.part1, &case.expected.part2) {
+        (Some(_), Some(_)) => Part::Both,
+        (Some(_), None) => Part::One,
+        (None, Some(_)) => Part::Two,
+        (None, None) => return Ok(()),
+    };
+
+    let solver =
+        days::solver(case.day).ok_or("there is no puzzle on this day")?;
+    let input = Input::from_path(format!("input/{}", case.input))
+        .map_err(|err| err.to_string())?;
+    let (answers, _) = solver(&input, part, &case.params)
+        .map_err(|err| err.in_file(&case.input).to_string())?;
+
+    let mut failures = Vec::new();
+    for (n, expected, actual) in &[
+        (1, &case.expected.part1, &answers.part1),
+        (2, &case.expected.part2, &answers.part2),
+    ] {
+        if normalize(expected) != normalize(actual) {
+            failures.push(format!(
+                "part {}: expected {:?}, got {:?}",
+                n,
+                normalize(expected).unwrap_or_default(),
+                normalize(actual).unwrap_or_default()
+            ));
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+fn main() {
+    // Skip the flags that `cargo test` passes to every test binary.
+    let only = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| arg.parse::<u32>().expect("expected day numbers"))
+        .collect::<BTreeSet<_>>();
+
+    let text = include_str!("answers.txt");
+    let cases = match parse_cases(text) {
+        Ok(cases) => cases,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let mut failed = Vec::new();
+    let mut passed = 0;
+    for day in
+        (1..=days::DAYS).filter(|day| only.is_empty() || only.contains(day))
+    {
+        let day_cases = cases
+            .iter()
+            .filter(|case| case.day == day)
+            .collect::<Vec<_>>();
+        let real_input = days::input_path(day);
+        if !day_cases
+            .iter()
+            .any(|case| real_input.ends_with(&case.input))
+        {
+            println!("day {:>2} {:<20} FAILED: no expected answers", day, "");
+            failed.push(day);
+            continue;
+        }
+
+        for case in day_cases {
+            let start = Instant::now();
+            match check(case) {
+                Ok(()) => {
+                    println!(
+                        "day {:>2} {:<20} ok ({:.3}s)",
+                        day,
+                        case.input,
+                        start.elapsed().as_secs_f64()
+                    );
+                    passed += 1;
+                }
+                Err(err) => {
+                    println!(
+                        "day {:>2} {:<20} FAILED: {}",
+                        day, case.input, err
+                    );
+                    failed.push(day);
+                }
+            }
+        }
+    }
+
+    println!();
+    println!("{} passed; {} failed", passed, failed.len());
+    if !failed.is_empty() {
+        failed.dedup();
+        println!(
+            "failing days: {}",
+            failed
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        std::process::exit(1);
+    }
+}
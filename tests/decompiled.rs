@@ -0,0 +1,46 @@
+//! Compiles the Rust functions that `aoc decompile` made of the ElfCode
+//! programs of days 19 and 21, and checks that they halt with the same
+//! registers as the machine does.
+extern crate advent_of_code_2018;
+
+#[path = "../input/december19decompiled.rs"]
+mod december19;
+#[path = "../input/december21decompiled.rs"]
+mod december21;
+
+use advent_of_code_2018::days;
+use advent_of_code_2018::elfcode::{Machine, Program};
+use advent_of_code_2018::input::Input;
+
+fn program(day: u32) -> Program<i64> {
+    let input = Input::for_day(day, None).unwrap();
+    days::program(day).unwrap()(&input.lines()).unwrap()
+}
+
+/// The registers the machine halts with, starting from `r`.
+fn run(program: &Program<i64>, r: [i64; 6]) -> [i64; 6] {
+    let mut machine = Machine::for_program(program, 6).unwrap();
+    machine.registers.copy_from_slice(&r);
+    machine.run(&program.instrs).unwrap();
+    let mut registers = [0; 6];
+    registers.copy_from_slice(&machine.registers);
+    registers
+}
+
+#[test]
+fn december19() {
+    let program = program(19);
+    let r = [0; 6];
+    assert_eq!(december19::run(r), run(&program, r));
+}
+
+#[test]
+fn december21() {
+    let program = program(21);
+    let compared =
+        advent_of_code_2018::december21::compared(&program, 0, 2).unwrap();
+    for &r0 in &compared {
+        let r = [r0, 0, 0, 0, 0, 0];
+        assert_eq!(december21::run(r), run(&program, r));
+    }
+}
@@ -0,0 +1,100 @@
+//! Starts `aoc-server` on a free loopback port and talks HTTP to it.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+
+/// A running server, stopped when dropped.
+struct Server {
+    child: Child,
+    address: String,
+}
+
+impl Server {
+    fn start() -> Server {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_aoc-server"))
+            .args(["--address", "127.0.0.1:0"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("the server starts");
+        let mut line = String::new();
+        BufReader::new(child.stdout.as_mut().expect("a piped stdout"))
+            .read_line(&mut line)
+            .expect("the server prints its address");
+        let address = line
+            .trim()
+            .strip_prefix("Listening on http://")
+            .expect("the server prints its address")
+            .to_owned();
+        Server { child, address }
+    }
+
+    /// Send a request, returning the status and body of the response.
+    fn request(&self, method: &str, path: &str, body: &str) -> (u16, Vec<u8>) {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            self.address,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let end = response
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .expect("the end of the headers");
+        let head = String::from_utf8_lossy(&response[..end]).into_owned();
+        let status = head[9..12].parse().unwrap();
+        (status, response[end + 4..].to_vec())
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn solves() {
+    let server = Server::start();
+    let input = "Step C must be finished before step A can begin.\n\
+                 Step C must be finished before step F can begin.\n\
+                 Step A must be finished before step B can begin.\n\
+                 Step A must be finished before step D can begin.\n\
+                 Step B must be finished before step E can begin.\n\
+                 Step D must be finished before step E can begin.\n\
+                 Step F must be finished before step E can begin.\n";
+    let (status, body) = server.request(
+        "POST",
+        "/day/7/part/both?workers=2&base_duration=0",
+        input,
+    );
+    let body = String::from_utf8(body).unwrap();
+    assert_eq!(status, 200, "{}", body);
+    assert!(body.starts_with(r#"{"day":7,"#));
+    assert!(body.contains(r#""part":1,"answer":"CABDFE""#));
+    assert!(body.contains(r#""part":2,"answer":"15""#));
+
+    let (status, body) = server.request("POST", "/day/1/part/1", "+1\nx\n");
+    assert_eq!(status, 400);
+    assert!(String::from_utf8(body).unwrap().contains("<body>:2:1"));
+    assert_eq!(server.request("GET", "/day/1/part/1", "").0, 405);
+    assert_eq!(server.request("POST", "/nowhere", "").0, 404);
+}
+
+#[test]
+fn renders() {
+    let server = Server::start();
+    let (status, body) =
+        server.request("POST", "/day/18/render?scale=3", ".#|\n|..\n");
+    assert_eq!(status, 200);
+    assert!(body.starts_with(b"\x89PNG\r\n\x1a\n"));
+    assert_eq!(server.request("POST", "/day/1/render", "+1").0, 404);
+}